@@ -0,0 +1,732 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use anyhow::{Context, Result};
+use orchard::{
+    keys::FullViewingKey as OrchardFullViewingKey,
+    note_encryption::OrchardDomain,
+};
+use sapling_crypto::{
+    keys::PreparedIncomingViewingKey,
+    note_encryption::{Zip212Enforcement, try_sapling_note_decryption},
+    zip32::DiversifiableFullViewingKey,
+};
+use serde_json::{Value, json};
+use zcash_keys::address::Address as DecodedAddress;
+use zcash_note_encryption::try_note_decryption;
+use zcash_primitives::transaction::Transaction as RawTransaction;
+use zcash_protocol::consensus::BranchId;
+use zcash_transparent::address::TransparentAddress as DecodedTransparentAddress;
+use zewif::{Account, BlockHeight, ProtocolAddress, TxId, Zewif};
+use zewif_zcashd::ZcashdWallet;
+use zip32::Scope;
+
+use crate::{
+    keys::{WalletKeys, sapling_ivk, sapling_spending_key},
+    sprout::is_sprout_address,
+};
+
+/// A Zcash value pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pool {
+    Transparent,
+    Sprout,
+    Sapling,
+    Orchard,
+}
+
+impl Pool {
+    /// Whether the pool is shielded, i.e. counted as "private" by
+    /// `z_gettotalbalance`.
+    pub fn is_shielded(&self) -> bool { !matches!(self, Pool::Transparent) }
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Pool::Transparent => "transparent",
+            Pool::Sprout => "sprout",
+            Pool::Sapling => "sapling",
+            Pool::Orchard => "orchard",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Formats a zatoshi amount as ZEC with eight decimal places, as `zcashd`
+/// does.
+pub fn format_zec(zatoshis: u64) -> String {
+    format!("{}.{:08}", zatoshis / 100_000_000, zatoshis % 100_000_000)
+}
+
+/// A confirmed/unconfirmed balance in zatoshis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolBalance {
+    confirmed: u64,
+    unconfirmed: u64,
+}
+
+impl PoolBalance {
+    pub fn confirmed(&self) -> u64 { self.confirmed }
+
+    pub fn unconfirmed(&self) -> u64 { self.unconfirmed }
+
+    pub fn total(&self) -> u64 { self.confirmed + self.unconfirmed }
+
+    fn add(&mut self, other: PoolBalance) {
+        self.confirmed += other.confirmed;
+        self.unconfirmed += other.unconfirmed;
+    }
+}
+
+/// The nullifiers of received Sapling notes, by the transaction and output
+/// index that created them.
+///
+/// A Sapling nullifier depends on the note's position in the commitment
+/// tree, which raw transactions do not record, so nullifiers are taken from
+/// the source wallet where it keeps them.
+#[derive(Debug, Clone, Default)]
+pub struct SaplingNullifiers {
+    nullifiers: HashMap<(TxId, u32), [u8; 32]>,
+}
+
+impl SaplingNullifiers {
+    pub fn new() -> Self { Self::default() }
+
+    /// The nullifiers zcashd computed for the Sapling notes it holds the
+    /// spending key for.
+    pub fn from_zcashd(wallet: &ZcashdWallet) -> Self {
+        let mut nullifiers = Self::new();
+        for (txid, tx) in wallet.transactions() {
+            let Some(note_data) = tx.sapling_note_data() else {
+                continue;
+            };
+            for (outpoint, note) in note_data {
+                if let Some(nullifier) = note
+                    .nullifier()
+                    .and_then(|nf| <[u8; 32]>::try_from(nf.as_ref()).ok())
+                {
+                    nullifiers.insert(*txid, outpoint.n(), nullifier);
+                }
+            }
+        }
+        nullifiers
+    }
+
+    pub fn insert(
+        &mut self,
+        txid: TxId,
+        output_index: u32,
+        nullifier: [u8; 32],
+    ) {
+        self.nullifiers.insert((txid, output_index), nullifier);
+    }
+
+    pub fn get(&self, txid: &TxId, output_index: u32) -> Option<&[u8; 32]> {
+        self.nullifiers.get(&(*txid, output_index))
+    }
+
+    pub fn len(&self) -> usize { self.nullifiers.len() }
+
+    pub fn is_empty(&self) -> bool { self.nullifiers.is_empty() }
+
+    /// Adds the nullifiers of `other`, such as those of another merged input.
+    pub fn extend(&mut self, other: SaplingNullifiers) {
+        self.nullifiers.extend(other.nullifiers);
+    }
+}
+
+/// A note or transparent output received by an account.
+#[derive(Debug, Clone)]
+pub struct ReceivedNote {
+    pool: Pool,
+    txid: TxId,
+    index: u32,
+    value: u64,
+    mined_height: Option<BlockHeight>,
+    spent: Option<bool>,
+    spent_by: Option<TxId>,
+}
+
+impl ReceivedNote {
+    pub fn pool(&self) -> Pool { self.pool }
+
+    pub fn txid(&self) -> &TxId { &self.txid }
+
+    /// The output or action index within the transaction.
+    pub fn index(&self) -> u32 { self.index }
+
+    /// The value in zatoshis.
+    pub fn value(&self) -> u64 { self.value }
+
+    pub fn mined_height(&self) -> Option<BlockHeight> { self.mined_height }
+
+    /// Whether a known wallet transaction spends this note, or `None` if that
+    /// cannot be determined: for Sapling notes whose nullifier the source
+    /// wallet did not supply (see [`SaplingNullifiers`]).
+    pub fn spent(&self) -> Option<bool> { self.spent }
+
+    /// The wallet transaction that spends this note, if known.
+    pub fn spent_by(&self) -> Option<&TxId> { self.spent_by.as_ref() }
+}
+
+/// The balances and note inventory of one account.
+#[derive(Debug, Clone)]
+pub struct AccountBalance {
+    wallet_index: usize,
+    account_index: usize,
+    name: String,
+    notes: Vec<ReceivedNote>,
+    sprout_addresses: usize,
+    undecodable_transactions: Vec<TxId>,
+    balances: BTreeMap<Pool, PoolBalance>,
+}
+
+impl AccountBalance {
+    pub fn wallet_index(&self) -> usize { self.wallet_index }
+
+    pub fn account_index(&self) -> usize { self.account_index }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Every note and transparent output received by the account.
+    pub fn notes(&self) -> &[ReceivedNote] { &self.notes }
+
+    /// Received notes whose spent status is unknown. They are counted as
+    /// unspent, so the balance may be overstated by their value.
+    pub fn unknown_spent_notes(&self) -> impl Iterator<Item = &ReceivedNote> {
+        self.notes.iter().filter(|note| note.spent.is_none())
+    }
+
+    /// The number of Sprout addresses of the account. Sprout balances are
+    /// not computed, so any funds they hold are missing from the balance.
+    pub fn sprout_addresses(&self) -> usize { self.sprout_addresses }
+
+    /// Relevant transactions that have no raw data or could not be parsed,
+    /// and so are missing from the balance.
+    pub fn undecodable_transactions(&self) -> &[TxId] {
+        &self.undecodable_transactions
+    }
+
+    /// The balance of each pool in which the account holds funds.
+    pub fn balances(&self) -> &BTreeMap<Pool, PoolBalance> { &self.balances }
+
+    /// The sum of all pool balances satisfying `filter`.
+    pub fn total(&self, filter: impl Fn(Pool) -> bool) -> PoolBalance {
+        let mut total = PoolBalance::default();
+        for (pool, balance) in &self.balances {
+            if filter(*pool) {
+                total.add(*balance);
+            }
+        }
+        total
+    }
+}
+
+/// Per-account, per-pool balances for every account in a `Zewif`.
+#[derive(Debug, Clone)]
+pub struct BalanceReport {
+    tip_height: Option<BlockHeight>,
+    min_confirmations: u32,
+    accounts: Vec<AccountBalance>,
+}
+
+impl BalanceReport {
+    /// The highest mined height of any transaction, used as the chain tip
+    /// when counting confirmations.
+    pub fn tip_height(&self) -> Option<BlockHeight> { self.tip_height }
+
+    pub fn min_confirmations(&self) -> u32 { self.min_confirmations }
+
+    pub fn accounts(&self) -> &[AccountBalance] { &self.accounts }
+
+    /// The wallet-wide totals in the shape returned by `zcashd`'s
+    /// `z_gettotalbalance`, counting confirmed funds only.
+    pub fn to_total_balance_json(&self) -> Value {
+        let transparent: u64 = self
+            .accounts
+            .iter()
+            .map(|a| a.total(|p| !p.is_shielded()).confirmed())
+            .sum();
+        let private: u64 = self
+            .accounts
+            .iter()
+            .map(|a| a.total(|p| p.is_shielded()).confirmed())
+            .sum();
+        json!({
+            "transparent": format_zec(transparent),
+            "private": format_zec(private),
+            "total": format_zec(transparent + private),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let accounts: Vec<Value> = self
+            .accounts
+            .iter()
+            .map(|account| {
+                let pools: serde_json::Map<String, Value> = account
+                    .balances
+                    .iter()
+                    .map(|(pool, balance)| {
+                        (
+                            pool.to_string(),
+                            json!({
+                                "confirmed": format_zec(balance.confirmed()),
+                                "unconfirmed": format_zec(balance.unconfirmed()),
+                            }),
+                        )
+                    })
+                    .collect();
+                json!({
+                    "wallet": account.wallet_index,
+                    "account": account.account_index,
+                    "name": account.name,
+                    "pools": pools,
+                    "notes": account.notes.len(),
+                    "unknown_spent_notes": account.unknown_spent_notes().count(),
+                    "sprout": if account.sprout_addresses > 0 {
+                        json!("unsupported")
+                    } else {
+                        Value::Null
+                    },
+                    "undecodable_transactions": account
+                        .undecodable_transactions
+                        .iter()
+                        .map(|txid| txid.to_string())
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        json!({
+            "tip_height": self.tip_height.map(u32::from),
+            "min_confirmations": self.min_confirmations,
+            "total": self.to_total_balance_json(),
+            "accounts": accounts,
+        })
+    }
+}
+
+impl fmt::Display for BalanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Balance Report")?;
+        match self.tip_height {
+            Some(height) => writeln!(
+                f,
+                "- Tip height: {} (minimum confirmations: {})",
+                u32::from(height),
+                self.min_confirmations
+            )?,
+            None => writeln!(f, "- Tip height: unknown")?,
+        }
+        for account in &self.accounts {
+            writeln!(f, "---")?;
+            writeln!(
+                f,
+                "Wallet {} account {} ({})",
+                account.wallet_index, account.account_index, account.name
+            )?;
+            writeln!(
+                f,
+                "{:<12} | {:>20} | {:>20}",
+                "Pool", "Confirmed", "Unconfirmed"
+            )?;
+            writeln!(f, "{:-<12}-+-{:->20}-+-{:->20}", "", "", "")?;
+            for (pool, balance) in &account.balances {
+                writeln!(
+                    f,
+                    "{:<12} | {:>20} | {:>20}",
+                    pool.to_string(),
+                    format_zec(balance.confirmed()),
+                    format_zec(balance.unconfirmed())
+                )?;
+            }
+            writeln!(f, "- Notes received: {}", account.notes.len())?;
+            let unknown = account.unknown_spent_notes().count();
+            if unknown > 0 {
+                writeln!(
+                    f,
+                    "⚠️ {} note(s) of unknown spent status are counted as unspent",
+                    unknown
+                )?;
+            }
+            if account.sprout_addresses > 0 {
+                writeln!(
+                    f,
+                    "⚠️ {} Sprout address(es): Sprout balances are not computed",
+                    account.sprout_addresses
+                )?;
+            }
+            if !account.undecodable_transactions.is_empty() {
+                writeln!(
+                    f,
+                    "🛑 {} transaction(s) could not be decoded and are not counted",
+                    account.undecodable_transactions.len()
+                )?;
+            }
+        }
+        writeln!(f, "---")?;
+        let total = self.to_total_balance_json();
+        writeln!(f, "Total (confirmed, as z_gettotalbalance):")?;
+        writeln!(
+            f,
+            "- transparent: {}",
+            total["transparent"].as_str().unwrap()
+        )?;
+        writeln!(f, "- private: {}", total["private"].as_str().unwrap())?;
+        write!(f, "- total: {}", total["total"].as_str().unwrap())
+    }
+}
+
+/// Computes per-account, per-pool balances for every account in `zewif` by
+/// decrypting the notes in each account's transactions and leaving out those
+/// spent by wallet transactions.
+///
+/// Funds mined at least `min_confirmations` blocks below the highest known
+/// transaction height are counted as confirmed; funds in unmined
+/// transactions are unconfirmed.
+///
+/// Without Sapling nullifiers the spent status of Sapling notes is unknown;
+/// see [`compute_balances_with_nullifiers`].
+pub fn compute_balances(
+    zewif: &Zewif,
+    min_confirmations: u32,
+) -> Result<BalanceReport> {
+    compute_balances_with_nullifiers(
+        zewif,
+        &SaplingNullifiers::new(),
+        min_confirmations,
+    )
+}
+
+/// Computes balances as [`compute_balances`] does, telling which Sapling
+/// notes are spent from their nullifiers in `sapling_nullifiers`.
+pub fn compute_balances_with_nullifiers(
+    zewif: &Zewif,
+    sapling_nullifiers: &SaplingNullifiers,
+    min_confirmations: u32,
+) -> Result<BalanceReport> {
+    let transactions = parse_transactions(zewif);
+    let tip_height = zewif
+        .transactions()
+        .values()
+        .filter_map(|tx| tx.mined_height())
+        .max();
+
+    // Spends are collected over every transaction in the container, since
+    // an account's funds may be spent by a transaction assigned elsewhere.
+    let mut spent_outpoints = HashMap::new();
+    let mut spent_sapling_nullifiers = HashMap::new();
+    let mut spent_orchard_nullifiers = HashMap::new();
+    for (txid, tx) in transactions
+        .iter()
+        .filter_map(|(txid, tx)| tx.as_ref().map(|tx| (txid, tx)))
+    {
+        if let Some(bundle) = tx.transparent_bundle() {
+            for txin in &bundle.vin {
+                let prevout = txin.prevout();
+                spent_outpoints
+                    .insert((*prevout.hash(), prevout.n()), txid.clone());
+            }
+        }
+        if let Some(bundle) = tx.sapling_bundle() {
+            for spend in bundle.shielded_spends() {
+                spent_sapling_nullifiers
+                    .insert(spend.nullifier().0, txid.clone());
+            }
+        }
+        if let Some(bundle) = tx.orchard_bundle() {
+            for action in bundle.actions() {
+                spent_orchard_nullifiers
+                    .insert(*action.nullifier(), txid.clone());
+            }
+        }
+    }
+    let spends = Spends {
+        spent_outpoints,
+        sapling_nullifiers,
+        spent_sapling_nullifiers,
+        spent_orchard_nullifiers,
+    };
+
+    let mut accounts = Vec::new();
+    for (wallet_index, wallet) in zewif.wallets().iter().enumerate() {
+        let keys = WalletKeys::new(wallet).with_context(|| {
+            format!("Preparing key material for wallet {}", wallet_index)
+        })?;
+        for (account_index, account) in wallet.accounts().iter().enumerate() {
+            let viewer =
+                AccountViewer::new(&keys, account).with_context(|| {
+                    format!(
+                        "Deriving keys for wallet {} account {}",
+                        wallet_index, account_index
+                    )
+                })?;
+            let mut balance = AccountBalance {
+                wallet_index,
+                account_index,
+                name: account.name().to_string(),
+                notes: Vec::new(),
+                sprout_addresses: account
+                    .addresses()
+                    .iter()
+                    .filter(|a| is_sprout_address(&a.as_string()))
+                    .count(),
+                undecodable_transactions: Vec::new(),
+                balances: BTreeMap::new(),
+            };
+            let mut txids: Vec<_> =
+                account.relevant_transactions().iter().collect();
+            txids.sort();
+            for txid in txids {
+                let mined_height = zewif
+                    .transactions()
+                    .get(txid)
+                    .and_then(|tx| tx.mined_height());
+                match transactions.get(txid) {
+                    Some(Some(tx)) => viewer.scan(
+                        txid,
+                        tx,
+                        mined_height,
+                        &spends,
+                        &mut balance,
+                    ),
+                    _ => balance.undecodable_transactions.push(txid.clone()),
+                }
+            }
+            tally(&mut balance, tip_height, min_confirmations);
+            accounts.push(balance);
+        }
+    }
+
+    Ok(BalanceReport {
+        tip_height,
+        min_confirmations,
+        accounts,
+    })
+}
+
+/// Parses the raw data of every transaction, mapping transactions without
+/// raw data or that fail to parse to `None`.
+fn parse_transactions(zewif: &Zewif) -> BTreeMap<TxId, Option<RawTransaction>> {
+    zewif
+        .transactions()
+        .iter()
+        .map(|(txid, tx)| {
+            // The consensus branch ID only affects signature hashing, which
+            // is not needed here; v5 transactions carry their own.
+            let parsed = tx.raw().and_then(|raw| {
+                RawTransaction::read(raw.as_ref(), BranchId::Nu5).ok()
+            });
+            (txid.clone(), parsed)
+        })
+        .collect()
+}
+
+fn tally(
+    balance: &mut AccountBalance,
+    tip_height: Option<BlockHeight>,
+    min_confirmations: u32,
+) {
+    let is_confirmed = |height: Option<BlockHeight>| match (height, tip_height)
+    {
+        (Some(height), Some(tip)) => {
+            let confirmations =
+                (u32::from(tip) + 1).saturating_sub(u32::from(height));
+            confirmations >= min_confirmations
+        }
+        _ => false,
+    };
+    for note in &balance.notes {
+        if note.spent == Some(true) {
+            continue;
+        }
+        let pool_balance = balance.balances.entry(note.pool).or_default();
+        if is_confirmed(note.mined_height) {
+            pool_balance.confirmed += note.value;
+        } else {
+            pool_balance.unconfirmed += note.value;
+        }
+    }
+}
+
+/// Spends made by transactions anywhere in the container, mapped to the
+/// transaction making them.
+struct Spends<'a> {
+    spent_outpoints: HashMap<([u8; 32], u32), TxId>,
+    sapling_nullifiers: &'a SaplingNullifiers,
+    spent_sapling_nullifiers: HashMap<[u8; 32], TxId>,
+    spent_orchard_nullifiers: HashMap<orchard::note::Nullifier, TxId>,
+}
+
+/// The viewing keys of one account.
+struct AccountViewer {
+    transparent: HashSet<DecodedTransparentAddress>,
+    sapling_ivks: Vec<PreparedIncomingViewingKey>,
+    orchard_fvks: Vec<OrchardFullViewingKey>,
+}
+
+impl AccountViewer {
+    fn new(keys: &WalletKeys, account: &Account) -> Result<Self> {
+        let mut viewer = AccountViewer {
+            transparent: HashSet::new(),
+            sapling_ivks: Vec::new(),
+            orchard_fvks: Vec::new(),
+        };
+        if let Some(ufvk) = keys.account_ufvk(account)? {
+            if let Some(dfvk) = ufvk.sapling() {
+                viewer.add_sapling_fvk(dfvk);
+            }
+            if let Some(fvk) = ufvk.orchard() {
+                viewer.orchard_fvks.push(fvk.clone());
+            }
+        }
+        for address in account.addresses() {
+            match address.address() {
+                ProtocolAddress::Transparent(transparent) => {
+                    if let Ok(DecodedAddress::Transparent(decoded)) =
+                        keys.decode_address(transparent.address())
+                    {
+                        viewer.transparent.insert(decoded);
+                    }
+                }
+                ProtocolAddress::Shielded(shielded) => {
+                    if let Some(xsk) =
+                        shielded.spending_key().and_then(sapling_spending_key)
+                    {
+                        viewer.add_sapling_fvk(
+                            &xsk.to_diversifiable_full_viewing_key(),
+                        );
+                    } else if let Some(ivk) = shielded
+                        .incoming_viewing_key()
+                        .and_then(|ivk| sapling_ivk(ivk.as_ref()))
+                    {
+                        viewer
+                            .sapling_ivks
+                            .push(PreparedIncomingViewingKey::new(&ivk));
+                    }
+                }
+                ProtocolAddress::Unified(unified) => {
+                    if let Ok(DecodedAddress::Unified(decoded)) =
+                        keys.decode_address(unified.address())
+                        && let Some(transparent) = decoded.transparent()
+                    {
+                        viewer.transparent.insert(*transparent);
+                    }
+                }
+            }
+        }
+        Ok(viewer)
+    }
+
+    fn add_sapling_fvk(&mut self, dfvk: &DiversifiableFullViewingKey) {
+        for scope in [Scope::External, Scope::Internal] {
+            self.sapling_ivks
+                .push(PreparedIncomingViewingKey::new(&dfvk.to_ivk(scope)));
+        }
+    }
+
+    fn scan(
+        &self,
+        txid: &TxId,
+        tx: &RawTransaction,
+        mined_height: Option<BlockHeight>,
+        spends: &Spends<'_>,
+        balance: &mut AccountBalance,
+    ) {
+        let mut notes = Vec::new();
+        // `spent_by` is `None` when the spent status is unknown, and
+        // `Some(None)` when the note is known to be unspent.
+        let mut receive =
+            |pool, index, value, spent_by: Option<Option<&TxId>>| {
+                notes.push(ReceivedNote {
+                    pool,
+                    txid: txid.clone(),
+                    index,
+                    value,
+                    mined_height,
+                    spent: spent_by.map(|spender| spender.is_some()),
+                    spent_by: spent_by.flatten().cloned(),
+                });
+            };
+
+        if let Some(bundle) = tx.transparent_bundle() {
+            let raw_txid = *tx.txid().as_ref();
+            for (index, txout) in bundle.vout.iter().enumerate() {
+                let index = index as u32;
+                if txout
+                    .recipient_address()
+                    .is_some_and(|a| self.transparent.contains(&a))
+                {
+                    let spent_by =
+                        spends.spent_outpoints.get(&(raw_txid, index));
+                    receive(
+                        Pool::Transparent,
+                        index,
+                        u64::from(txout.value()),
+                        Some(spent_by),
+                    );
+                }
+            }
+        }
+
+        if let Some(bundle) = tx.sapling_bundle() {
+            for (index, output) in bundle.shielded_outputs().iter().enumerate()
+            {
+                let index = index as u32;
+                let received = self.sapling_ivks.iter().find_map(|ivk| {
+                    try_sapling_note_decryption(
+                        ivk,
+                        output,
+                        Zip212Enforcement::GracePeriod,
+                    )
+                });
+                if let Some((note, _, _)) = received {
+                    let spent_by = spends
+                        .sapling_nullifiers
+                        .get(txid, index)
+                        .map(|nf| spends.spent_sapling_nullifiers.get(nf));
+                    receive(
+                        Pool::Sapling,
+                        index,
+                        note.value().inner(),
+                        spent_by,
+                    );
+                }
+            }
+        }
+
+        if let Some(bundle) = tx.orchard_bundle() {
+            for (index, action) in bundle.actions().iter().enumerate() {
+                let domain = OrchardDomain::for_action(action);
+                for fvk in &self.orchard_fvks {
+                    let received = [Scope::External, Scope::Internal]
+                        .into_iter()
+                        .find_map(|scope| {
+                            let ivk =
+                                orchard::keys::PreparedIncomingViewingKey::new(
+                                    &fvk.to_ivk(scope),
+                                );
+                            try_note_decryption(&domain, &ivk, action)
+                        });
+                    if let Some((note, _, _)) = received {
+                        let spent_by = spends
+                            .spent_orchard_nullifiers
+                            .get(&note.nullifier(fvk));
+                        receive(
+                            Pool::Orchard,
+                            index as u32,
+                            note.value().inner(),
+                            Some(spent_by),
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        balance.notes.extend(notes);
+    }
+}
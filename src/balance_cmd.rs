@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{balance, file_args::FileArgsLike, input_args::InputArgs};
+
+/// Compute per-account, per-pool balances for a wallet
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    input_args: InputArgs,
+
+    /// Minimum confirmations for funds to count as confirmed
+    #[arg(long, default_value_t = 1)]
+    min_confirmations: u32,
+
+    /// Output as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf { self.input_args.file() }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        let outcome = self.input_args.read()?;
+        let report = balance::compute_balances_with_nullifiers(
+            outcome.zewif(),
+            outcome.sapling_nullifiers(),
+            self.min_confirmations,
+        )?;
+        if self.json {
+            Ok(serde_json::to_string_pretty(&report.to_json())?)
+        } else {
+            Ok(report.to_string())
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use bc_envelope::prelude::*;
+use zcash_protocol::consensus::{self, NetworkUpgrade, Parameters};
+use zewif::{BlockHeight, Network, Zewif};
+use zewif_zcashd::ZcashdDump;
+
+use crate::{metadata, network};
+
+/// Keynames of zcashd records whose values begin with a `CKeyMetadata`: a
+/// 32-bit version followed by the key's 64-bit creation time.
+const KEY_METADATA_RECORDS: [&str; 2] = ["keymeta", "sapzkeymeta"];
+
+/// Keynames of zcashd records whose values begin with a `CHDChain`: a
+/// 32-bit version and the 32-byte seed fingerprint, followed by the seed's
+/// 64-bit creation time.
+const HD_CHAIN_RECORDS: [&str; 2] = ["hdchain", "mnemonichdchain"];
+
+/// A mainnet block whose time is known: Blossom activation, from which
+/// the target block spacing is 75 seconds, on 2019-12-11.
+const ANCHOR_HEIGHT: u32 = 653_600;
+const ANCHOR_TIME: i64 = 1_576_065_000;
+
+/// Block spacings assumed when estimating heights after and before the
+/// anchor. Each errs towards an earlier height: 80 rather than 75 seconds
+/// after it, and 140 rather than 150 seconds before it.
+const SPACING_AFTER_ANCHOR: i64 = 80;
+const SPACING_BEFORE_ANCHOR: i64 = 140;
+
+/// How far, in blocks, an estimate is moved back to allow for clock skew:
+/// about a week.
+const SAFETY_MARGIN: i64 = 8_064;
+
+/// The earliest key or HD seed creation time recorded in a zcashd wallet,
+/// in seconds since the Unix epoch.
+///
+/// zcashd records a time of 0 for keys of unknown age and 1 for keys
+/// imported without one, so those are ignored.
+pub fn earliest_key_time(dump: &ZcashdDump) -> Option<i64> {
+    dump.records()
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = value.as_ref();
+            let offset = if KEY_METADATA_RECORDS.contains(&key.keyname.as_str())
+            {
+                4
+            } else if HD_CHAIN_RECORDS.contains(&key.keyname.as_str()) {
+                36
+            } else {
+                return None;
+            };
+            let bytes = value.get(offset..offset + 8)?;
+            Some(i64::from_le_bytes(bytes.try_into().unwrap()))
+        })
+        .filter(|&time| time > 1)
+        .min()
+}
+
+/// A conservative estimate of the height of the chain at `time`, in seconds
+/// since the Unix epoch: one that errs towards an earlier height, and is
+/// never below Sapling activation.
+///
+/// Only mainnet block times are predictable enough to estimate from, so
+/// this is `None` for other networks.
+pub fn height_at_time(network: Network, time: i64) -> Option<BlockHeight> {
+    if network != Network::Main {
+        return None;
+    }
+    let elapsed = time - ANCHOR_TIME;
+    let blocks = if elapsed >= 0 {
+        elapsed / SPACING_AFTER_ANCHOR
+    } else {
+        elapsed / SPACING_BEFORE_ANCHOR
+    };
+    let sapling = consensus::MAIN_NETWORK
+        .activation_height(NetworkUpgrade::Sapling)
+        .map_or(0, u32::from);
+    let height =
+        (ANCHOR_HEIGHT as i64 + blocks - SAFETY_MARGIN).max(sapling as i64);
+    Some(BlockHeight::from(height as u32))
+}
+
+/// Gives every account of `zewif` without a birthday a conservative one:
+/// the earliest of the mined height of its first transaction and the height
+/// estimated from `key_time`, the wallet's earliest key creation time.
+/// Accounts with neither keep no birthday. The estimated heights are
+/// recorded in the metadata as `birthday-estimate`, so they can be told
+/// apart from birthdays a source recorded.
+///
+/// The network is worked out from the wallets' stored networks and address
+/// prefixes, and `key_time` is used only on mainnet (see [`height_at_time`]),
+/// so testnet and regtest accounts get the height of their first
+/// transaction alone.
+///
+/// Only the zcashd reader estimates birthdays, as zcashd records none; the
+/// other readers keep whatever birthday their source supplied.
+pub fn estimate_birthdays(zewif: &mut Zewif, key_time: Option<i64>) {
+    let key_height = match network::detect_network(zewif) {
+        Ok(Some(network)) => {
+            key_time.and_then(|time| height_at_time(network, time))
+        }
+        // Without a known network, key times can't be placed on a chain.
+        _ => None,
+    };
+    let estimates: Vec<Vec<Option<BlockHeight>>> = zewif
+        .wallets()
+        .iter()
+        .map(|wallet| {
+            wallet
+                .accounts()
+                .iter()
+                .map(|account| {
+                    account
+                        .relevant_transactions()
+                        .iter()
+                        .filter_map(|txid| zewif.transactions().get(txid))
+                        .filter_map(|tx| tx.mined_height())
+                        .chain(key_height)
+                        .min()
+                })
+                .collect()
+        })
+        .collect();
+    let mut estimated = BTreeSet::new();
+    for (wallet, estimates) in zewif.wallets_mut().into_iter().zip(estimates) {
+        for (account, estimate) in
+            wallet.accounts_mut().into_iter().zip(estimates)
+        {
+            if account.birthday_height().is_none() {
+                account.set_birthday_height(estimate);
+                estimated.extend(estimate.map(u32::from));
+            }
+        }
+    }
+    if !estimated.is_empty() {
+        let mut payload = Envelope::new("birthdayEstimate");
+        for height in estimated {
+            payload = payload.add_assertion("height", height);
+        }
+        metadata::record(zewif, "birthday-estimate", payload);
+    }
+}
+
+/// Whether a birthday of `height` in `zewif` was estimated by
+/// [`estimate_birthdays`] rather than recorded by the source or set with
+/// [`override_birthday`].
+pub fn is_estimated(zewif: &Zewif, height: BlockHeight) -> Result<bool> {
+    let heights = |kind| -> Result<BTreeSet<u32>> {
+        let mut heights = BTreeSet::new();
+        for payload in metadata::payloads(zewif, kind)? {
+            for height in payload.objects_for_predicate("height") {
+                heights.insert(height.extract_subject::<u32>()?);
+            }
+        }
+        Ok(heights)
+    };
+    let height = u32::from(height);
+    Ok(heights("birthday-estimate")?.contains(&height)
+        && !heights("birthday-override")?.contains(&height))
+}
+
+/// Sets the birthday of every account of `zewif` to `height`, recording the
+/// override in the metadata as `birthday-override`.
+pub fn override_birthday(zewif: &mut Zewif, height: BlockHeight) {
+    for wallet in zewif.wallets_mut() {
+        for account in wallet.accounts_mut() {
+            account.set_birthday_height(Some(height));
+        }
+    }
+    let payload = Envelope::new("birthdayOverride")
+        .add_assertion("height", u32::from(height));
+    metadata::record(zewif, "birthday-override", payload);
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::Args;
+use rpassword::prompt_password;
+
+use crate::{
+    file_args::FileArgsLike,
+    filter_args::FilterArgs,
+    format::Registry,
+    migrator::{Migrator, OutputSink},
+    network::NetworkName,
+    progress::Progress,
+};
+
+/// Migrate several wallets and merge them into one
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    /// An input wallet file; repeat to merge several. Files named `*.zewif`
+    /// are read as Zewif, the rest in the `--from` format
+    #[arg(long = "input", value_name = "PATH", required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Format of the inputs that aren't Zewif files
+    #[arg(
+        long,
+        default_value = "zcashd",
+        value_parser = Registry::builtin().migrating_reader_parser()
+    )]
+    from: String,
+
+    /// Output format
+    #[arg(
+        long,
+        default_value = "zewif",
+        value_parser = Registry::builtin().writer_parser()
+    )]
+    to: String,
+
+    /// Compress the output
+    #[arg(long)]
+    compress: bool,
+
+    /// Encrypt the output
+    #[arg(long)]
+    encrypt: bool,
+
+    /// Write a zingo wallet without the input's transactions, for zingo to
+    /// rebuild by rescanning from the wallet's birthday
+    #[arg(long)]
+    rescan: bool,
+
+    /// Check that every address can be re-derived from the wallet's keys,
+    /// failing if any cannot
+    #[arg(long)]
+    verify: bool,
+
+    /// Leave Sprout addresses out of zcashd migrations, recording their
+    /// exclusion in the output's metadata
+    #[arg(long)]
+    drop_sprout: bool,
+
+    /// Abort unless every input wallet belongs to this network
+    #[arg(long, value_enum)]
+    expect_network: Option<NetworkName>,
+
+    /// Fail if a zingo wallet has bytes left over after parsing, rather than
+    /// reporting a partial success
+    #[arg(long)]
+    strict: bool,
+
+    #[command(flatten)]
+    filter_args: FilterArgs,
+
+    /// Set the birthday height of every account, overriding the one read
+    /// from or estimated for the wallets
+    #[arg(long, value_name = "HEIGHT")]
+    birthday: Option<u32>,
+
+    /// Output file path, or `-` for stdout
+    output_file: String,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf { &self.inputs[0] }
+}
+
+impl CommandArgs {
+    /// Migrates and merges the inputs, and writes the result.
+    pub fn run(&self, progress: &Progress) -> Result<()> {
+        let output = match self.output_file.as_str() {
+            "-" => OutputSink::Stdout,
+            path => OutputSink::File(PathBuf::from(path)),
+        };
+        let mut inputs = self.inputs.iter();
+        let first = inputs.next().expect("at least one input is required");
+        let mut migrator = inputs.fold(
+            Migrator::new(self.input_format(first), first),
+            |migrator, input| migrator.input(self.input_format(input), input),
+        );
+        migrator = migrator
+            .to(&self.to)
+            .output(output)
+            .compress(self.compress)
+            .rescan(self.rescan)
+            .verify(self.verify)
+            .drop_sprout(self.drop_sprout)
+            .expect_network(self.expect_network)
+            .strict(self.strict)
+            .filter(self.filter_args.filter())
+            .birthday(self.birthday)
+            .decrypt_with(|| {
+                Ok(prompt_password("Enter decryption password: ")?)
+            })
+            .on_report({
+                let progress = progress.clone();
+                move |report| progress.report(report)
+            });
+        if self.encrypt {
+            migrator = migrator.encrypt_with(|| {
+                Ok(prompt_password("Enter encryption password: ")?)
+            });
+        }
+        migrator.run(progress)?;
+        Ok(())
+    }
+
+    fn input_format(&self, input: &Path) -> &str {
+        if input.extension().is_some_and(|ext| ext == "zewif") {
+            "zewif"
+        } else {
+            &self.from
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    fmt::{self, Write as _},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use serde_json::{Value, json};
+
+/// Exit code for failures that have no more specific classification.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Failures that can occur while reading a Zewif file, or for
+/// [`ZewifInputError::WrongPassword`], any encrypted wallet.
+///
+/// Each variant maps to a distinct process exit code (see
+/// [`ZewifInputError::exit_code`]) so that scripts driving `zmigrate` can
+/// branch on the class of failure without parsing the error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZewifInputError {
+    /// The input is not valid CBOR.
+    InvalidCbor(String),
+
+    /// The input is valid CBOR but is not a Zewif envelope.
+    NotZewif(String),
+
+    /// The input is encrypted and the supplied password does not decrypt it.
+    /// Decryption is authenticated, so damaged ciphertext is reported this
+    /// way too. Also used for ZecWallet Lite wallets whose seed doesn't
+    /// decrypt.
+    WrongPassword,
+
+    /// The envelope does not uncompress: its contents are damaged.
+    Corrupt(String),
+
+    /// The input declares a Zewif format version this build cannot read.
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    /// The envelope could not be decoded into a `Zewif`. `path` locates the
+    /// innermost element that failed, e.g. `["zewif", "wallet[0]",
+    /// "account[2]"]`.
+    Decode { path: Vec<String>, message: String },
+}
+
+impl ZewifInputError {
+    /// The process exit code that corresponds to this failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ZewifInputError::InvalidCbor(_) => 10,
+            ZewifInputError::NotZewif(_) => 11,
+            ZewifInputError::WrongPassword => 12,
+            ZewifInputError::UnsupportedVersion { .. } => 13,
+            ZewifInputError::Decode { .. } => 14,
+            ZewifInputError::Corrupt(_) => 15,
+        }
+    }
+
+    /// A stable identifier for this failure in machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZewifInputError::InvalidCbor(_) => "invalid_cbor",
+            ZewifInputError::NotZewif(_) => "not_zewif",
+            ZewifInputError::WrongPassword => "wrong_password",
+            ZewifInputError::UnsupportedVersion { .. } => "unsupported_version",
+            ZewifInputError::Decode { .. } => "decode",
+            ZewifInputError::Corrupt(_) => "corrupt",
+        }
+    }
+}
+
+impl fmt::Display for ZewifInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZewifInputError::InvalidCbor(message) => {
+                write!(f, "Input is not valid CBOR: {}", message)
+            }
+            ZewifInputError::NotZewif(message) => {
+                write!(f, "Input is not a Zewif envelope: {}", message)
+            }
+            ZewifInputError::WrongPassword => {
+                write!(f, "Wrong password: unable to decrypt the wallet")
+            }
+            ZewifInputError::Corrupt(message) => {
+                write!(f, "Zewif envelope is corrupt: {}", message)
+            }
+            ZewifInputError::UnsupportedVersion { found, supported } => {
+                write!(
+                    f,
+                    "Unsupported Zewif version {} (this build supports version {})",
+                    found, supported
+                )
+            }
+            ZewifInputError::Decode { path, message } => {
+                write!(
+                    f,
+                    "Could not decode Zewif at {}: {}",
+                    path.join("/"),
+                    message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ZewifInputError {}
+
+fn input_error(error: &anyhow::Error) -> Option<&ZewifInputError> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ZewifInputError>())
+}
+
+/// Returns the process exit code for an error, using the most specific typed
+/// error found anywhere in its cause chain.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    input_error(error)
+        .map(ZewifInputError::exit_code)
+        .unwrap_or(EXIT_FAILURE)
+}
+
+/// Returns a stable identifier for an error, using the most specific typed
+/// error found anywhere in its cause chain.
+pub fn error_code(error: &anyhow::Error) -> &'static str {
+    input_error(error)
+        .map(ZewifInputError::code)
+        .unwrap_or("failure")
+}
+
+/// How a fatal error is reported on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// A message followed by its causes
+    Human,
+
+    /// A single JSON object
+    Json,
+}
+
+/// Formats an error and its causes for a person to read. With `styled`, the
+/// message is decorated with an emoji and color.
+pub fn format_error(error: &anyhow::Error, styled: bool) -> String {
+    let mut output = String::from("---\n");
+    if styled {
+        writeln!(output, "🔴 {} {}\n", "Error:".red().bold(), error).unwrap();
+    } else {
+        writeln!(output, "Error: {}\n", error).unwrap();
+    }
+    for cause in error.chain().skip(1) {
+        writeln!(output, "Caused by: {}", cause).unwrap();
+    }
+    output
+}
+
+/// Describes an error as a JSON object with its code, exit code, message and
+/// causes, and the pipeline stage and input file involved, if known.
+pub fn error_json(
+    error: &anyhow::Error,
+    stage: Option<&str>,
+    input: Option<&Path>,
+) -> Value {
+    let causes: Vec<String> = error
+        .chain()
+        .skip(1)
+        .map(|cause| cause.to_string())
+        .collect();
+    json!({
+        "code": error_code(error),
+        "exit_code": exit_code(error),
+        "message": error.to_string(),
+        "causes": causes,
+        "stage": stage,
+        "input": input.map(|path| path.display().to_string()),
+    })
+}
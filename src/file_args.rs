@@ -1,6 +1,10 @@
-use std::{io::Read, path::PathBuf};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use zewif::Data;
 
@@ -19,3 +23,26 @@ pub trait FileArgsLike {
 pub struct FileArgs {
     pub file: PathBuf,
 }
+
+/// Creates a new file for possibly secret material that only the current
+/// user can read, failing if the file already exists.
+pub fn create_secret_file(path: &Path) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))
+}
+
+/// Writes possibly secret material to a new file that only the current user
+/// can read.
+pub fn write_secret_file(path: &Path, contents: &str) -> Result<()> {
+    let mut file = create_secret_file(path)?;
+    writeln!(file, "{}", contents)?;
+    Ok(())
+}
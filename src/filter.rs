@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, bail};
+use bc_envelope::prelude::*;
+use zewif::{BlockHeight, Transaction, TxId, Zewif, ZewifWallet};
+
+use crate::{
+    balance::{self, SaplingNullifiers},
+    keys::WalletKeys,
+    metadata,
+    split::account_matches,
+};
+
+/// Filters that trim the transaction history of a migrated wallet.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    since_height: Option<u32>,
+    until_height: Option<u32>,
+    account: Option<String>,
+    exclude_spent: bool,
+    no_raw_tx: bool,
+}
+
+impl TransactionFilter {
+    pub fn new() -> Self { Self::default() }
+
+    /// Drops transactions mined below this height.
+    pub fn with_since_height(mut self, height: Option<u32>) -> Self {
+        self.since_height = height;
+        self
+    }
+
+    /// Drops transactions mined above this height, and unmined ones.
+    pub fn with_until_height(mut self, height: Option<u32>) -> Self {
+        self.until_height = height;
+        self
+    }
+
+    /// Keeps only the accounts matching this index within their wallet,
+    /// name, or unified full viewing key, and the transactions they
+    /// reference.
+    pub fn with_account(mut self, account: Option<String>) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Drops transactions whose received notes are all known to be spent,
+    /// keeping those that spend wallet notes.
+    pub fn with_exclude_spent(mut self, exclude_spent: bool) -> Self {
+        self.exclude_spent = exclude_spent;
+        self
+    }
+
+    /// Drops the raw bytes of the transactions kept.
+    pub fn with_no_raw_tx(mut self, no_raw_tx: bool) -> Self {
+        self.no_raw_tx = no_raw_tx;
+        self
+    }
+
+    pub fn since_height(&self) -> Option<u32> { self.since_height }
+
+    pub fn until_height(&self) -> Option<u32> { self.until_height }
+
+    pub fn account(&self) -> Option<&str> { self.account.as_deref() }
+
+    pub fn exclude_spent(&self) -> bool { self.exclude_spent }
+
+    pub fn no_raw_tx(&self) -> bool { self.no_raw_tx }
+
+    /// Whether no filter is set.
+    pub fn is_empty(&self) -> bool {
+        self.since_height.is_none()
+            && self.until_height.is_none()
+            && self.account.is_none()
+            && !self.exclude_spent
+            && !self.no_raw_tx
+    }
+
+    /// Applies the filters to `zewif`, recording them in the metadata as
+    /// `transaction-filter` so importers know the history is partial.
+    ///
+    /// Accounts keep listing the ids of the transactions that were dropped.
+    ///
+    /// Without Sapling nullifiers the spent status of Sapling notes is
+    /// unknown, so `exclude_spent` keeps every transaction receiving them;
+    /// see [`apply_with_nullifiers`](Self::apply_with_nullifiers).
+    pub fn apply(&self, zewif: &Zewif) -> Result<Zewif> {
+        self.apply_with_nullifiers(zewif, &SaplingNullifiers::new())
+    }
+
+    /// Applies the filters as [`apply`](Self::apply) does, telling which
+    /// Sapling notes are spent from their nullifiers in `sapling_nullifiers`.
+    pub fn apply_with_nullifiers(
+        &self,
+        zewif: &Zewif,
+        sapling_nullifiers: &SaplingNullifiers,
+    ) -> Result<Zewif> {
+        let mut filtered = Zewif::new();
+        metadata::copy(zewif, &mut filtered)?;
+        let wallets = match &self.account {
+            Some(selector) => select_accounts(zewif, selector)?,
+            None => zewif.wallets().to_vec(),
+        };
+        let referenced: Option<HashSet<TxId>> =
+            self.account.as_ref().map(|_| {
+                wallets
+                    .iter()
+                    .flat_map(|w| w.accounts())
+                    .flat_map(|a| a.relevant_transactions())
+                    .copied()
+                    .collect()
+            });
+        let spent = if self.exclude_spent {
+            spent_transactions(zewif, sapling_nullifiers)?
+        } else {
+            HashSet::new()
+        };
+
+        let mut removed = 0u64;
+        for (txid, tx) in zewif.transactions() {
+            let keep = referenced.as_ref().is_none_or(|r| r.contains(txid))
+                && !spent.contains(txid)
+                && self.in_height_range(tx.mined_height());
+            if !keep {
+                removed += 1;
+            } else if self.no_raw_tx {
+                filtered.add_transaction(*txid, without_raw(tx)?);
+            } else {
+                filtered.add_transaction(*txid, tx.clone());
+            }
+        }
+        for wallet in wallets {
+            filtered.add_wallet(wallet);
+        }
+
+        let mut payload = Envelope::new("transactionFilter")
+            .add_assertion("removedTransactions", removed)
+            .add_assertion("excludeSpent", self.exclude_spent)
+            .add_assertion("rawTransactionsRemoved", self.no_raw_tx);
+        if let Some(height) = self.since_height {
+            payload = payload.add_assertion("sinceHeight", height);
+        }
+        if let Some(height) = self.until_height {
+            payload = payload.add_assertion("untilHeight", height);
+        }
+        if let Some(account) = &self.account {
+            payload = payload.add_assertion("account", account.as_str());
+        }
+        metadata::record(&mut filtered, "transaction-filter", payload);
+        Ok(filtered)
+    }
+
+    fn in_height_range(&self, height: Option<BlockHeight>) -> bool {
+        match height.map(u32::from) {
+            Some(height) => {
+                self.since_height.is_none_or(|since| height >= since)
+                    && self.until_height.is_none_or(|until| height <= until)
+            }
+            // Unmined transactions come after every mined one.
+            None => self.until_height.is_none(),
+        }
+    }
+}
+
+/// The wallets of `zewif` with only their accounts matching `selector`,
+/// leaving out wallets with none.
+fn select_accounts(zewif: &Zewif, selector: &str) -> Result<Vec<ZewifWallet>> {
+    let mut wallets = Vec::new();
+    for wallet in zewif.wallets() {
+        let keys = WalletKeys::new(wallet)?;
+        let mut selected = ZewifWallet::new(wallet.network());
+        if let Some(seed_material) = wallet.seed_material() {
+            selected.set_seed_material(seed_material.clone());
+        }
+        for (index, account) in wallet.accounts().iter().enumerate() {
+            if account_matches(&keys, index, account, selector)? {
+                selected.add_account(account.clone());
+            }
+        }
+        if !selected.accounts().is_empty() {
+            wallets.push(selected);
+        }
+    }
+    if wallets.is_empty() {
+        bail!("No account matches {}", selector);
+    }
+    Ok(wallets)
+}
+
+/// The transactions all of whose notes received by the wallet's accounts
+/// are known to be spent, leaving out those that spend wallet notes so the
+/// history still shows where the funds went.
+fn spent_transactions(
+    zewif: &Zewif,
+    sapling_nullifiers: &SaplingNullifiers,
+) -> Result<HashSet<TxId>> {
+    let report = balance::compute_balances_with_nullifiers(
+        zewif,
+        sapling_nullifiers,
+        1,
+    )?;
+    let notes: Vec<_> =
+        report.accounts().iter().flat_map(|a| a.notes()).collect();
+    let mut received = HashSet::new();
+    let mut keep = HashSet::new();
+    for note in &notes {
+        received.insert(*note.txid());
+        if note.spent() != Some(true) {
+            keep.insert(*note.txid());
+        }
+        if let Some(spender) = note.spent_by() {
+            keep.insert(*spender);
+        }
+    }
+    Ok(received.difference(&keep).copied().collect())
+}
+
+/// A copy of `tx` without its raw bytes.
+fn without_raw(tx: &Transaction) -> Result<Transaction> {
+    let envelope = Envelope::from(tx.clone());
+    let envelope = match envelope.optional_assertion_with_predicate("raw")? {
+        Some(raw) => envelope.remove_assertion(raw),
+        None => envelope,
+    };
+    Ok(Transaction::try_from(envelope)?)
+}
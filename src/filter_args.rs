@@ -0,0 +1,40 @@
+use clap::Args;
+
+use crate::filter::TransactionFilter;
+
+/// Filters on the transaction history written
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct FilterArgs {
+    /// Leave out transactions mined below this height
+    #[arg(long, value_name = "HEIGHT")]
+    since_height: Option<u32>,
+
+    /// Leave out transactions mined above this height, and unmined ones
+    #[arg(long, value_name = "HEIGHT")]
+    until_height: Option<u32>,
+
+    /// Keep only the accounts with this index within their wallet, name, or
+    /// unified full viewing key, and their transactions
+    #[arg(long)]
+    account: Option<String>,
+
+    /// Leave out transactions whose received notes are all spent
+    #[arg(long)]
+    exclude_spent: bool,
+
+    /// Leave out the raw bytes of transactions
+    #[arg(long)]
+    no_raw_tx: bool,
+}
+
+impl FilterArgs {
+    pub fn filter(&self) -> TransactionFilter {
+        TransactionFilter::new()
+            .with_since_height(self.since_height)
+            .with_until_height(self.until_height)
+            .with_account(self.account.clone())
+            .with_exclude_spent(self.exclude_spent)
+            .with_no_raw_tx(self.no_raw_tx)
+    }
+}
@@ -0,0 +1,415 @@
+use std::fmt;
+
+use anyhow::{Result, bail};
+use bc_envelope::Envelope;
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use zewif::Zewif;
+
+use crate::{
+    balance::SaplingNullifiers, inspect::Inspection, network::NetworkName,
+    progress::Progress, source::WalletSource, sprout::SproutReport, zcashd_cmd,
+    zewif_cmd,
+};
+
+/// Supplies a password when one is needed.
+pub type PasswordFn = Box<dyn FnOnce() -> Result<String>>;
+
+/// Options for reading a wallet. Each reader uses only those that apply to
+/// its format.
+#[derive(Default)]
+pub struct ReadOptions {
+    drop_sprout: bool,
+    strict: bool,
+    expect_network: Option<NetworkName>,
+    password: Option<PasswordFn>,
+    allow_disk: bool,
+}
+
+impl ReadOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Leave Sprout addresses out of the migrated wallet.
+    pub fn with_drop_sprout(mut self, drop_sprout: bool) -> Self {
+        self.drop_sprout = drop_sprout;
+        self
+    }
+
+    /// Fail rather than tolerate data the reader can't parse.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Fail unless the wallet belongs to this network. Readers that return
+    /// a `Zewif` needn't check this themselves.
+    pub fn with_expect_network(mut self, network: Option<NetworkName>) -> Self {
+        self.expect_network = network;
+        self
+    }
+
+    /// The password for an encrypted input, only called if it is needed.
+    pub fn with_password(mut self, password: Option<PasswordFn>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Let a wallet in memory be written to a temporary file for a reader
+    /// that needs a file, on systems without anonymous in-memory files; see
+    /// [`with_memory_file`](crate::source::with_memory_file).
+    pub fn with_allow_disk(mut self, allow_disk: bool) -> Self {
+        self.allow_disk = allow_disk;
+        self
+    }
+
+    pub fn drop_sprout(&self) -> bool { self.drop_sprout }
+
+    pub fn strict(&self) -> bool { self.strict }
+
+    pub fn expect_network(&self) -> Option<NetworkName> { self.expect_network }
+
+    pub fn allow_disk(&self) -> bool { self.allow_disk }
+
+    /// Takes the password callback, failing if none was given.
+    pub fn take_password(&mut self) -> Result<PasswordFn> {
+        match self.password.take() {
+            Some(password) => Ok(password),
+            None => bail!("Input is encrypted, but no password was given"),
+        }
+    }
+}
+
+/// Options for writing a wallet. Each writer uses only those that apply to
+/// its format.
+#[derive(Default)]
+pub struct WriteOptions {
+    compress: bool,
+    password: Option<PasswordFn>,
+    rescan: bool,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn with_compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Encrypt the output with a password, only called once the output is
+    /// ready to be encrypted.
+    pub fn with_password(mut self, password: Option<PasswordFn>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Write wallets whose format has no place for the input's
+    /// transactions without them, to be rebuilt by rescanning the chain.
+    pub fn with_rescan(mut self, rescan: bool) -> Self {
+        self.rescan = rescan;
+        self
+    }
+
+    pub fn compress(&self) -> bool { self.compress }
+
+    pub fn rescan(&self) -> bool { self.rescan }
+
+    pub fn encrypt(&self) -> bool { self.password.is_some() }
+
+    /// Takes the encryption password callback, if any.
+    pub fn take_password(&mut self) -> Option<PasswordFn> {
+        self.password.take()
+    }
+}
+
+/// A wallet read by a [`WalletReader`].
+#[derive(Debug)]
+pub struct ReadOutcome {
+    zewif: Zewif,
+    sprout_report: Option<SproutReport>,
+    sapling_nullifiers: SaplingNullifiers,
+}
+
+impl ReadOutcome {
+    pub fn new(zewif: Zewif) -> Self {
+        Self {
+            zewif,
+            sprout_report: None,
+            sapling_nullifiers: SaplingNullifiers::new(),
+        }
+    }
+
+    pub fn with_sprout_report(mut self, report: SproutReport) -> Self {
+        self.sprout_report = Some(report);
+        self
+    }
+
+    /// The nullifiers of received Sapling notes, for wallets that keep them.
+    pub fn with_sapling_nullifiers(
+        mut self,
+        nullifiers: SaplingNullifiers,
+    ) -> Self {
+        self.sapling_nullifiers = nullifiers;
+        self
+    }
+
+    pub fn zewif(&self) -> &Zewif { &self.zewif }
+
+    pub fn sprout_report(&self) -> Option<&SproutReport> {
+        self.sprout_report.as_ref()
+    }
+
+    pub fn sapling_nullifiers(&self) -> &SaplingNullifiers {
+        &self.sapling_nullifiers
+    }
+
+    pub fn into_parts(self) -> (Zewif, Option<SproutReport>) {
+        (self.zewif, self.sprout_report)
+    }
+}
+
+/// A wallet format that can be migrated from.
+pub trait WalletReader {
+    /// The name the format is selected by, as in `--from zcashd`.
+    fn name(&self) -> &'static str;
+
+    /// A one-line description of the format for `--help`.
+    fn help(&self) -> &'static str;
+
+    /// Whether the reader honors [`ReadOptions::drop_sprout`].
+    fn drops_sprout(&self) -> bool { false }
+
+    /// Whether [`WalletReader::read`] can migrate the format, rather than
+    /// only dump or inspect it.
+    fn migrates(&self) -> bool { true }
+
+    /// Migrates the wallet to Zewif.
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome>;
+
+    /// A debug dump of the wallet as parsed, for formats that can be parsed
+    /// but not yet migrated. `None` if the migrated wallet should be dumped
+    /// instead.
+    fn dump(
+        &self,
+        _input: &WalletSource,
+        _options: &mut ReadOptions,
+    ) -> Option<Result<String>> {
+        None
+    }
+
+    /// A compact overview of the wallet, for `inspect`. By default that of
+    /// the migrated wallet; readers override this to add what their format
+    /// records, or to inspect wallets they can't migrate.
+    fn inspect(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Result<Inspection> {
+        let outcome = self.read(input, options, &Progress::quiet())?;
+        Ok(Inspection::of_zewif(self.name(), outcome.zewif()))
+    }
+
+    /// The opened (decrypted and uncompressed) envelope, for formats stored
+    /// as Zewif envelopes, so that it can be written again without being
+    /// decoded. `None` for other formats.
+    fn envelope(
+        &self,
+        _input: &WalletSource,
+        _options: &mut ReadOptions,
+        _progress: &Progress,
+    ) -> Option<Result<Envelope>> {
+        None
+    }
+}
+
+/// A format that migrated wallets can be written in.
+pub trait WalletWriter {
+    /// The name the format is selected by, as in `--to zewif`.
+    fn name(&self) -> &'static str;
+
+    /// A one-line description of the format for `--help`.
+    fn help(&self) -> &'static str;
+
+    /// Encodes the wallet.
+    fn write(
+        &self,
+        zewif: &Zewif,
+        options: &mut WriteOptions,
+        progress: &Progress,
+    ) -> Result<Vec<u8>>;
+
+    /// Whether the writer accepts the envelopes of
+    /// [`WalletReader::envelope`].
+    fn writes_envelopes(&self) -> bool { false }
+
+    /// Encodes a Zewif envelope as read, without decoding it.
+    fn write_envelope(
+        &self,
+        _envelope: Envelope,
+        _options: &mut WriteOptions,
+        _progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        bail!("{} output needs a decoded wallet", self.name())
+    }
+}
+
+/// Writes the migrated wallet as a Rust `Debug` dump.
+pub struct DumpWriter;
+
+impl WalletWriter for DumpWriter {
+    fn name(&self) -> &'static str { "dump" }
+
+    fn help(&self) -> &'static str { "Output as Debug dump" }
+
+    fn write(
+        &self,
+        zewif: &Zewif,
+        options: &mut WriteOptions,
+        _progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        ensure_plain_dump(options)?;
+        Ok(format!("{:#?}\n", zewif).into_bytes())
+    }
+}
+
+/// Fails if a dump was asked to be compressed or encrypted.
+pub fn ensure_plain_dump(options: &WriteOptions) -> Result<()> {
+    if options.compress() || options.encrypt() {
+        bail!("Dumps cannot be compressed or encrypted");
+    }
+    Ok(())
+}
+
+/// The wallet formats available for migration, by name.
+pub struct Registry {
+    readers: Vec<Box<dyn WalletReader>>,
+    writers: Vec<Box<dyn WalletWriter>>,
+}
+
+impl Registry {
+    /// A registry with no formats.
+    pub fn empty() -> Self {
+        Self {
+            readers: Vec::new(),
+            writers: Vec::new(),
+        }
+    }
+
+    /// A registry with every format built into this crate, depending on the
+    /// enabled features.
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register_reader(zcashd_cmd::ZcashdReader);
+        #[cfg(feature = "zingo")]
+        registry.register_reader(crate::zingo_cmd::ZingoReader);
+        #[cfg(feature = "zecwallet")]
+        registry.register_reader(crate::zecwallet_cmd::ZecwalletReader);
+        #[cfg(feature = "ywallet")]
+        registry.register_reader(crate::ywallet_cmd::YwalletReader);
+        #[cfg(feature = "sqlite")]
+        registry.register_reader(crate::sqlite_cmd::SqliteReader);
+        registry.register_reader(zewif_cmd::ZewifReader);
+        for writer in zewif_cmd::EnvelopeWriter::ALL {
+            registry.register_writer(writer);
+        }
+        #[cfg(feature = "zingo")]
+        registry.register_writer(crate::zingo_cmd::ZingoWriter);
+        registry.register_writer(DumpWriter);
+        registry
+    }
+
+    /// Adds a reader, replacing any with the same name.
+    pub fn register_reader(&mut self, reader: impl WalletReader + 'static) {
+        self.readers.retain(|r| r.name() != reader.name());
+        self.readers.push(Box::new(reader));
+    }
+
+    /// Adds a writer, replacing any with the same name.
+    pub fn register_writer(&mut self, writer: impl WalletWriter + 'static) {
+        self.writers.retain(|w| w.name() != writer.name());
+        self.writers.push(Box::new(writer));
+    }
+
+    pub fn readers(&self) -> impl Iterator<Item = &dyn WalletReader> {
+        self.readers.iter().map(|r| r.as_ref())
+    }
+
+    pub fn writers(&self) -> impl Iterator<Item = &dyn WalletWriter> {
+        self.writers.iter().map(|w| w.as_ref())
+    }
+
+    pub fn reader(&self, name: &str) -> Result<&dyn WalletReader> {
+        match self.readers().find(|r| r.name() == name) {
+            Some(reader) => Ok(reader),
+            None => bail!(
+                "Unknown input format: {} (available: {})",
+                name,
+                names(self.readers().map(|r| r.name()))
+            ),
+        }
+    }
+
+    pub fn writer(&self, name: &str) -> Result<&dyn WalletWriter> {
+        match self.writers().find(|w| w.name() == name) {
+            Some(writer) => Ok(writer),
+            None => bail!(
+                "Unknown output format: {} (available: {})",
+                name,
+                names(self.writers().map(|w| w.name()))
+            ),
+        }
+    }
+
+    /// A clap parser accepting the names of the registered readers.
+    pub fn reader_parser(&self) -> PossibleValuesParser {
+        PossibleValuesParser::new(
+            self.readers()
+                .map(|r| PossibleValue::new(r.name()).help(r.help())),
+        )
+    }
+
+    /// A clap parser accepting the names of the registered readers that can
+    /// migrate their format; see [`WalletReader::migrates`].
+    pub fn migrating_reader_parser(&self) -> PossibleValuesParser {
+        PossibleValuesParser::new(
+            self.readers()
+                .filter(|r| r.migrates())
+                .map(|r| PossibleValue::new(r.name()).help(r.help())),
+        )
+    }
+
+    /// A clap parser accepting the names of the registered writers.
+    pub fn writer_parser(&self) -> PossibleValuesParser {
+        PossibleValuesParser::new(
+            self.writers()
+                .map(|w| PossibleValue::new(w.name()).help(w.help())),
+        )
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self { Self::builtin() }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field(
+                "readers",
+                &self.readers().map(|r| r.name()).collect::<Vec<_>>(),
+            )
+            .field(
+                "writers",
+                &self.writers().map(|w| w.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+use rpassword::prompt_password;
+use zewif::Zewif;
+
+use crate::{
+    file_args::FileArgsLike,
+    format::{ReadOptions, ReadOutcome, Registry},
+    progress::Progress,
+    source::WalletSource,
+};
+
+/// An input wallet and its format
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct InputArgs {
+    /// Input format
+    #[arg(long, value_parser = Registry::builtin().migrating_reader_parser())]
+    pub from: String,
+
+    /// Input file path
+    pub file: PathBuf,
+}
+
+impl FileArgsLike for InputArgs {
+    fn file(&self) -> &PathBuf { &self.file }
+}
+
+impl InputArgs {
+    /// Reads the input with its format's reader.
+    pub fn read(&self) -> Result<ReadOutcome> {
+        Registry::builtin().reader(&self.from)?.read(
+            &WalletSource::from(self.file()),
+            &mut prompting_read_options(),
+            &Progress::quiet(),
+        )
+    }
+
+    /// Loads the input as a `Zewif`, migrating it first if it is not already
+    /// in Zewif format.
+    pub fn load_zewif(&self) -> Result<Zewif> {
+        Ok(self.read()?.into_parts().0)
+    }
+}
+
+/// Read options that prompt for a password on the terminal if one is needed.
+pub fn prompting_read_options() -> ReadOptions {
+    ReadOptions::new().with_password(Some(Box::new(|| {
+        Ok(prompt_password("Enter decryption password: ")?)
+    })))
+}
@@ -0,0 +1,317 @@
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use zewif::{BlockHeight, Network, ProtocolAddress, Zewif};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, migrate_to_zewif};
+
+use crate::{
+    format::PasswordFn,
+    network::network_name,
+    sprout::is_sprout_address,
+    zewif_cmd::{self, ZewifFile},
+};
+
+/// Counts of what a wallet contains, once it is available as a `Zewif`.
+#[derive(Debug, Clone, Default)]
+pub struct ContentSummary {
+    wallets: usize,
+    accounts: usize,
+    addresses: BTreeMap<&'static str, usize>,
+    transactions: usize,
+    height_range: Option<(BlockHeight, BlockHeight)>,
+}
+
+impl ContentSummary {
+    pub fn new(zewif: &Zewif) -> Self {
+        let mut summary = Self {
+            wallets: zewif.wallets().len(),
+            transactions: zewif.transactions().len(),
+            ..Default::default()
+        };
+        for account in zewif.wallets().iter().flat_map(|w| w.accounts()) {
+            summary.accounts += 1;
+            for address in account.addresses() {
+                let kind = match address.address() {
+                    ProtocolAddress::Transparent(_) => "transparent",
+                    ProtocolAddress::Shielded(_)
+                        if is_sprout_address(&address.as_string()) =>
+                    {
+                        "sprout"
+                    }
+                    ProtocolAddress::Shielded(_) => "sapling",
+                    ProtocolAddress::Unified(_) => "unified",
+                };
+                *summary.addresses.entry(kind).or_default() += 1;
+            }
+        }
+        let heights = zewif
+            .transactions()
+            .values()
+            .filter_map(|tx| tx.mined_height());
+        summary.height_range =
+            heights.fold(None, |range, height| match range {
+                None => Some((height, height)),
+                Some((low, high)) => Some((low.min(height), high.max(height))),
+            });
+        summary
+    }
+
+    pub fn wallets(&self) -> usize { self.wallets }
+
+    pub fn accounts(&self) -> usize { self.accounts }
+
+    /// Address counts keyed by kind: `transparent`, `sprout`, `sapling` or
+    /// `unified`.
+    pub fn addresses(&self) -> &BTreeMap<&'static str, usize> {
+        &self.addresses
+    }
+
+    pub fn transactions(&self) -> usize { self.transactions }
+
+    /// The lowest and highest mined height of the wallet's transactions.
+    pub fn height_range(&self) -> Option<(BlockHeight, BlockHeight)> {
+        self.height_range
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "wallets": self.wallets,
+            "accounts": self.accounts,
+            "addresses": self.addresses,
+            "transactions": self.transactions,
+            "height_range": self.height_range.map(|(low, high)| json!({
+                "min": u32::from(low),
+                "max": u32::from(high),
+            })),
+        })
+    }
+}
+
+/// A compact overview of a wallet file of any supported format.
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    format: &'static str,
+    version: Option<String>,
+    network: Option<Network>,
+    encrypted: Option<bool>,
+    compressed: Option<bool>,
+    keyname_counts: Option<BTreeMap<String, usize>>,
+    keyname_summary: Option<String>,
+    contents: Option<ContentSummary>,
+}
+
+impl Inspection {
+    /// An inspection of a wallet in the format named `format`, with nothing
+    /// yet known about it.
+    pub fn new(format: &'static str) -> Self {
+        Self {
+            format,
+            version: None,
+            network: None,
+            encrypted: None,
+            compressed: None,
+            keyname_counts: None,
+            keyname_summary: None,
+            contents: None,
+        }
+    }
+
+    /// An inspection of a wallet from its migrated form: the network of its
+    /// first wallet, and its contents.
+    pub fn of_zewif(format: &'static str, zewif: &Zewif) -> Self {
+        let mut inspection = Self::new(format);
+        inspection.network = zewif.wallets().first().map(|w| w.network());
+        inspection.contents = Some(ContentSummary::new(zewif));
+        inspection
+    }
+
+    pub fn with_version(mut self, version: impl ToString) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn with_encrypted(mut self, encrypted: bool) -> Self {
+        self.encrypted = Some(encrypted);
+        self
+    }
+
+    pub fn format(&self) -> &str { self.format }
+
+    /// The wallet's serialization or client version, if the format records
+    /// one.
+    pub fn version(&self) -> Option<&str> { self.version.as_deref() }
+
+    pub fn network(&self) -> Option<Network> { self.network }
+
+    pub fn encrypted(&self) -> Option<bool> { self.encrypted }
+
+    /// Whether a Zewif file is compressed; `None` for other formats.
+    pub fn compressed(&self) -> Option<bool> { self.compressed }
+
+    /// The number of records per keyname in a zcashd wallet.
+    pub fn keyname_counts(&self) -> Option<&BTreeMap<String, usize>> {
+        self.keyname_counts.as_ref()
+    }
+
+    pub fn contents(&self) -> Option<&ContentSummary> { self.contents.as_ref() }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "format": self.format,
+            "version": self.version,
+            "network": self.network.map(network_name),
+            "encrypted": self.encrypted,
+            "compressed": self.compressed,
+            "keynames": self.keyname_counts,
+            "contents": self.contents.as_ref().map(|c| c.to_json()),
+        })
+    }
+}
+
+fn yes_no(flag: bool) -> &'static str { if flag { "yes" } else { "no" } }
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Wallet Inspection")?;
+        writeln!(f, "- Format: {}", self.format)?;
+        writeln!(
+            f,
+            "- Version: {}",
+            self.version.as_deref().unwrap_or("unknown")
+        )?;
+        writeln!(
+            f,
+            "- Network: {}",
+            self.network.map(network_name).unwrap_or("unknown")
+        )?;
+        if let Some(encrypted) = self.encrypted {
+            writeln!(f, "- Encrypted: {}", yes_no(encrypted))?;
+        }
+        if let Some(compressed) = self.compressed {
+            writeln!(f, "- Compressed: {}", yes_no(compressed))?;
+        }
+        if let Some(contents) = &self.contents {
+            writeln!(f, "- Wallets: {}", contents.wallets)?;
+            writeln!(f, "- Accounts: {}", contents.accounts)?;
+            let addresses: Vec<String> = contents
+                .addresses
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .collect();
+            if addresses.is_empty() {
+                writeln!(f, "- Addresses: none")?;
+            } else {
+                writeln!(f, "- Addresses: {}", addresses.join(", "))?;
+            }
+            writeln!(f, "- Transactions: {}", contents.transactions)?;
+            match contents.height_range {
+                Some((low, high)) => writeln!(
+                    f,
+                    "- Heights: {} to {}",
+                    u32::from(low),
+                    u32::from(high)
+                )?,
+                None => writeln!(f, "- Heights: unknown")?,
+            }
+        }
+        if let Some(summary) = &self.keyname_summary {
+            writeln!(f, "- Records:")?;
+            for line in summary.lines().filter(|line| !line.is_empty()) {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Inspects a zcashd `wallet.dat`.
+pub fn inspect_zcashd(file: &Path) -> Result<Inspection> {
+    let db_dump =
+        BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump, true)
+        .context("Parsing Zcashd dump")?;
+
+    let keyname_counts: BTreeMap<String, usize> = zcashd_dump
+        .records_by_keyname()
+        .iter()
+        .map(|(keyname, records)| (keyname.clone(), records.len()))
+        .collect();
+
+    let (zcashd_wallet, _) = ZcashdParser::parse_dump(&zcashd_dump, true)
+        .context("Parsing Zcashd dump")?;
+    let zewif = migrate_to_zewif(&zcashd_wallet, BlockHeight::default())
+        .context("Migrating to Zewif")?;
+
+    let mut inspection = Inspection::new("zcashd");
+    inspection.version = Some(zcashd_wallet.client_version().to_string());
+    inspection.network = Some(zcashd_wallet.network());
+    // zcashd stores its wallet encryption master keys as `mkey` records.
+    inspection.encrypted = Some(keyname_counts.contains_key("mkey"));
+    inspection.keyname_counts = Some(keyname_counts);
+    inspection.keyname_summary = Some(zcashd_dump.keyname_summary());
+    inspection.contents = Some(ContentSummary::new(&zewif));
+    Ok(inspection)
+}
+
+/// Inspects a Zewif file.
+///
+/// Whether the file is encrypted and compressed is read from its outer
+/// envelope. The contents of an encrypted file are only inspected if a
+/// `password` is given, and it is only called then.
+pub fn inspect_zewif(
+    file: &Path,
+    password: Option<PasswordFn>,
+) -> Result<Inspection> {
+    inspect_zewif_file(zewif_cmd::read_zewif_file(file)?, password)
+}
+
+/// Inspects a Zewif file as read; see [`inspect_zewif`].
+pub fn inspect_zewif_file(
+    zewif_file: ZewifFile,
+    password: Option<PasswordFn>,
+) -> Result<Inspection> {
+    let encrypted = zewif_file.is_encrypted();
+    let compressed = zewif_file.is_compressed();
+    let ze = match password {
+        Some(password) => zewif_file.open(password)?,
+        None if encrypted => {
+            let mut inspection =
+                Inspection::new("zewif").with_encrypted(encrypted);
+            inspection.compressed = Some(compressed);
+            return Ok(inspection);
+        }
+        None => zewif_file
+            .open(|| unreachable!("only encrypted files need a password"))?,
+    };
+    let version = zewif_cmd::zewif_version(ze.envelope())?;
+    let zewif = zewif_cmd::decode_zewif(ze.envelope())?;
+    let mut inspection = Inspection::of_zewif("zewif", &zewif)
+        .with_version(version)
+        .with_encrypted(encrypted);
+    inspection.compressed = Some(compressed);
+    Ok(inspection)
+}
+
+/// Inspects a zingo wallet file.
+#[cfg(feature = "zingo")]
+pub fn inspect_zingo(file: &Path) -> Result<Inspection> {
+    Ok(Inspection::new("zingo")
+        .with_version(crate::zingo_cmd::wallet_version(file)?)
+        .with_network(crate::zingo_cmd::wallet_network(file)?))
+}
+
+/// Inspects a ZecWallet Lite wallet file, without unlocking its keys.
+#[cfg(feature = "zecwallet")]
+pub fn inspect_zecwallet(file: &Path) -> Result<Inspection> {
+    let (version, encrypted) = crate::zecwallet_cmd::wallet_version(file)?;
+    Ok(Inspection::new("zecwallet")
+        .with_version(version)
+        .with_network(crate::zecwallet_cmd::wallet_network(file)?)
+        .with_encrypted(encrypted))
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    file_args::FileArgsLike,
+    format::{ReadOptions, Registry},
+    input_args::prompting_read_options,
+    source::WalletSource,
+};
+
+/// Show a compact overview of a wallet
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    /// Input format
+    #[arg(long, value_parser = Registry::builtin().reader_parser())]
+    from: String,
+
+    /// Input file path
+    file: PathBuf,
+
+    /// Output as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Ask for the password of an encrypted Zewif file to show its contents
+    #[arg(long)]
+    decrypt: bool,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf { &self.file }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        let mut options = if self.decrypt {
+            prompting_read_options()
+        } else {
+            ReadOptions::new()
+        };
+        let inspection = Registry::builtin()
+            .reader(&self.from)?
+            .inspect(&WalletSource::from(&self.file), &mut options)?;
+        if self.json {
+            Ok(serde_json::to_string_pretty(&inspection.to_json())?)
+        } else {
+            Ok(inspection.to_string().trim_end().to_string())
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use anyhow::{Result, anyhow};
+use ripemd::Ripemd160;
+use sapling_crypto::{
+    SaplingIvk,
+    keys::DiversifiableFullViewingKey,
+    zip32::{ExtendedFullViewingKey, ExtendedSpendingKey},
+};
+use sha2::{Digest, Sha256};
+use zcash_address::{
+    ZcashAddress,
+    unified::{self, Container, Encoding},
+};
+use zcash_keys::{
+    address::Address as DecodedAddress,
+    encoding::{
+        decode_extended_full_viewing_key, decode_extended_spending_key,
+    },
+    keys::{UnifiedFullViewingKey, UnifiedSpendingKey},
+};
+use zcash_protocol::consensus::{self, NetworkConstants, NetworkType};
+use zcash_transparent::{
+    address::TransparentAddress as DecodedTransparentAddress,
+    keys::{IncomingViewingKey, NonHardenedChildIndex},
+};
+use zewif::{Account, Network, SeedMaterial, SpendingKey, ZewifWallet};
+use zip32::{AccountId, ChildIndex, Scope};
+
+/// Converts a Zewif network into the corresponding address network type.
+pub fn network_type(network: Network) -> NetworkType {
+    match network {
+        Network::Main => NetworkType::Main,
+        Network::Test => NetworkType::Test,
+        Network::Regtest => NetworkType::Regtest,
+    }
+}
+
+/// Returns the raw HD seed bytes for a wallet's seed material.
+pub fn seed_bytes(seed_material: &SeedMaterial) -> Result<Vec<u8>> {
+    match seed_material {
+        SeedMaterial::Bip39Mnemonic(mnemonic) => {
+            Ok(parse_mnemonic(mnemonic.mnemonic())?.seed().to_vec())
+        }
+        SeedMaterial::PreBIP39Seed(seed) => Ok(seed.as_ref().to_vec()),
+    }
+}
+
+/// A BIP-39 mnemonic parsed from its phrase.
+pub struct ParsedMnemonic {
+    language: &'static str,
+    entropy: Vec<u8>,
+    seed: [u8; 64],
+}
+
+impl ParsedMnemonic {
+    /// The name of the word list the phrase is in.
+    pub fn language(&self) -> &'static str { self.language }
+
+    pub fn entropy(&self) -> &[u8] { &self.entropy }
+
+    /// The HD seed, without a passphrase.
+    pub fn seed(&self) -> &[u8; 64] { &self.seed }
+}
+
+/// Parses a BIP-39 mnemonic in whichever supported language its words are
+/// in: zcashd records the language, but not every source does.
+pub fn parse_mnemonic(phrase: &str) -> Result<ParsedMnemonic> {
+    fn parse<L: bip0039::Language>(
+        phrase: &str,
+        language: &'static str,
+    ) -> Option<ParsedMnemonic> {
+        let mnemonic = bip0039::Mnemonic::<L>::from_phrase(phrase).ok()?;
+        Some(ParsedMnemonic {
+            language,
+            entropy: mnemonic.entropy().to_vec(),
+            seed: mnemonic.to_seed(""),
+        })
+    }
+
+    // English first, so its error is the one reported.
+    let english = bip0039::Mnemonic::<bip0039::English>::from_phrase(phrase);
+    if let Ok(mnemonic) = english.as_ref() {
+        return Ok(ParsedMnemonic {
+            language: "English",
+            entropy: mnemonic.entropy().to_vec(),
+            seed: mnemonic.to_seed(""),
+        });
+    }
+    parse::<bip0039::ChineseSimplified>(phrase, "Chinese (Simplified)")
+        .or_else(|| {
+            parse::<bip0039::ChineseTraditional>(
+                phrase,
+                "Chinese (Traditional)",
+            )
+        })
+        .or_else(|| parse::<bip0039::Czech>(phrase, "Czech"))
+        .or_else(|| parse::<bip0039::French>(phrase, "French"))
+        .or_else(|| parse::<bip0039::Italian>(phrase, "Italian"))
+        .or_else(|| parse::<bip0039::Japanese>(phrase, "Japanese"))
+        .or_else(|| parse::<bip0039::Korean>(phrase, "Korean"))
+        .or_else(|| parse::<bip0039::Portuguese>(phrase, "Portuguese"))
+        .or_else(|| parse::<bip0039::Spanish>(phrase, "Spanish"))
+        .ok_or_else(|| {
+            anyhow!("Invalid BIP-39 mnemonic: {}", english.unwrap_err())
+        })
+}
+
+/// Parses a hardened-only HD path such as `m/32'/133'/2147483647'/5'`.
+pub fn parse_hd_path(path: &str) -> Option<Vec<ChildIndex>> {
+    let mut components = path.split('/');
+    if components.next()? != "m" {
+        return None;
+    }
+    components
+        .map(|component| {
+            let index = component
+                .strip_suffix('\'')
+                .or_else(|| component.strip_suffix('h'))?;
+            Some(ChildIndex::hardened(index.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Decodes a Zewif Sapling spending key.
+pub fn sapling_spending_key(key: &SpendingKey) -> Option<ExtendedSpendingKey> {
+    match key {
+        SpendingKey::Sapling(key) => {
+            ExtendedSpendingKey::from_bytes(key.as_ref()).ok()
+        }
+        _ => None,
+    }
+}
+
+/// The Zewif form of a Sapling spending key.
+pub fn zewif_sapling_spending_key(xsk: &ExtendedSpendingKey) -> SpendingKey {
+    SpendingKey::Sapling(zewif::sapling::SaplingExtendedSpendingKey::new(
+        xsk.to_bytes(),
+    ))
+}
+
+/// The Zewif form of a Sapling incoming viewing key.
+pub fn zewif_sapling_ivk(
+    ivk: &SaplingIvk,
+) -> zewif::sapling::SaplingIncomingViewingKey {
+    zewif::sapling::SaplingIncomingViewingKey::new(ivk.to_repr())
+}
+
+/// Decodes a Sapling extended spending key in its Bech32 encoding, such as
+/// `secret-extended-key-main1…`.
+pub fn decode_sapling_spending_key(
+    network: NetworkType,
+    encoded: &str,
+) -> Option<ExtendedSpendingKey> {
+    decode_extended_spending_key(
+        network.hrp_sapling_extended_spending_key(),
+        encoded,
+    )
+    .ok()
+}
+
+/// Decodes a Sapling extended full viewing key in its Bech32 encoding, such
+/// as `zxviews1…`.
+pub fn decode_sapling_viewing_key(
+    network: NetworkType,
+    encoded: &str,
+) -> Option<ExtendedFullViewingKey> {
+    decode_extended_full_viewing_key(
+        network.hrp_sapling_extended_full_viewing_key(),
+        encoded,
+    )
+    .ok()
+}
+
+/// Decodes a transparent secret key in wallet import format, failing if it
+/// belongs to another network.
+pub fn decode_transparent_secret_key(
+    network: NetworkType,
+    encoded: &str,
+) -> Option<[u8; 32]> {
+    let data = bs58::decode(encoded).with_check(None).into_vec().ok()?;
+    let (&version, key) = data.split_first()?;
+    let expected = match network {
+        NetworkType::Main => 0x80,
+        NetworkType::Test | NetworkType::Regtest => 0xef,
+    };
+    // Keys for compressed public keys are followed by a 1.
+    let key = match key {
+        [key @ .., 1] if key.len() == 32 => key,
+        key => key,
+    };
+    if version != expected {
+        return None;
+    }
+    key.try_into().ok()
+}
+
+/// The Sapling incoming viewing key within a unified full or incoming
+/// viewing key, failing if the key belongs to another network or has no
+/// Sapling component.
+pub fn unified_sapling_ivk(
+    network: NetworkType,
+    encoded: &str,
+) -> Option<SaplingIvk> {
+    if let Ok((key_network, ufvk)) = unified::Ufvk::decode(encoded) {
+        if key_network != network {
+            return None;
+        }
+        return ufvk.items().into_iter().find_map(|item| match item {
+            unified::Fvk::Sapling(data) => {
+                DiversifiableFullViewingKey::from_bytes(&data)
+                    .map(|dfvk| dfvk.to_ivk(Scope::External))
+            }
+            _ => None,
+        });
+    }
+    let (key_network, uivk) = unified::Uivk::decode(encoded).ok()?;
+    if key_network != network {
+        return None;
+    }
+    // The Sapling item is the diversifier key followed by the ivk itself.
+    uivk.items().into_iter().find_map(|item| match item {
+        unified::Ivk::Sapling(data) => sapling_ivk(&data[32..]),
+        _ => None,
+    })
+}
+
+/// Decodes an address string, failing if it belongs to another network.
+pub fn decode_address(
+    network: NetworkType,
+    address: &str,
+) -> Result<DecodedAddress, String> {
+    let zcash_address =
+        ZcashAddress::try_from_encoded(address).map_err(|e| e.to_string())?;
+    DecodedAddress::try_from_zcash_address(network, zcash_address)
+        .map_err(|e| e.to_string())
+}
+
+/// Decodes a Zewif Sapling incoming viewing key.
+pub fn sapling_ivk(ivk: &[u8]) -> Option<SaplingIvk> {
+    let repr = <[u8; 32]>::try_from(ivk).ok()?;
+    let fr = Option::<jubjub::Fr>::from(jubjub::Fr::from_bytes(&repr))?;
+    Some(SaplingIvk(fr))
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// If a transparent secret key controls `address`, returns whether the
+/// address uses the compressed form of its public key. Legacy zcashd keys may
+/// use either form.
+pub fn transparent_key_compression(
+    secret_key: &[u8],
+    address: &DecodedTransparentAddress,
+) -> Option<bool> {
+    let secret_key = secp256k1::SecretKey::from_slice(secret_key).ok()?;
+    let public_key = secp256k1::PublicKey::from_secret_key(
+        &secp256k1::Secp256k1::signing_only(),
+        &secret_key,
+    );
+    let DecodedTransparentAddress::PublicKeyHash(hash) = address else {
+        return None;
+    };
+    if *hash == hash160(&public_key.serialize()) {
+        Some(true)
+    } else if *hash == hash160(&public_key.serialize_uncompressed()) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Whether the account's transparent key at `change/index` derives `address`.
+pub fn transparent_derivation_matches(
+    ufvk: &UnifiedFullViewingKey,
+    change: u32,
+    index: u32,
+    address: &DecodedTransparentAddress,
+) -> bool {
+    let Some(account_pubkey) = ufvk.transparent() else {
+        return false;
+    };
+    let Some(index) = NonHardenedChildIndex::from_index(index) else {
+        return false;
+    };
+    let derived = match change {
+        0 => account_pubkey
+            .derive_external_ivk()
+            .ok()
+            .and_then(|ivk| ivk.derive_address(index).ok()),
+        1 => account_pubkey
+            .derive_internal_ivk()
+            .ok()
+            .and_then(|ivk| ivk.derive_address(index).ok()),
+        _ => None,
+    };
+    derived.as_ref() == Some(address)
+}
+
+/// The key material shared by all accounts of one Zewif wallet.
+pub struct WalletKeys {
+    network: NetworkType,
+    seed: Option<Vec<u8>>,
+}
+
+impl WalletKeys {
+    pub fn new(wallet: &ZewifWallet) -> Result<Self> {
+        let seed = wallet.seed_material().map(seed_bytes).transpose()?;
+        Ok(Self {
+            network: network_type(wallet.network()),
+            seed,
+        })
+    }
+
+    pub fn network(&self) -> NetworkType { self.network }
+
+    pub fn seed(&self) -> Option<&[u8]> { self.seed.as_deref() }
+
+    /// Derives the Sapling spending key at an HD path from the wallet seed.
+    pub fn sapling_key_at_path(
+        &self,
+        path: &str,
+    ) -> Option<ExtendedSpendingKey> {
+        let seed = self.seed.as_ref()?;
+        let indices = parse_hd_path(path)?;
+        let master = ExtendedSpendingKey::master(seed);
+        Some(ExtendedSpendingKey::from_path(&master, &indices))
+    }
+
+    /// Derives the unified full viewing key for a ZIP-32 account, if the
+    /// wallet has a seed and the account has a ZIP-32 index.
+    pub fn account_ufvk(
+        &self,
+        account: &Account,
+    ) -> Result<Option<UnifiedFullViewingKey>> {
+        let Some(account_id) = account.zip32_account_id() else {
+            return Ok(None);
+        };
+        Ok(self
+            .account_usk(account_id)?
+            .map(|usk| usk.to_unified_full_viewing_key()))
+    }
+
+    /// Derives the unified spending key for a ZIP-32 account index, if the
+    /// wallet has a seed.
+    pub fn account_usk(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<UnifiedSpendingKey>> {
+        let Some(seed) = &self.seed else {
+            return Ok(None);
+        };
+        let account_id = AccountId::try_from(account_id)
+            .map_err(|_| anyhow!("Invalid ZIP-32 account index"))?;
+        // Regtest shares the testnet coin type, so the testnet parameters
+        // derive the same keys.
+        let usk = match self.network {
+            NetworkType::Main => UnifiedSpendingKey::from_seed(
+                &consensus::MAIN_NETWORK,
+                seed,
+                account_id,
+            ),
+            NetworkType::Test | NetworkType::Regtest => {
+                UnifiedSpendingKey::from_seed(
+                    &consensus::TEST_NETWORK,
+                    seed,
+                    account_id,
+                )
+            }
+        }
+        .map_err(|e| anyhow!("{:?}", e))?;
+        Ok(Some(usk))
+    }
+
+    /// Whether `encoded` is the unified full viewing key of `account` on
+    /// this wallet's network.
+    pub fn is_account_ufvk(
+        &self,
+        account: &Account,
+        encoded: &str,
+    ) -> Result<bool> {
+        let Ok((network, ufvk)) = unified::Ufvk::decode(encoded) else {
+            return Ok(false);
+        };
+        if network != self.network {
+            return Ok(false);
+        }
+        let Some(account_ufvk) = self.account_ufvk(account)? else {
+            return Ok(false);
+        };
+        let ufvk = UnifiedFullViewingKey::parse(&ufvk)
+            .map_err(|e| anyhow!("Invalid unified full viewing key: {}", e))?;
+        // Both keys are encoded for the same network, whichever it is.
+        Ok(ufvk.encode(&consensus::MAIN_NETWORK)
+            == account_ufvk.encode(&consensus::MAIN_NETWORK))
+    }
+
+    /// Decodes an address string for this wallet's network.
+    pub fn decode_address(
+        &self,
+        address: &str,
+    ) -> Result<DecodedAddress, String> {
+        decode_address(self.network, address)
+    }
+}
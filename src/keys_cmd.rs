@@ -0,0 +1,245 @@
+use std::{
+    fmt::Write as _,
+    io::{self, IsTerminal, Write as _},
+    path::PathBuf,
+};
+
+use anyhow::{Result, bail};
+use clap::{Args, Subcommand};
+use zewif::{BlockHeight, SeedMaterial, Zewif, ZewifWallet};
+
+use crate::{
+    birthday,
+    exec::Exec,
+    file_args::{FileArgsLike, write_secret_file},
+    input_args::InputArgs,
+    keys::parse_mnemonic,
+    legacy,
+};
+
+/// Export key material from a wallet
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    command: KeysCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysCommand {
+    /// Print the wallet's BIP-39 mnemonic seed phrase
+    ExportSeed(ExportSeedArgs),
+
+    /// Classify keys as seed-derived or imported, and write a sweep manifest
+    /// of the imported ones
+    Legacy(LegacyArgs),
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        match &self.command {
+            KeysCommand::ExportSeed(args) => args.input_args.file(),
+            KeysCommand::Legacy(args) => args.input_args.file(),
+        }
+    }
+}
+
+impl Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        match &self.command {
+            KeysCommand::ExportSeed(args) => args.exec(),
+            KeysCommand::Legacy(args) => args.exec(),
+        }
+    }
+}
+
+/// Print the wallet's BIP-39 mnemonic seed phrase
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct ExportSeedArgs {
+    #[command(flatten)]
+    input_args: InputArgs,
+
+    /// Skip the confirmation prompt and allow writing the seed phrase to a
+    /// non-terminal stdout, such as a file or pipe
+    #[arg(long)]
+    force: bool,
+}
+
+impl Exec for ExportSeedArgs {
+    fn exec(&self) -> Result<String> {
+        if !self.force {
+            if !io::stdout().is_terminal() {
+                bail!(
+                    "Refusing to write the seed phrase to a non-terminal stdout; use --force to override"
+                );
+            }
+            confirm_export()?;
+        }
+        let zewif = self.input_args.load_zewif()?;
+        export_seed(&zewif)
+    }
+}
+
+/// Classify keys as seed-derived or imported, and write a sweep manifest of
+/// the imported ones
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct LegacyArgs {
+    #[command(flatten)]
+    input_args: InputArgs,
+
+    /// Write the imported spending keys as a JSON sweep manifest to this
+    /// file, which must not already exist
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+}
+
+impl Exec for LegacyArgs {
+    fn exec(&self) -> Result<String> {
+        let zewif = self.input_args.load_zewif()?;
+        let report = legacy::classify_keys(&zewif)?;
+        let mut output = report.to_string();
+        if let Some(path) = &self.manifest {
+            write_secret_file(
+                path,
+                &serde_json::to_string_pretty(&report.to_manifest_json())?,
+            )?;
+            write!(
+                output,
+                "\n---\nSweep manifest with {} key(s) written to {}",
+                report.sweep_keys().len(),
+                path.display()
+            )?;
+        }
+        Ok(output)
+    }
+}
+
+fn confirm_export() -> Result<()> {
+    eprintln!(
+        "⚠️  The seed phrase gives full control of all funds in this wallet."
+    );
+    eprintln!(
+        "⚠️  Anyone who sees it can spend them. Make sure no one is watching your screen."
+    );
+    eprint!("Type \"export\" to continue: ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if answer.trim() != "export" {
+        bail!("Seed export cancelled");
+    }
+    Ok(())
+}
+
+/// Formats the mnemonic, language, ZIP-32 account indices and birthday of
+/// every wallet in `zewif` that has a BIP-39 seed.
+pub fn export_seed(zewif: &Zewif) -> Result<String> {
+    let mut output = String::new();
+    let mut exported = 0;
+    for (index, wallet) in zewif.wallets().iter().enumerate() {
+        let Some(SeedMaterial::Bip39Mnemonic(mnemonic)) =
+            wallet.seed_material()
+        else {
+            continue;
+        };
+        if exported > 0 {
+            writeln!(output, "---")?;
+        }
+        exported += 1;
+        writeln!(output, "Wallet: {}", index)?;
+        writeln!(output, "Network: {:?}", wallet.network())?;
+        writeln!(output, "Mnemonic: {}", mnemonic.mnemonic())?;
+        match mnemonic.language() {
+            Some(language) => writeln!(output, "Language: {:?}", language)?,
+            None => writeln!(
+                output,
+                "Language: {} (from the words)",
+                parse_mnemonic(mnemonic.mnemonic())?.language()
+            )?,
+        }
+        let account_ids: Vec<String> = wallet
+            .accounts()
+            .iter()
+            .filter_map(|a| a.zip32_account_id())
+            .map(|id| id.to_string())
+            .collect();
+        if account_ids.is_empty() {
+            writeln!(output, "ZIP-32 accounts: none")?;
+        } else {
+            writeln!(output, "ZIP-32 accounts: {}", account_ids.join(", "))?;
+        }
+        match wallet_birthday(zewif, wallet)? {
+            Some((height, BirthdaySource::Stored)) => {
+                writeln!(output, "Birthday height: {}", u32::from(height))?
+            }
+            Some((height, BirthdaySource::Estimated)) => writeln!(
+                output,
+                "Birthday height: {} (estimated)",
+                u32::from(height)
+            )?,
+            Some((height, BirthdaySource::EarliestTransaction)) => writeln!(
+                output,
+                "Birthday height: {} (earliest transaction)",
+                u32::from(height)
+            )?,
+            None => writeln!(output, "Birthday height: unknown")?,
+        }
+    }
+
+    if exported == 0 {
+        if zewif.wallets().iter().any(|w| {
+            matches!(w.seed_material(), Some(SeedMaterial::PreBIP39Seed(_)))
+        }) {
+            bail!(
+                "Wallet has a legacy pre-BIP-39 HD seed, which has no mnemonic phrase"
+            );
+        }
+        bail!("Wallet has no BIP-39 mnemonic seed");
+    }
+    Ok(output.trim_end().to_string())
+}
+
+/// Where a wallet's birthday comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BirthdaySource {
+    /// An account birthday recorded by the source or set with `--birthday`.
+    Stored,
+
+    /// An account birthday estimated during migration; see
+    /// [`birthday::estimate_birthdays`].
+    Estimated,
+
+    /// The earliest mined height of the wallet's transactions.
+    EarliestTransaction,
+}
+
+/// The wallet's birthday: the earliest account birthday, or failing that the
+/// earliest mined height of any of its transactions.
+pub fn wallet_birthday(
+    zewif: &Zewif,
+    wallet: &ZewifWallet,
+) -> Result<Option<(BlockHeight, BirthdaySource)>> {
+    let stored = wallet
+        .accounts()
+        .iter()
+        .filter_map(|a| a.birthday_height())
+        .min();
+    if let Some(stored) = stored {
+        let source = if birthday::is_estimated(zewif, stored)? {
+            BirthdaySource::Estimated
+        } else {
+            BirthdaySource::Stored
+        };
+        return Ok(Some((stored, source)));
+    }
+    Ok(wallet
+        .accounts()
+        .iter()
+        .flat_map(|a| a.relevant_transactions())
+        .filter_map(|txid| zewif.transactions().get(txid))
+        .filter_map(|tx| tx.mined_height())
+        .min()
+        .map(|height| (height, BirthdaySource::EarliestTransaction)))
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use zcash_keys::{
+    address::Address as DecodedAddress, encoding::encode_extended_spending_key,
+    keys::UnifiedFullViewingKey,
+};
+use zcash_protocol::consensus::{NetworkConstants, NetworkType};
+use zewif::{
+    Account, ProtocolAddress, ShieldedAddress, TransparentAddress,
+    TransparentSpendAuthority, Zewif,
+};
+use zip32::ChildIndex;
+
+use crate::{
+    keys::{
+        WalletKeys, parse_hd_path, sapling_spending_key,
+        transparent_derivation_matches, transparent_key_compression,
+    },
+    sprout::is_sprout_address,
+    verify::AddressKind,
+};
+
+/// The ZIP-32 account under which zcashd derives the keys of its
+/// non-unified addresses, such as Sapling keys at
+/// `m/32'/133'/2147483647'/n'`.
+pub const LEGACY_ACCOUNT: u32 = 0x7FFF_FFFF;
+
+/// Where the key for an address comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOrigin {
+    /// The key can be regenerated from the wallet's HD seed.
+    SeedDerived,
+
+    /// The key is derived from the wallet's HD seed under zcashd's
+    /// [`LEGACY_ACCOUNT`], which no light wallet scans, so like an imported
+    /// key its funds must be swept.
+    LegacyAccount,
+
+    /// The key was imported and cannot be regenerated from the seed; its
+    /// funds must be swept before the wallet is abandoned.
+    Imported,
+
+    /// The wallet holds no spending key for the address.
+    WatchOnly,
+}
+
+impl fmt::Display for KeyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            KeyOrigin::SeedDerived => "seed-derived",
+            KeyOrigin::LegacyAccount => "legacy account",
+            KeyOrigin::Imported => "imported",
+            KeyOrigin::WatchOnly => "watch-only",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl KeyOrigin {
+    /// Whether funds held by the key must be swept before the wallet is
+    /// abandoned, as a light wallet restored from the seed won't find them.
+    pub fn needs_sweep(&self) -> bool {
+        matches!(self, KeyOrigin::LegacyAccount | KeyOrigin::Imported)
+    }
+}
+
+/// The origin of the key behind one address.
+#[derive(Debug, Clone)]
+pub struct KeyClassification {
+    wallet_index: usize,
+    account_index: usize,
+    kind: AddressKind,
+    address: String,
+    origin: KeyOrigin,
+}
+
+impl KeyClassification {
+    pub fn wallet_index(&self) -> usize { self.wallet_index }
+
+    pub fn account_index(&self) -> usize { self.account_index }
+
+    pub fn kind(&self) -> AddressKind { self.kind }
+
+    pub fn address(&self) -> &str { &self.address }
+
+    pub fn origin(&self) -> KeyOrigin { self.origin }
+}
+
+/// A spending key whose funds must be swept, encoded for import into a
+/// sweep tool.
+#[derive(Clone)]
+pub enum SweepKey {
+    /// A transparent key in Wallet Import Format.
+    Transparent { address: String, wif: String },
+
+    /// A Sapling extended spending key in its Bech32 encoding.
+    Sapling {
+        address: String,
+        extended_spending_key: String,
+    },
+
+    /// A Sprout address. Only zcashd can spend Sprout notes, so its funds
+    /// must be moved to a newer pool with zcashd rather than a sweep tool,
+    /// and no key is exported.
+    Sprout { address: String },
+}
+
+// Never print key material in debug output.
+impl fmt::Debug for SweepKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SweepKey::Transparent { address, .. } => f
+                .debug_struct("Transparent")
+                .field("address", address)
+                .finish_non_exhaustive(),
+            SweepKey::Sapling { address, .. } => f
+                .debug_struct("Sapling")
+                .field("address", address)
+                .finish_non_exhaustive(),
+            SweepKey::Sprout { address } => {
+                f.debug_struct("Sprout").field("address", address).finish()
+            }
+        }
+    }
+}
+
+/// Classification of every key in a `Zewif` as seed-derived or imported,
+/// together with the imported keys that need sweeping.
+#[derive(Debug, Clone, Default)]
+pub struct LegacyKeyReport {
+    classifications: Vec<KeyClassification>,
+    sweep_keys: Vec<SweepKey>,
+}
+
+impl LegacyKeyReport {
+    pub fn classifications(&self) -> &[KeyClassification] {
+        &self.classifications
+    }
+
+    pub fn sweep_keys(&self) -> &[SweepKey] { &self.sweep_keys }
+
+    fn count(&self, origin: KeyOrigin) -> usize {
+        self.classifications
+            .iter()
+            .filter(|c| c.origin == origin)
+            .count()
+    }
+
+    /// The sweep manifest: every key that needs sweeping with the address it
+    /// controls.
+    ///
+    /// The result contains spending keys and must be handled as secret.
+    pub fn to_manifest_json(&self) -> Value {
+        let keys: Vec<Value> = self
+            .sweep_keys
+            .iter()
+            .map(|key| match key {
+                SweepKey::Transparent { address, wif } => json!({
+                    "type": "transparent",
+                    "address": address,
+                    "wif": wif,
+                }),
+                SweepKey::Sapling {
+                    address,
+                    extended_spending_key,
+                } => json!({
+                    "type": "sapling",
+                    "address": address,
+                    "extended_spending_key": extended_spending_key,
+                }),
+                SweepKey::Sprout { address } => json!({
+                    "type": "sprout",
+                    "address": address,
+                    "sweep_with": "zcashd",
+                }),
+            })
+            .collect();
+        json!({ "keys": keys })
+    }
+}
+
+impl fmt::Display for LegacyKeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Legacy Key Report")?;
+        writeln!(f, "- Seed-derived: {}", self.count(KeyOrigin::SeedDerived))?;
+        writeln!(
+            f,
+            "- Legacy account: {}",
+            self.count(KeyOrigin::LegacyAccount)
+        )?;
+        writeln!(f, "- Imported: {}", self.count(KeyOrigin::Imported))?;
+        writeln!(f, "- Watch-only: {}", self.count(KeyOrigin::WatchOnly))?;
+        let to_sweep: Vec<_> = self
+            .classifications
+            .iter()
+            .filter(|c| c.origin.needs_sweep())
+            .collect();
+        if to_sweep.is_empty() {
+            write!(
+                f,
+                "✅ All spending keys can be regenerated from the seed by a light wallet"
+            )?;
+        } else {
+            for c in &to_sweep {
+                writeln!(
+                    f,
+                    "⚠️  wallet {} account {} {} address {} ({})",
+                    c.wallet_index,
+                    c.account_index,
+                    c.kind,
+                    c.address,
+                    c.origin
+                )?;
+            }
+            write!(
+                f,
+                "🛑 {} key(s) must be swept before abandoning this wallet",
+                to_sweep.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Classifies the key behind every address in `zewif` and collects the
+/// spending keys that need sweeping: imported ones, and those zcashd derived
+/// under its [`LEGACY_ACCOUNT`].
+pub fn classify_keys(zewif: &Zewif) -> Result<LegacyKeyReport> {
+    let mut report = LegacyKeyReport::default();
+    for (wallet_index, wallet) in zewif.wallets().iter().enumerate() {
+        let keys = WalletKeys::new(wallet).with_context(|| {
+            format!("Preparing key material for wallet {}", wallet_index)
+        })?;
+        for (account_index, account) in wallet.accounts().iter().enumerate() {
+            let ufvk = keys.account_ufvk(account).with_context(|| {
+                format!(
+                    "Deriving keys for wallet {} account {}",
+                    wallet_index, account_index
+                )
+            })?;
+            for address in account.addresses() {
+                let (kind, origin, sweep_key) = match address.address() {
+                    ProtocolAddress::Transparent(transparent) => {
+                        let (origin, sweep_key) = classify_transparent(
+                            &keys,
+                            account,
+                            transparent,
+                            ufvk.as_ref(),
+                        );
+                        (AddressKind::Transparent, origin, sweep_key)
+                    }
+                    // Sprout keys are never derived from a seed.
+                    ProtocolAddress::Shielded(shielded)
+                        if is_sprout_address(shielded.address()) =>
+                    {
+                        let sweep_key = SweepKey::Sprout {
+                            address: shielded.address().to_string(),
+                        };
+                        (
+                            AddressKind::Sprout,
+                            KeyOrigin::Imported,
+                            Some(sweep_key),
+                        )
+                    }
+                    ProtocolAddress::Shielded(shielded) => {
+                        let (origin, sweep_key) = classify_sapling(
+                            &keys,
+                            account,
+                            shielded,
+                            ufvk.as_ref(),
+                        );
+                        (AddressKind::Sapling, origin, sweep_key)
+                    }
+                    // Unified addresses only exist for ZIP-32 accounts.
+                    ProtocolAddress::Unified(_) => {
+                        let origin = if ufvk.is_some() {
+                            KeyOrigin::SeedDerived
+                        } else {
+                            KeyOrigin::WatchOnly
+                        };
+                        (AddressKind::Unified, origin, None)
+                    }
+                };
+                report.classifications.push(KeyClassification {
+                    wallet_index,
+                    account_index,
+                    kind,
+                    address: address.as_string(),
+                    origin,
+                });
+                report.sweep_keys.extend(sweep_key);
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn is_legacy_account(account: &Account) -> bool {
+    account.zip32_account_id() == Some(LEGACY_ACCOUNT)
+}
+
+fn classify_transparent(
+    keys: &WalletKeys,
+    account: &Account,
+    address: &TransparentAddress,
+    ufvk: Option<&UnifiedFullViewingKey>,
+) -> (KeyOrigin, Option<SweepKey>) {
+    let Some(TransparentSpendAuthority::SpendingKey(key)) =
+        address.spend_authority()
+    else {
+        return (KeyOrigin::WatchOnly, None);
+    };
+    let Ok(DecodedAddress::Transparent(decoded)) =
+        keys.decode_address(address.address())
+    else {
+        return (KeyOrigin::Imported, None);
+    };
+    let seed_derived = if let (Some(ufvk), Some(derivation)) =
+        (ufvk, address.derivation_info())
+    {
+        transparent_derivation_matches(
+            ufvk,
+            u32::from(derivation.change()),
+            u32::from(derivation.address_index()),
+            &decoded,
+        )
+    } else {
+        false
+    };
+    let origin = match (seed_derived, is_legacy_account(account)) {
+        (true, false) => return (KeyOrigin::SeedDerived, None),
+        (true, true) => KeyOrigin::LegacyAccount,
+        (false, _) => KeyOrigin::Imported,
+    };
+    // A key that doesn't control its address can't sweep it either.
+    let sweep_key =
+        transparent_key_compression(key.as_ref(), &decoded).map(|compressed| {
+            SweepKey::Transparent {
+                address: address.address().to_string(),
+                wif: encode_wif(keys.network(), key.as_ref(), compressed),
+            }
+        });
+    (origin, sweep_key)
+}
+
+fn classify_sapling(
+    keys: &WalletKeys,
+    account: &Account,
+    address: &ShieldedAddress,
+    ufvk: Option<&UnifiedFullViewingKey>,
+) -> (KeyOrigin, Option<SweepKey>) {
+    let Some(xsk) = address.spending_key().and_then(sapling_spending_key)
+    else {
+        return (KeyOrigin::WatchOnly, None);
+    };
+    let path = address.hd_derivation_path();
+    let from_path = path
+        .and_then(|path| keys.sapling_key_at_path(path))
+        .is_some_and(|derived| derived.to_bytes() == xsk.to_bytes());
+    let from_account =
+        ufvk.and_then(|ufvk| ufvk.sapling()).is_some_and(|dfvk| {
+            let fvk = xsk.to_diversifiable_full_viewing_key();
+            fvk.to_bytes() == dfvk.to_bytes()
+        });
+    // The account is the third component of `m/32'/coin'/account'/…`.
+    let legacy = if from_path {
+        path.and_then(|path| parse_hd_path(path)?.get(2).copied())
+            == Some(ChildIndex::hardened(LEGACY_ACCOUNT))
+    } else {
+        is_legacy_account(account)
+    };
+    let origin = match (from_path || from_account, legacy) {
+        (true, false) => return (KeyOrigin::SeedDerived, None),
+        (true, true) => KeyOrigin::LegacyAccount,
+        (false, _) => KeyOrigin::Imported,
+    };
+    let sweep_key = SweepKey::Sapling {
+        address: address.address().to_string(),
+        extended_spending_key: encode_extended_spending_key(
+            keys.network().hrp_sapling_extended_spending_key(),
+            &xsk,
+        ),
+    };
+    (origin, Some(sweep_key))
+}
+
+/// Encodes a transparent secret key in Wallet Import Format.
+fn encode_wif(
+    network: NetworkType,
+    secret_key: &[u8],
+    compressed: bool,
+) -> String {
+    let prefix = match network {
+        NetworkType::Main => 0x80,
+        NetworkType::Test | NetworkType::Regtest => 0xef,
+    };
+    let mut payload = vec![prefix];
+    payload.extend_from_slice(secret_key);
+    if compressed {
+        payload.push(0x01);
+    }
+    bs58::encode(payload).with_check().into_string()
+}
@@ -1,5 +1,44 @@
+pub mod balance;
+pub mod balance_cmd;
+pub mod birthday;
+pub mod convert_cmd;
+pub mod error;
 pub mod exec;
 pub mod file_args;
+pub mod filter;
+pub mod filter_args;
+pub mod format;
+pub mod input_args;
+pub mod inspect;
+pub mod inspect_cmd;
+pub mod keys;
+pub mod keys_cmd;
+pub mod legacy;
+pub mod logging;
+pub mod merge;
+pub mod metadata;
+pub mod migrator;
+pub mod network;
+pub mod progress;
+pub mod records_cmd;
+pub mod source;
+pub mod split;
+pub mod split_cmd;
+pub mod sprout;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_cmd;
+pub mod verify;
+#[cfg(feature = "ywallet")]
+pub mod ywallet;
+#[cfg(feature = "ywallet")]
+pub mod ywallet_cmd;
 pub mod zcashd_cmd;
+#[cfg(feature = "zecwallet")]
+pub mod zecwallet_cmd;
+pub mod zewif_cmd;
 #[cfg(feature = "zingo")]
 pub mod zingo_cmd;
+#[cfg(feature = "zingo")]
+pub mod zingo_export;
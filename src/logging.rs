@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result, anyhow};
+use tracing_subscriber::{
+    EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+/// The stderr log level for a verbosity, where each `-v` adds one and `-q`
+/// subtracts one.
+fn level(verbosity: i8) -> &'static str {
+    match verbosity {
+        i8::MIN..=-1 => "error",
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    }
+}
+
+/// The log file filter: a full trace of `zmigrate` itself, and only warnings
+/// and errors from the crates it uses.
+const FILE_FILTER: &str = "warn,zmigrate=trace";
+
+/// Whether output on stderr may use color and emoji: only when stderr is a
+/// terminal and `NO_COLOR` is not set.
+pub fn stderr_styled() -> bool {
+    io::stderr().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Installs the global `tracing` subscriber.
+///
+/// Log events and span fields must never include secret material: no seeds,
+/// mnemonics, spending keys, passwords, or raw wallet record values. Log
+/// counts, stage names, file paths and record keynames instead.
+///
+/// Events go to stderr, filtered by `RUST_LOG` if it is set and by
+/// `verbosity` otherwise, one JSON object per line if `json` is set. If
+/// `log_file` is given, a full trace of `zmigrate` is also written to it,
+/// regardless of verbosity.
+pub fn init(verbosity: i8, log_file: Option<&Path>, json: bool) -> Result<()> {
+    let stderr_filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(level(verbosity)));
+    let stderr_layer = if json {
+        fmt::layer()
+            .json()
+            .with_writer(io::stderr)
+            .with_filter(stderr_filter)
+            .boxed()
+    } else {
+        fmt::layer()
+            .with_writer(io::stderr)
+            .with_ansi(stderr_styled())
+            .with_filter(stderr_filter)
+            .boxed()
+    };
+
+    let file_layer = log_file
+        .map(|path| -> Result<_> {
+            let file = File::create(path).with_context(|| {
+                format!("Failed to create log file: {}", path.display())
+            })?;
+            Ok(fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_filter(EnvFilter::new(FILE_FILTER)))
+        })
+        .transpose()?;
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to initialize logging: {}", e))
+}
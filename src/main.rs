@@ -1,64 +1,47 @@
 mod styles;
 
-use std::{
-    fs::File,
-    io::{self, Write},
-    path::PathBuf,
-};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use bc_envelope::prelude::*;
-use clap::{Parser as ClapParser, ValueEnum};
+use anyhow::Result;
+use clap::{ArgAction, Parser as ClapParser, Subcommand};
 use rpassword::prompt_password;
-use zewif::ZewifEnvelope;
-use zmigrate::zcashd_cmd;
-#[cfg(feature = "zingo")]
-use zmigrate::zingo_cmd;
-
-/// Supported input formats for wallet migration
-#[derive(Debug, Clone, ValueEnum)]
-pub enum InputFormat {
-    /// Input from a `zcashd` wallet
-    Zcashd,
-
-    /// Input from a `zingo` wallet
-    #[cfg(feature = "zingo")]
-    Zingo,
-
-    /// Input from a `zewif` wallet
-    Zewif,
-}
-
-/// Supported output formats for wallet migration
-#[derive(Debug, Clone, ValueEnum)]
-pub enum OutputFormat {
-    /// Output in Zewif binary format (default)
-    Zewif,
-
-    /// Output in Zewif UR format
-    UR,
-
-    /// Output as Envelope Notation
-    Format,
-
-    /// Output as Debug dump
-    Dump,
-}
+use zmigrate::{
+    balance_cmd, convert_cmd,
+    error::{self, ErrorFormat},
+    exec::Exec,
+    file_args::FileArgsLike,
+    filter_args::FilterArgs,
+    format::Registry,
+    inspect_cmd, keys_cmd, logging,
+    migrator::{Migrator, OutputSink},
+    network::NetworkName,
+    progress::{Progress, ProgressFormat},
+    split_cmd, zcashd_cmd,
+};
 
 /// A tool for migrating Zcash wallets
 #[derive(Debug, clap::Parser)]
 #[command(author, version)]
 #[command(propagate_version = true)]
 #[command(styles=styles::get_styles())]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(subcommand_negates_reqs = true)]
 #[doc(hidden)]
 pub struct Cli {
-    /// Input format: zcashd or zingo
-    #[arg(long, value_enum)]
-    pub from: InputFormat,
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Input format
+    #[arg(long, value_parser = Registry::builtin().reader_parser())]
+    pub from: String,
 
-    /// Output format: zewif (default) or dump
-    #[arg(long, value_enum, default_value_t = OutputFormat::Zewif)]
-    pub to: OutputFormat,
+    /// Output format
+    #[arg(
+        long,
+        default_value = "zewif",
+        value_parser = Registry::builtin().writer_parser()
+    )]
+    pub to: String,
 
     /// Compress the output
     #[arg(long)]
@@ -68,6 +51,59 @@ pub struct Cli {
     #[arg(long)]
     pub encrypt: bool,
 
+    /// Write a zingo wallet without the input's transactions, for zingo to
+    /// rebuild by rescanning from the wallet's birthday
+    #[arg(long)]
+    pub rescan: bool,
+
+    /// Check that every address can be re-derived from the wallet's keys,
+    /// failing if any cannot
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Leave Sprout addresses out of a zcashd migration, recording their
+    /// exclusion in the output's metadata
+    #[arg(long)]
+    pub drop_sprout: bool,
+
+    /// Abort unless the input wallet belongs to this network
+    #[arg(long, value_enum)]
+    pub expect_network: Option<NetworkName>,
+
+    /// Fail if a zingo wallet has bytes left over after parsing, rather than
+    /// reporting a partial success
+    #[arg(long)]
+    pub strict: bool,
+
+    #[command(flatten)]
+    pub filter_args: FilterArgs,
+
+    /// Set the birthday height of every account, overriding the one read
+    /// from or estimated for the wallet
+    #[arg(long, value_name = "HEIGHT")]
+    pub birthday: Option<u32>,
+
+    /// Don't report progress on stderr, and only log errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Log more detail on stderr; repeat for even more (`RUST_LOG`
+    /// overrides)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Also write a full trace log to this file
+    #[arg(long, value_name = "PATH", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// How to report a fatal error on stderr
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human, global = true)]
+    pub error_format: ErrorFormat,
+
+    /// How to report progress on stderr
+    #[arg(long, value_enum, default_value_t = ProgressFormat::Human, global = true)]
+    pub progress: ProgressFormat,
+
     /// Input file path
     pub input_file: String,
 
@@ -75,145 +111,127 @@ pub struct Cli {
     pub output_file: String,
 }
 
+/// Commands other than converting a single wallet
+#[derive(Debug, Subcommand)]
 #[doc(hidden)]
-fn main() {
-    bc_envelope::register_tags();
-    if let Err(e) = inner_main() {
-        eprintln!("---");
-        eprintln!("🔴 Error: {}\n", e);
-        for cause in e.chain().skip(1) {
-            eprintln!("Caused by: {}", cause);
+pub enum Commands {
+    /// Compute per-account, per-pool balances for a wallet
+    Balance(balance_cmd::CommandArgs),
+
+    /// Migrate several wallets and merge them into one
+    Convert(convert_cmd::CommandArgs),
+
+    /// Show a compact overview of a wallet
+    Inspect(inspect_cmd::CommandArgs),
+
+    /// Export key material from a wallet
+    Keys(keys_cmd::CommandArgs),
+
+    /// Extract one wallet or account into a wallet file of its own
+    Split(split_cmd::CommandArgs),
+
+    /// Process a zcashd wallet file
+    Zcashd(zcashd_cmd::CommandArgs),
+}
+
+impl Commands {
+    fn input_file(&self) -> &Path {
+        match self {
+            Commands::Balance(args) => args.file(),
+            Commands::Convert(args) => args.file(),
+            Commands::Inspect(args) => args.file(),
+            Commands::Keys(args) => args.file(),
+            Commands::Split(args) => args.file(),
+            Commands::Zcashd(args) => args.file(),
         }
-        std::process::exit(1);
     }
 }
 
 #[doc(hidden)]
-fn inner_main() -> Result<()> {
+fn main() {
+    bc_envelope::register_tags();
     let cli = Cli::parse();
-
-    let input_path = PathBuf::from(cli.input_file.as_str());
-
-    match cli.from {
-        InputFormat::Zcashd => {
-            let zewif = zcashd_cmd::zcashd_to_zewif(&input_path)?;
-            let envelope = Envelope::from(zewif.clone());
-            let mut ze = ZewifEnvelope::new(envelope)?;
-            if cli.compress {
-                ze.compress()?;
-            }
-            if cli.encrypt {
-                let password = prompt_password("Enter encryption password: ")?;
-                let key = ZewifEnvelope::derive_encryption_key(password);
-                ze.encrypt(&key)?;
-            }
-            let mut output: Box<dyn Write> = match cli.output_file.as_str() {
-                "-" => Box::new(io::stdout()),
-                path => Box::new(File::create(path).with_context(|| {
-                    format!("Failed to create output file: {}", path)
-                })?),
-            };
-            match cli.to {
-                OutputFormat::Zewif => {
-                    output.write_all(&ze.envelope().to_cbor_data())?;
-                }
-                OutputFormat::UR => {
-                    let envelope_ur = ze.envelope().ur_string();
-                    writeln!(output, "{}", envelope_ur)?;
-                }
-                OutputFormat::Format => {
-                    writeln!(output, "{}", ze.envelope().format())?;
-                }
-                OutputFormat::Dump => {
-                    writeln!(output, "{:#?}", zewif)?;
-                }
-            }
-        }
-        InputFormat::Zewif => {
-            // Read the input file as CBOR and parse as Envelope
-            let input_data = std::fs::read(&input_path).with_context(|| {
-                format!("Failed to read input file: {}", cli.input_file)
-            })?;
-            let envelope = Envelope::try_from_cbor_data(input_data)
-                .with_context(|| "Failed to parse input as Envelope")?;
-            let mut ze = ZewifEnvelope::new(envelope)?;
-            // If encrypted, prompt for password and decrypt
-            if ze.is_encrypted() {
-                let password = prompt_password("Enter decryption password: ")?;
-                let key = ZewifEnvelope::derive_encryption_key(password);
-                ze.decrypt(&key)?;
-            }
-            // If compressed, uncompress
-            if ze.is_compressed() {
-                ze.uncompress()?;
-            }
-            let mut output: Box<dyn Write> = match cli.output_file.as_str() {
-                "-" => Box::new(io::stdout()),
-                path => Box::new(File::create(path).with_context(|| {
-                    format!("Failed to create output file: {}", path)
-                })?),
-            };
-            match cli.to {
-                OutputFormat::Format => {
-                    writeln!(output, "{}", ze.envelope().format())?;
-                }
-                OutputFormat::Zewif => {
-                    output.write_all(&ze.envelope().to_cbor_data())?;
-                }
-                OutputFormat::UR => {
-                    let envelope_ur = ze.envelope().ur_string();
-                    writeln!(output, "{}", envelope_ur)?;
-                }
-                OutputFormat::Dump => {
-                    // Try to reconstruct Zewif for debug output
-                    match zewif::Zewif::try_from(ze.envelope().clone()) {
-                        Ok(zewif) => writeln!(output, "{:#?}", zewif)?,
-                        Err(_) => writeln!(
-                            output,
-                            "Could not decode Zewif from envelope."
-                        )?,
-                    }
-                }
-            }
-        }
-        #[cfg(feature = "zingo")]
-        InputFormat::Zingo => {
-            if cli.compress {
-                anyhow::bail!(
-                    "Compression is not yet supported for zingo ingestion"
-                );
-            }
-            if cli.encrypt {
-                anyhow::bail!(
-                    "Encryption is not yet supported for zingo ingestion"
+    // With JSON errors, nothing but JSON is written to stderr.
+    let json_stderr = cli.error_format == ErrorFormat::Json;
+    let mut progress = if cli.quiet
+        || (json_stderr && cli.progress == ProgressFormat::Human)
+    {
+        Progress::quiet()
+    } else {
+        Progress::new(cli.progress)
+    };
+    if json_stderr {
+        progress = progress.with_json_reports();
+    }
+    if let Err(e) = inner_main(&cli, &progress) {
+        // The error is printed below; this records it in the log file.
+        tracing::debug!("Failed: {:#}", e);
+        match cli.error_format {
+            ErrorFormat::Human => {
+                eprint!(
+                    "{}",
+                    error::format_error(&e, logging::stderr_styled())
                 );
             }
-            match cli.to {
-                OutputFormat::Dump => {
-                    let dump = zingo_cmd::dump_wallet(&input_path)?;
-                    let mut output: Box<dyn Write> =
-                        match cli.output_file.as_str() {
-                            "-" => Box::new(io::stdout()),
-                            path => Box::new(File::create(path).with_context(
-                                || {
-                                    format!(
-                                        "Failed to create output file: {}",
-                                        path
-                                    )
-                                },
-                            )?),
-                        };
-                    writeln!(output, "{}", dump)?;
-                }
-                other => {
-                    anyhow::bail!(
-                        "Zingo ingestion supports only --to dump for now (requested {:?})",
-                        other
-                    );
-                }
+            ErrorFormat::Json => {
+                let input = match &cli.command {
+                    Some(command) => command.input_file(),
+                    None => Path::new(&cli.input_file),
+                };
+                let stage = progress.current_stage().map(|stage| stage.id());
+                eprintln!("{}", error::error_json(&e, stage, Some(input)));
             }
         }
-    };
+        std::process::exit(error::exit_code(&e));
+    }
+}
 
+#[doc(hidden)]
+fn inner_main(cli: &Cli, progress: &Progress) -> Result<()> {
+    logging::init(
+        cli.verbose.min(i8::MAX as u8) as i8 - i8::from(cli.quiet),
+        cli.log_file.as_deref(),
+        cli.error_format == ErrorFormat::Json,
+    )?;
+
+    if let Some(command) = &cli.command {
+        let output = match command {
+            Commands::Balance(args) => args.exec()?,
+            Commands::Convert(args) => return args.run(progress),
+            Commands::Inspect(args) => args.exec()?,
+            Commands::Keys(args) => args.exec()?,
+            Commands::Split(args) => args.exec()?,
+            Commands::Zcashd(args) => args.exec()?,
+        };
+        println!("{}", output);
+        return Ok(());
+    }
+
+    let output = match cli.output_file.as_str() {
+        "-" => OutputSink::Stdout,
+        path => OutputSink::File(PathBuf::from(path)),
+    };
+    let mut migrator = Migrator::new(&cli.from, &cli.input_file)
+        .to(&cli.to)
+        .output(output)
+        .compress(cli.compress)
+        .rescan(cli.rescan)
+        .verify(cli.verify)
+        .drop_sprout(cli.drop_sprout)
+        .expect_network(cli.expect_network)
+        .strict(cli.strict)
+        .filter(cli.filter_args.filter())
+        .birthday(cli.birthday)
+        .decrypt_with(|| Ok(prompt_password("Enter decryption password: ")?))
+        .on_report({
+            let progress = progress.clone();
+            move |report| progress.report(report)
+        });
+    if cli.encrypt {
+        migrator = migrator.encrypt_with(|| {
+            Ok(prompt_password("Enter encryption password: ")?)
+        });
+    }
+    migrator.run(progress)?;
     Ok(())
 }
@@ -0,0 +1,194 @@
+use anyhow::{Result, bail};
+use bc_envelope::prelude::*;
+use zewif::{Account, ProtocolAddress, Transaction, Zewif, ZewifWallet};
+
+use crate::{keys::seed_bytes, metadata, network::network_name};
+
+/// Merges wallets migrated from several sources into one `Zewif`, given
+/// each with a description of its source, such as its file name.
+///
+/// Wallets with the same seed on the same network are merged into one, as
+/// are wallets without a seed that share an address. Within a merged
+/// wallet, accounts with the same ZIP-32 index, or without one but sharing
+/// an address, are merged too. An address found more than once is kept
+/// once, with the keys of every copy, and a transaction found more than
+/// once is kept as its most complete copy. The sources of each wallet are
+/// recorded in the metadata as `merged-sources`, and the metadata of every
+/// source is carried over.
+///
+/// Fails if the wallets belong to different networks.
+pub fn merge(sources: Vec<(String, Zewif)>) -> Result<Zewif> {
+    let mut networks = sources
+        .iter()
+        .flat_map(|(_, zewif)| zewif.wallets())
+        .map(|wallet| wallet.network());
+    if let Some(first) = networks.next()
+        && let Some(other) = networks.find(|network| *network != first)
+    {
+        bail!(
+            "Cannot merge {} wallets with {} wallets",
+            network_name(first),
+            network_name(other)
+        );
+    }
+
+    let mut merged = Zewif::new();
+    let mut wallets: Vec<(ZewifWallet, Option<Vec<u8>>, Vec<String>)> =
+        Vec::new();
+    let mut duplicate_transactions = 0u64;
+    for (source, zewif) in &sources {
+        for wallet in zewif.wallets() {
+            let seed = wallet.seed_material().map(seed_bytes).transpose()?;
+            let existing = wallets.iter_mut().find(|(w, s, _)| {
+                w.network() == wallet.network()
+                    && match (s, &seed) {
+                        (Some(s), Some(seed)) => s == seed,
+                        (None, None) => shares_address(w, wallet),
+                        _ => false,
+                    }
+            });
+            match existing {
+                Some((target, _, wallet_sources)) => {
+                    merge_accounts(target, wallet);
+                    if !wallet_sources.contains(source) {
+                        wallet_sources.push(source.clone());
+                    }
+                }
+                None => {
+                    wallets.push((wallet.clone(), seed, vec![source.clone()]))
+                }
+            }
+        }
+        for (txid, tx) in zewif.transactions() {
+            let keep = match merged.transactions().get(txid) {
+                Some(existing) => {
+                    duplicate_transactions += 1;
+                    completeness(tx) > completeness(existing)
+                }
+                None => true,
+            };
+            if keep {
+                merged.add_transaction(*txid, tx.clone());
+            }
+        }
+        metadata::copy(zewif, &mut merged)?;
+    }
+
+    let mut payload = Envelope::new("mergedSources")
+        .add_assertion("duplicateTransactions", duplicate_transactions);
+    for (index, (wallet, _, wallet_sources)) in wallets.into_iter().enumerate()
+    {
+        let mut entry = Envelope::new(index as u64);
+        for source in wallet_sources {
+            entry = entry.add_assertion("source", source);
+        }
+        payload = payload.add_assertion("wallet", entry);
+        merged.add_wallet(wallet);
+    }
+    metadata::record(&mut merged, "merged-sources", payload);
+    Ok(merged)
+}
+
+fn address_strings(account: &Account) -> impl Iterator<Item = String> + '_ {
+    account.addresses().iter().map(|a| a.as_string())
+}
+
+fn shares_address(a: &ZewifWallet, b: &ZewifWallet) -> bool {
+    let addresses: Vec<String> =
+        a.accounts().iter().flat_map(address_strings).collect();
+    b.accounts()
+        .iter()
+        .flat_map(address_strings)
+        .any(|address| addresses.contains(&address))
+}
+
+fn same_account(a: &Account, b: &Account) -> bool {
+    match (a.zip32_account_id(), b.zip32_account_id()) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => address_strings(b)
+            .any(|address| address_strings(a).any(|other| other == address)),
+        _ => false,
+    }
+}
+
+/// Merges the accounts of `source` into `target`.
+fn merge_accounts(target: &mut ZewifWallet, source: &ZewifWallet) {
+    for account in source.accounts() {
+        match target
+            .accounts_mut()
+            .into_iter()
+            .find(|a| same_account(a, account))
+        {
+            Some(existing) => merge_account(existing, account),
+            None => target.add_account(account.clone()),
+        }
+    }
+}
+
+/// Adds the addresses and transactions of `source` that `target` lacks,
+/// and the keys of its addresses that `target`'s copies lack, keeping the
+/// earlier of their birthdays.
+fn merge_account(target: &mut Account, source: &Account) {
+    for address in source.addresses() {
+        let address_string = address.as_string();
+        match target
+            .addresses_mut()
+            .iter_mut()
+            .find(|a| a.as_string() == address_string)
+        {
+            Some(existing) => {
+                let mut merged = existing.address().clone();
+                merge_keys(&mut merged, address.address());
+                existing.set_address(merged);
+            }
+            None => target.add_address(address.clone()),
+        }
+    }
+    for txid in source.relevant_transactions() {
+        target.add_relevant_transaction(*txid);
+    }
+    if let Some(birthday) = source.birthday_height()
+        && target.birthday_height().is_none_or(|b| birthday < b)
+    {
+        target.set_birthday_height(Some(birthday));
+    }
+}
+
+/// Adds the keys of `source`, a copy of the same address, that `target`
+/// lacks.
+fn merge_keys(target: &mut ProtocolAddress, source: &ProtocolAddress) {
+    match (target, source) {
+        (
+            ProtocolAddress::Shielded(target),
+            ProtocolAddress::Shielded(source),
+        ) => {
+            if target.spending_key().is_none()
+                && let Some(key) = source.spending_key()
+            {
+                target.set_spending_key(key.clone());
+            }
+            if target.incoming_viewing_key().is_none()
+                && let Some(ivk) = source.incoming_viewing_key()
+            {
+                target.set_incoming_viewing_key(ivk.clone());
+            }
+        }
+        (
+            ProtocolAddress::Transparent(target),
+            ProtocolAddress::Transparent(source),
+        ) => {
+            if target.spend_authority().is_none()
+                && let Some(authority) = source.spend_authority()
+            {
+                target.set_spend_authority(authority.clone());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// How much of a transaction a copy holds, as the number of its fields that
+/// are set, such as its raw bytes, mined height and bundles.
+fn completeness(tx: &Transaction) -> usize {
+    Envelope::from(tx.clone()).assertions().len()
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use bc_envelope::prelude::*;
+use zewif::Zewif;
+
+/// The vendor recorded on every attachment `zmigrate` adds to a `Zewif`.
+pub const VENDOR: &str = "com.blockchaincommons.zmigrate";
+
+/// The `conformsTo` of every attachment `zmigrate` adds, followed by its
+/// kind.
+const CONFORMS_TO_BASE: &str = "https://github.com/BlockchainCommons/zmigrate/";
+
+/// Records a change `zmigrate` made to a `Zewif` during migration as an
+/// attachment, so a later reader can tell the output is not a faithful copy
+/// of its source.
+///
+/// `kind` names the change, and `payload` describes it.
+pub fn record(zewif: &mut Zewif, kind: &str, payload: Envelope) {
+    let conforms_to = format!("{}{}", CONFORMS_TO_BASE, kind);
+    zewif.add_attachment(payload, VENDOR, Some(&conforms_to));
+}
+
+/// The kind of an attachment `zmigrate` recorded, given its `conformsTo`,
+/// or `None` for attachments added by others.
+pub fn kind(conforms_to: Option<&str>) -> Option<&str> {
+    conforms_to.and_then(|c| c.strip_prefix(CONFORMS_TO_BASE))
+}
+
+/// The payloads of the attachments of `kind` that `zmigrate` recorded in
+/// `zewif`.
+pub fn payloads(zewif: &Zewif, kind: &str) -> Result<Vec<Envelope>> {
+    let conforms_to = format!("{}{}", CONFORMS_TO_BASE, kind);
+    let carrier = zewif
+        .attachments()
+        .add_to_envelope(Envelope::new("attachments"));
+    let mut payloads = Vec::new();
+    for attachment in carrier.attachments()? {
+        if attachment.attachment_conforms_to()?.as_deref()
+            == Some(conforms_to.as_str())
+        {
+            payloads.push(attachment.attachment_payload()?);
+        }
+    }
+    Ok(payloads)
+}
+
+/// Adds the attachments of `from`, whoever recorded them, to `to`.
+pub fn copy(from: &Zewif, to: &mut Zewif) -> Result<()> {
+    copy_if(from, to, |_| true).map(|_| ())
+}
+
+/// Adds the attachments of `from` for which `keep` returns `true`, given
+/// their `conformsTo`, to `to`. Returns the `conformsTo` of those left out.
+pub fn copy_if(
+    from: &Zewif,
+    to: &mut Zewif,
+    keep: impl Fn(Option<&str>) -> bool,
+) -> Result<Vec<String>> {
+    let carrier = from
+        .attachments()
+        .add_to_envelope(Envelope::new("attachments"));
+    let mut skipped = Vec::new();
+    for attachment in carrier.attachments()? {
+        let conforms_to = attachment.attachment_conforms_to()?;
+        if !keep(conforms_to.as_deref()) {
+            skipped.push(conforms_to.unwrap_or_default());
+            continue;
+        }
+        to.add_attachment(
+            attachment.attachment_payload()?,
+            &attachment.attachment_vendor()?,
+            conforms_to.as_deref(),
+        );
+    }
+    Ok(skipped)
+}
@@ -0,0 +1,464 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use anyhow::{Context, Result, bail};
+use zewif::{BlockHeight, Zewif};
+
+use crate::{
+    balance::SaplingNullifiers,
+    birthday,
+    filter::TransactionFilter,
+    format::{
+        PasswordFn, ReadOptions, Registry, WriteOptions, ensure_plain_dump,
+    },
+    merge,
+    network::{self, NetworkName},
+    progress::{Progress, Stage},
+    source::WalletSource,
+    sprout::SproutReport,
+    verify::{self, VerificationReport},
+};
+
+/// Where a [`Migrator`] writes its output.
+pub enum OutputSink {
+    Stdout,
+
+    /// A file, created only once the migration has succeeded up to the
+    /// point of writing.
+    File(PathBuf),
+
+    Writer(Box<dyn Write>),
+}
+
+impl OutputSink {
+    fn open(self) -> Result<Box<dyn Write>> {
+        Ok(match self {
+            OutputSink::Stdout => Box::new(io::stdout()),
+            OutputSink::File(path) => {
+                Box::new(File::create(&path).with_context(|| {
+                    format!("Failed to create output file: {}", path.display())
+                })?)
+            }
+            OutputSink::Writer(writer) => writer,
+        })
+    }
+}
+
+/// A report produced while a migration runs, passed to the handler set with
+/// [`Migrator::on_report`] as soon as it is available.
+#[derive(Debug, Clone, Copy)]
+pub enum Report<'a> {
+    /// The Sprout keys and notes found in a zcashd wallet. Only produced if
+    /// there are any.
+    Sprout(&'a SproutReport),
+
+    /// The result of checking every address against the wallet's keys.
+    Verification(&'a VerificationReport),
+}
+
+impl Report<'_> {
+    /// The stable identifier of the report in JSON output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Report::Sprout(_) => "sprout",
+            Report::Verification(_) => "verification",
+        }
+    }
+}
+
+impl std::fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Report::Sprout(report) => report.fmt(f),
+            Report::Verification(report) => report.fmt(f),
+        }
+    }
+}
+
+/// The outcome of a successful [`Migrator::run`].
+#[derive(Debug, Default)]
+pub struct Migration {
+    zewif: Option<Zewif>,
+    sprout_report: Option<SproutReport>,
+    verification: Option<VerificationReport>,
+}
+
+impl Migration {
+    /// The migrated wallet. `None` for formats that can only be dumped, such
+    /// as zingo, and for Zewif input written as an envelope again without
+    /// being decoded.
+    pub fn zewif(&self) -> Option<&Zewif> { self.zewif.as_ref() }
+
+    pub fn into_zewif(self) -> Option<Zewif> { self.zewif }
+
+    /// The Sprout report, for zcashd input. With several inputs, that of
+    /// the first zcashd input.
+    pub fn sprout_report(&self) -> Option<&SproutReport> {
+        self.sprout_report.as_ref()
+    }
+
+    /// The key derivation report, if verification was requested.
+    pub fn verification(&self) -> Option<&VerificationReport> {
+        self.verification.as_ref()
+    }
+}
+
+/// Converts a wallet from one format to another.
+///
+/// ```no_run
+/// # use zmigrate::{migrator::*, progress::Progress};
+/// # fn main() -> anyhow::Result<()> {
+/// let migration = Migrator::new("zcashd", "wallet.dat")
+///     .output(OutputSink::File("wallet.zewif".into()))
+///     .compress(true)
+///     .verify(true)
+///     .run(&Progress::quiet())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Formats are looked up by name in a [`Registry`], by default
+/// [`Registry::builtin`].
+///
+/// Further wallets added with [`Migrator::input`] are migrated in turn and
+/// merged into one Zewif; see [`merge::merge`].
+pub struct Migrator {
+    registry: Option<Registry>,
+    inputs: Vec<(String, WalletSource)>,
+    to: String,
+    output: OutputSink,
+    compress: bool,
+    rescan: bool,
+    encryption_password: Option<PasswordFn>,
+    decryption_password: Option<Rc<RefCell<DecryptionPassword>>>,
+    verify: bool,
+    drop_sprout: bool,
+    expect_network: Option<NetworkName>,
+    strict: bool,
+    filter: TransactionFilter,
+    birthday: Option<u32>,
+    allow_disk: bool,
+    on_report: Option<Box<dyn FnMut(Report<'_>)>>,
+}
+
+type DecryptionPassword = Box<dyn FnMut() -> Result<String>>;
+
+impl Migrator {
+    /// A migration of `input`, a wallet file path or the wallet's bytes in
+    /// the format named `from`, to Zewif binary on stdout.
+    pub fn new(
+        from: impl Into<String>,
+        input: impl Into<WalletSource>,
+    ) -> Self {
+        Self {
+            registry: None,
+            inputs: vec![(from.into(), input.into())],
+            to: "zewif".to_string(),
+            output: OutputSink::Stdout,
+            compress: false,
+            rescan: false,
+            encryption_password: None,
+            decryption_password: None,
+            verify: false,
+            drop_sprout: false,
+            expect_network: None,
+            strict: false,
+            filter: TransactionFilter::new(),
+            birthday: None,
+            allow_disk: false,
+            on_report: None,
+        }
+    }
+
+    /// Looks formats up in `registry` rather than the built-in one.
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Adds another wallet, in the format named `from`, to be merged with
+    /// the others.
+    pub fn input(
+        mut self,
+        from: impl Into<String>,
+        input: impl Into<WalletSource>,
+    ) -> Self {
+        self.inputs.push((from.into(), input.into()));
+        self
+    }
+
+    /// The name of the output format.
+    pub fn to(mut self, to: impl Into<String>) -> Self {
+        self.to = to.into();
+        self
+    }
+
+    pub fn output(mut self, output: OutputSink) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Compresses the output envelope.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Writes formats that have no place for transaction records, such as
+    /// zingo wallets, without the input's transactions, leaving the wallet
+    /// to rebuild them by rescanning. Without it such output fails if the
+    /// input has transactions.
+    pub fn rescan(mut self, rescan: bool) -> Self {
+        self.rescan = rescan;
+        self
+    }
+
+    /// Encrypts the output envelope with a password, which is only asked for
+    /// once the migration has succeeded up to the point of encrypting.
+    pub fn encrypt_with(
+        mut self,
+        password: impl FnOnce() -> Result<String> + 'static,
+    ) -> Self {
+        self.encryption_password = Some(Box::new(password));
+        self
+    }
+
+    /// Supplies the password for encrypted Zewif input. It is asked for
+    /// once for each input that is encrypted.
+    pub fn decrypt_with(
+        mut self,
+        password: impl FnMut() -> Result<String> + 'static,
+    ) -> Self {
+        self.decryption_password =
+            Some(Rc::new(RefCell::new(Box::new(password))));
+        self
+    }
+
+    /// Checks that every address can be re-derived from the wallet's keys,
+    /// failing if any cannot.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Leaves Sprout addresses out of a zcashd migration, recording their
+    /// exclusion in the output's metadata. When merging, it applies to the
+    /// inputs whose format supports it, and fails if none does.
+    pub fn drop_sprout(mut self, drop_sprout: bool) -> Self {
+        self.drop_sprout = drop_sprout;
+        self
+    }
+
+    /// Lets wallets given as bytes be written to a temporary file for
+    /// readers that need one, on systems that can't hold them in an
+    /// anonymous in-memory file. Off by default, as the file holds the
+    /// plaintext wallet.
+    pub fn allow_disk(mut self, allow_disk: bool) -> Self {
+        self.allow_disk = allow_disk;
+        self
+    }
+
+    /// Fails unless the input wallet belongs to this network.
+    pub fn expect_network(mut self, network: Option<NetworkName>) -> Self {
+        self.expect_network = network;
+        self
+    }
+
+    /// Fails if the reader can't parse all of the input, such as a zingo
+    /// wallet with bytes left over.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Trims the migrated wallet's transaction history before it is
+    /// written.
+    pub fn filter(mut self, filter: TransactionFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the birthday of every account to this height, overriding the
+    /// one read or estimated.
+    pub fn birthday(mut self, height: Option<u32>) -> Self {
+        self.birthday = height;
+        self
+    }
+
+    /// Calls `handler` with each report as soon as it is produced, including
+    /// when the migration later fails because of it.
+    pub fn on_report(
+        mut self,
+        handler: impl FnMut(Report<'_>) + 'static,
+    ) -> Self {
+        self.on_report = Some(Box::new(handler));
+        self
+    }
+
+    /// Runs the migration, reporting each stage to `progress`.
+    pub fn run(mut self, progress: &Progress) -> Result<Migration> {
+        let registry = self.registry.take().unwrap_or_default();
+        let readers = self
+            .inputs
+            .iter()
+            .map(|(from, _)| registry.reader(from))
+            .collect::<Result<Vec<_>>>()?;
+        let writer = registry.writer(&self.to)?;
+        // Sprout addresses are dropped from the inputs whose readers can
+        // drop them, so at least one must.
+        if self.drop_sprout && !readers.iter().any(|r| r.drops_sprout()) {
+            bail!(
+                "--drop-sprout does not apply to {} wallets",
+                readers[0].name()
+            );
+        }
+        let mut write_options = WriteOptions::new()
+            .with_compress(self.compress)
+            .with_rescan(self.rescan)
+            .with_password(self.encryption_password.take());
+
+        if writer.name() == "dump" {
+            ensure_plain_dump(&write_options)?;
+        }
+
+        let inputs = std::mem::take(&mut self.inputs);
+        if let ([reader], [(_, input)]) = (&readers[..], &inputs[..])
+            && writer.name() == "dump"
+            && let Some(dump) = reader.dump(input, &mut self.read_options())
+        {
+            let dump = dump?;
+            let stage = progress.start(Stage::Write);
+            writeln!(self.output.open()?, "{}", dump)?;
+            stage.finish();
+            return Ok(Migration::default());
+        }
+        // With nothing to do to the wallet itself, re-encode the envelope as
+        // read, so that files this build can't decode can still be
+        // converted, say to Envelope Notation to see what's wrong with them.
+        if let ([reader], [(_, input)]) = (&readers[..], &inputs[..])
+            && writer.writes_envelopes()
+            && !self.changes_wallet()
+            && let Some(envelope) =
+                reader.envelope(input, &mut self.read_options(), progress)
+        {
+            let encoded = writer.write_envelope(
+                envelope?,
+                &mut write_options,
+                progress,
+            )?;
+            let stage = progress.start(Stage::Write);
+            self.output.open()?.write_all(&encoded)?;
+            stage.finish();
+            return Ok(Migration::default());
+        }
+
+        let mut sources = Vec::new();
+        let mut sprout_report = None;
+        let mut sapling_nullifiers = SaplingNullifiers::new();
+        for (reader, (_, input)) in readers.iter().zip(&inputs) {
+            let outcome =
+                reader.read(input, &mut self.read_options(), progress);
+            // With several inputs, say which one failed.
+            let outcome = if inputs.len() > 1 {
+                outcome.with_context(|| format!("Reading {}", input))?
+            } else {
+                outcome?
+            };
+            sapling_nullifiers.extend(outcome.sapling_nullifiers().clone());
+            let (zewif, report) = outcome.into_parts();
+            if let Some(report) = &report
+                && report.has_sprout()
+            {
+                self.report(Report::Sprout(report));
+            }
+            sprout_report = sprout_report.or(report);
+            sources.push((input.to_string(), zewif));
+        }
+        let mut zewif = if sources.len() == 1 {
+            sources.pop().unwrap().1
+        } else {
+            let stage = progress.start(Stage::Merge);
+            let zewif = merge::merge(sources)?;
+            stage.finish_with_count(zewif.wallets().len(), "wallets");
+            zewif
+        };
+        if let Some(expected) = self.expect_network {
+            network::ensure_network(&zewif, expected)?;
+        }
+        if let Some(height) = self.birthday {
+            birthday::override_birthday(&mut zewif, BlockHeight::from(height));
+        }
+        let zewif = if self.filter.is_empty() {
+            zewif
+        } else {
+            let stage = progress.start(Stage::Filter);
+            let zewif = self
+                .filter
+                .apply_with_nullifiers(&zewif, &sapling_nullifiers)?;
+            stage.finish_with_count(zewif.transactions().len(), "transactions");
+            zewif
+        };
+        let verification = if self.verify {
+            Some(self.verify_zewif(&zewif, progress)?)
+        } else {
+            None
+        };
+        // Encode before creating the output so a failure doesn't leave a
+        // partial output file behind.
+        let encoded = writer.write(&zewif, &mut write_options, progress)?;
+        let stage = progress.start(Stage::Write);
+        self.output.open()?.write_all(&encoded)?;
+        stage.finish();
+        Ok(Migration {
+            zewif: Some(zewif),
+            sprout_report,
+            verification,
+        })
+    }
+
+    /// The options for reading one input.
+    fn read_options(&self) -> ReadOptions {
+        let password = self.decryption_password.clone().map(|password| {
+            Box::new(move || (*password.borrow_mut())()) as PasswordFn
+        });
+        ReadOptions::new()
+            .with_drop_sprout(self.drop_sprout)
+            .with_strict(self.strict)
+            .with_expect_network(self.expect_network)
+            .with_password(password)
+            .with_allow_disk(self.allow_disk)
+    }
+
+    /// Whether the wallet must be decoded, to be checked or changed before
+    /// it is written.
+    fn changes_wallet(&self) -> bool {
+        self.verify
+            || self.expect_network.is_some()
+            || self.birthday.is_some()
+            || !self.filter.is_empty()
+    }
+
+    fn report(&mut self, report: Report<'_>) {
+        if let Some(handler) = &mut self.on_report {
+            handler(report);
+        }
+    }
+
+    fn verify_zewif(
+        &mut self,
+        zewif: &Zewif,
+        progress: &Progress,
+    ) -> Result<VerificationReport> {
+        let stage = progress.start(Stage::Verify);
+        let report = verify::verify_zewif(zewif)?;
+        stage.finish_with_count(report.results().len(), "addresses");
+        self.report(Report::Verification(&report));
+        verify::ensure_clean(&report)?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use zcash_address::{ConversionError, TryFromAddress, ZcashAddress, unified};
+use zcash_protocol::consensus::NetworkType;
+use zewif::{Network, Zewif};
+
+use crate::keys::network_type;
+
+/// A network a wallet can be expected to belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NetworkName {
+    /// The Zcash main network
+    #[value(alias = "main")]
+    Mainnet,
+
+    /// The Zcash test network
+    #[value(alias = "test")]
+    Testnet,
+
+    /// A local regression-test network
+    Regtest,
+}
+
+impl From<NetworkName> for Network {
+    fn from(name: NetworkName) -> Self {
+        match name {
+            NetworkName::Mainnet => Network::Main,
+            NetworkName::Testnet => Network::Test,
+            NetworkName::Regtest => Network::Regtest,
+        }
+    }
+}
+
+/// The name a network is shown with in summaries.
+pub fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Main => "mainnet",
+        Network::Test => "testnet",
+        Network::Regtest => "regtest",
+    }
+}
+
+/// The network an encoded address belongs to.
+struct AddressNetwork(NetworkType);
+
+impl TryFromAddress for AddressNetwork {
+    type Error = ();
+
+    fn try_from_sprout(
+        net: NetworkType,
+        _data: [u8; 64],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+
+    fn try_from_sapling(
+        net: NetworkType,
+        _data: [u8; 43],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+
+    fn try_from_unified(
+        net: NetworkType,
+        _data: unified::Address,
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+
+    fn try_from_transparent_p2pkh(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+
+    fn try_from_transparent_p2sh(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+
+    fn try_from_tex(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(Self(net))
+    }
+}
+
+/// The network encoded in an address string, if it can be decoded.
+pub fn address_network(address: &str) -> Option<NetworkType> {
+    let address = ZcashAddress::try_from_encoded(address).ok()?;
+    address.convert::<AddressNetwork>().ok().map(|n| n.0)
+}
+
+/// Whether an address encoded for `address_network` can belong to a wallet
+/// on `wallet_network`.
+fn is_compatible(
+    wallet_network: NetworkType,
+    address_network: NetworkType,
+) -> bool {
+    // Regtest reuses the testnet transparent prefixes, so regtest transparent
+    // addresses decode as testnet ones.
+    wallet_network == address_network
+        || (wallet_network == NetworkType::Regtest
+            && address_network == NetworkType::Test)
+}
+
+/// Determines the network of every wallet in `zewif` from its stored network
+/// and its address prefixes, failing if they disagree or if the wallets
+/// belong to different networks.
+///
+/// Returns `None` if `zewif` contains no wallets.
+pub fn detect_network(zewif: &Zewif) -> Result<Option<Network>> {
+    let mut detected: Option<Network> = None;
+    for (wallet_index, wallet) in zewif.wallets().iter().enumerate() {
+        let network = wallet.network();
+        for address in wallet.accounts().iter().flat_map(|a| a.addresses()) {
+            let address = address.as_string();
+            if let Some(address_network) = address_network(&address)
+                && !is_compatible(network_type(network), address_network)
+            {
+                bail!(
+                    "Wallet {} is recorded as a {} wallet, but address {} is not a {} address",
+                    wallet_index,
+                    network_name(network),
+                    address,
+                    network_name(network)
+                );
+            }
+        }
+        match detected {
+            Some(detected) if detected != network => bail!(
+                "Input mixes {} and {} wallets",
+                network_name(detected),
+                network_name(network)
+            ),
+            _ => detected = Some(network),
+        }
+    }
+    Ok(detected)
+}
+
+/// Fails unless every wallet in `zewif` belongs to the `expected` network.
+pub fn ensure_network(zewif: &Zewif, expected: NetworkName) -> Result<()> {
+    let expected = Network::from(expected);
+    match detect_network(zewif)? {
+        Some(network) if network != expected => bail!(
+            "Expected a {} wallet, but the input is a {} wallet",
+            network_name(expected),
+            network_name(network)
+        ),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde_json::json;
+use tracing::{info, info_span, span::EnteredSpan};
+
+use crate::migrator::Report;
+
+/// How progress is reported on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// One human-readable line per stage
+    Human,
+
+    /// One JSON object per line, for driving a GUI
+    Json,
+}
+
+/// A stage of the migration pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// A step of reading a wallet, defined by its reader.
+    Read(ReadStage),
+    Migrate,
+    Merge,
+    Filter,
+    Verify,
+    Compress,
+    Encrypt,
+    Write,
+}
+
+impl Stage {
+    /// The stable identifier of the stage in JSON progress.
+    pub fn id(self) -> &'static str {
+        match self {
+            Stage::Read(stage) => stage.id,
+            Stage::Migrate => "migrate",
+            Stage::Merge => "merge",
+            Stage::Filter => "filter",
+            Stage::Verify => "verify",
+            Stage::Compress => "compress",
+            Stage::Encrypt => "encrypt",
+            Stage::Write => "write",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Read(stage) => stage.label,
+            Stage::Migrate => "Migrating to Zewif",
+            Stage::Merge => "Merging wallets",
+            Stage::Filter => "Filtering transactions",
+            Stage::Verify => "Verifying key derivation",
+            Stage::Compress => "Compressing",
+            Stage::Encrypt => "Encrypting",
+            Stage::Write => "Writing output",
+        }
+    }
+}
+
+/// A format-specific step of reading a wallet, such as parsing zcashd's
+/// records. Each reader defines its own, so adding a format doesn't mean
+/// adding to [`Stage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadStage {
+    id: &'static str,
+    label: &'static str,
+}
+
+impl ReadStage {
+    /// A step identified in JSON progress by `id`, which should not change
+    /// once released, and shown to people as `label`.
+    pub const fn new(id: &'static str, label: &'static str) -> Self {
+        Self { id, label }
+    }
+}
+
+/// Reports pipeline progress on stderr, or nothing when quiet.
+///
+/// Each stage also runs inside a `stage` tracing span, whether or not
+/// progress is reported.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    format: Option<ProgressFormat>,
+    reports: ProgressFormat,
+    start: Instant,
+    current: Cell<Option<Stage>>,
+}
+
+impl Progress {
+    pub fn new(format: ProgressFormat) -> Self {
+        Self {
+            format: Some(format),
+            reports: format,
+            start: Instant::now(),
+            current: Cell::new(None),
+        }
+    }
+
+    /// A reporter that reports nothing.
+    pub fn quiet() -> Self {
+        Self {
+            format: None,
+            reports: ProgressFormat::Human,
+            start: Instant::now(),
+            current: Cell::new(None),
+        }
+    }
+
+    /// Prints reports as JSON, whatever the progress format.
+    pub fn with_json_reports(mut self) -> Self {
+        self.reports = ProgressFormat::Json;
+        self
+    }
+
+    /// Prints a report produced by the pipeline on stderr. Reports are
+    /// printed even when progress is not.
+    pub fn report(&self, report: Report<'_>) {
+        match self.reports {
+            ProgressFormat::Human => eprintln!("{}", report),
+            ProgressFormat::Json => eprintln!(
+                "{}",
+                json!({
+                    "event": "report",
+                    "report": report.id(),
+                    "message": report.to_string(),
+                })
+            ),
+        }
+    }
+
+    /// The stage that was started but not finished, if any. After a failure
+    /// this is the stage that failed.
+    pub fn current_stage(&self) -> Option<Stage> { self.current.get() }
+
+    /// Reports the start of `stage`, returning a timer to report its end.
+    pub fn start(&self, stage: Stage) -> StageTimer<'_> {
+        let span = info_span!("stage", stage = stage.id()).entered();
+        self.current.set(Some(stage));
+        match self.format {
+            Some(ProgressFormat::Human) => {
+                eprintln!("⏳ {}...", stage.label());
+            }
+            Some(ProgressFormat::Json) => {
+                eprintln!(
+                    "{}",
+                    json!({
+                        "event": "start",
+                        "stage": stage.id(),
+                        "elapsed_ms": millis(self.start.elapsed()),
+                    })
+                );
+            }
+            None => {}
+        }
+        StageTimer {
+            progress: self,
+            stage,
+            start: Instant::now(),
+            _span: span,
+        }
+    }
+}
+
+/// A running stage; see [`Progress::start`].
+#[must_use = "a stage should be finished to report its end"]
+pub struct StageTimer<'a> {
+    progress: &'a Progress,
+    stage: Stage,
+    start: Instant,
+    _span: EnteredSpan,
+}
+
+impl StageTimer<'_> {
+    /// Reports the end of the stage.
+    pub fn finish(self) { self.report(None) }
+
+    /// Reports the end of the stage and how many items, such as
+    /// `"transactions"`, it processed.
+    pub fn finish_with_count(self, count: usize, unit: &str) {
+        self.report(Some((count, unit)))
+    }
+
+    fn report(self, count: Option<(usize, &str)>) {
+        let elapsed = self.start.elapsed();
+        self.progress.current.set(None);
+        match count {
+            Some((count, unit)) => info!(
+                elapsed_ms = millis(elapsed),
+                count,
+                unit,
+                "{} finished",
+                self.stage.label()
+            ),
+            None => info!(
+                elapsed_ms = millis(elapsed),
+                "{} finished",
+                self.stage.label()
+            ),
+        }
+        match self.progress.format {
+            Some(ProgressFormat::Human) => match count {
+                Some((count, unit)) => eprintln!(
+                    "✅ {}: {} {} ({:.1}s)",
+                    self.stage.label(),
+                    count,
+                    unit,
+                    elapsed.as_secs_f64()
+                ),
+                None => eprintln!(
+                    "✅ {} ({:.1}s)",
+                    self.stage.label(),
+                    elapsed.as_secs_f64()
+                ),
+            },
+            Some(ProgressFormat::Json) => {
+                let mut event = json!({
+                    "event": "finish",
+                    "stage": self.stage.id(),
+                    "stage_ms": millis(elapsed),
+                    "elapsed_ms": millis(self.progress.start.elapsed()),
+                });
+                if let Some((count, unit)) = count {
+                    event["count"] = json!(count);
+                    event["unit"] = json!(unit);
+                }
+                eprintln!("{}", event);
+            }
+            None => {}
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 { duration.as_millis() as u64 }
@@ -0,0 +1,362 @@
+use std::{
+    fmt::Write as _,
+    fs::DirBuilder,
+    io::{self, IsTerminal, Write as _},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use serde_json::{Value, json};
+use zewif_zcashd::{BDBDump, ZcashdDump};
+
+use crate::file_args::{FileArgs, FileArgsLike, write_secret_file};
+
+/// Keynames of zcashd records whose values hold spending keys or seeds,
+/// encrypted or not.
+pub const SECRET_KEYNAMES: [&str; 11] = [
+    "key",
+    "ckey",
+    "wkey",
+    "zkey",
+    "czkey",
+    "sapzkey",
+    "csapzkey",
+    "hdseed",
+    "chdseed",
+    "mnemonicphrase",
+    "cmnemonicphrase",
+];
+
+/// List the raw BerkeleyDB records of a zcashd wallet file
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// Only show records with this keyname; may be repeated
+    #[arg(long = "keyname", value_name = "KEYNAME")]
+    keynames: Vec<String>,
+
+    /// Output as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the selected records as JSON to this file, which must not
+    /// already exist
+    #[arg(long, value_name = "PATH")]
+    export_json: Option<PathBuf>,
+
+    /// Write the selected records to a new minimal BerkeleyDB wallet file,
+    /// which must not already exist; requires the `db_load` tool
+    #[arg(long, value_name = "PATH")]
+    export_wallet: Option<PathBuf>,
+
+    /// Show and export the values of records holding spending keys or
+    /// seeds. They are still redacted when stdout is not a terminal
+    #[arg(long)]
+    force: bool,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf { &self.file_args.file }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        let records = read_records(self.file(), &self.keynames)?;
+        let secret_keynames = secret_keynames(&records);
+        let exporting =
+            self.export_json.is_some() || self.export_wallet.is_some();
+        if !secret_keynames.is_empty() && exporting && !self.force {
+            bail!(
+                "The selected records include spending keys or seeds ({}); \
+                 use --force to export them, or --keyname to leave them out",
+                secret_keynames.join(", ")
+            );
+        }
+        let shown = if self.force && io::stdout().is_terminal() {
+            records.clone()
+        } else {
+            redact_secrets(&records)
+        };
+        let mut output = if self.json {
+            serde_json::to_string_pretty(&records_to_json(&shown))?
+        } else {
+            format_records(&shown)?
+        };
+        if let Some(path) = &self.export_json {
+            write_secret_file(
+                path,
+                &serde_json::to_string_pretty(&records_to_json(&records))?,
+            )?;
+            write!(
+                output,
+                "\n---\n{} record(s) written to {}",
+                records.len(),
+                path.display()
+            )?;
+        }
+        if let Some(path) = &self.export_wallet {
+            export_wallet(&records, path)?;
+            write!(
+                output,
+                "\n---\n{} record(s) written to {}",
+                records.len(),
+                path.display()
+            )?;
+        }
+        Ok(output)
+    }
+}
+
+/// One raw key/value record of a zcashd wallet.
+#[derive(Debug, Clone)]
+pub struct RawRecord {
+    keyname: String,
+    key_data: Vec<u8>,
+    value: Vec<u8>,
+    key_text: String,
+    value_text: String,
+    redacted: bool,
+}
+
+impl RawRecord {
+    pub fn keyname(&self) -> &str { &self.keyname }
+
+    /// The part of the key that follows the keyname.
+    pub fn key_data(&self) -> &[u8] { &self.key_data }
+
+    /// The value, which is empty if it was redacted.
+    pub fn value(&self) -> &[u8] { &self.value }
+
+    /// The key as rendered by the zcashd parser.
+    pub fn key_text(&self) -> &str { &self.key_text }
+
+    /// The value as rendered by the zcashd parser.
+    pub fn value_text(&self) -> &str { &self.value_text }
+
+    /// Whether the value holds spending keys or seeds; see
+    /// [`SECRET_KEYNAMES`].
+    pub fn is_secret(&self) -> bool {
+        SECRET_KEYNAMES.contains(&self.keyname.as_str())
+    }
+
+    /// Whether the value was left out by [`redact_secrets`].
+    pub fn is_redacted(&self) -> bool { self.redacted }
+
+    /// The complete BerkeleyDB key: the length-prefixed keyname followed by
+    /// the key data.
+    pub fn raw_key(&self) -> Vec<u8> {
+        let mut key = Vec::new();
+        write_compact_size(&mut key, self.keyname.len() as u64);
+        key.extend_from_slice(self.keyname.as_bytes());
+        key.extend_from_slice(&self.key_data);
+        key
+    }
+
+    pub fn to_json(&self) -> Value {
+        if self.redacted {
+            return json!({
+                "keyname": self.keyname,
+                "key": hex::encode(&self.key_data),
+                "key_text": self.key_text,
+                "redacted": true,
+            });
+        }
+        json!({
+            "keyname": self.keyname,
+            "key": hex::encode(&self.key_data),
+            "value": hex::encode(&self.value),
+            "key_text": self.key_text,
+            "value_text": self.value_text,
+        })
+    }
+}
+
+/// Reads the records of a zcashd wallet file, keeping only those whose
+/// keyname is in `keynames` unless it is empty. Records are sorted by
+/// keyname and then key.
+pub fn read_records(
+    file: &Path,
+    keynames: &[String],
+) -> Result<Vec<RawRecord>> {
+    let db_dump =
+        BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump, true)
+        .context("Parsing Zcashd dump")?;
+
+    let mut records: Vec<RawRecord> = zcashd_dump
+        .records()
+        .iter()
+        .filter(|(key, _)| {
+            keynames.is_empty() || keynames.contains(&key.keyname)
+        })
+        .map(|(key, value)| RawRecord {
+            keyname: key.keyname.to_string(),
+            key_data: key.data.as_ref().to_vec(),
+            value: value.as_ref().to_vec(),
+            key_text: key.to_string(),
+            value_text: value.to_string(),
+            redacted: false,
+        })
+        .collect();
+    records.sort_by(|a, b| {
+        (&a.keyname, &a.key_data).cmp(&(&b.keyname, &b.key_data))
+    });
+    Ok(records)
+}
+
+/// The distinct keynames of the records in `records` that hold spending keys
+/// or seeds.
+pub fn secret_keynames(records: &[RawRecord]) -> Vec<&str> {
+    let mut keynames: Vec<&str> = records
+        .iter()
+        .filter(|r| r.is_secret())
+        .map(|r| r.keyname())
+        .collect();
+    keynames.dedup();
+    keynames
+}
+
+/// A copy of `records` with the values of those holding spending keys or
+/// seeds left out.
+pub fn redact_secrets(records: &[RawRecord]) -> Vec<RawRecord> {
+    records
+        .iter()
+        .map(|record| {
+            if !record.is_secret() {
+                return record.clone();
+            }
+            RawRecord {
+                value: Vec::new(),
+                value_text: String::new(),
+                redacted: true,
+                ..record.clone()
+            }
+        })
+        .collect()
+}
+
+pub fn records_to_json(records: &[RawRecord]) -> Value {
+    Value::Array(records.iter().map(|r| r.to_json()).collect())
+}
+
+fn format_records(records: &[RawRecord]) -> Result<String> {
+    let mut output = String::new();
+    let mut last_keyname: Option<&str> = None;
+    for record in records {
+        if last_keyname.is_some_and(|k| k != record.keyname) {
+            writeln!(output)?;
+        }
+        last_keyname = Some(&record.keyname);
+        writeln!(output, "🔑 {}", record.keyname)?;
+        writeln!(output, "\tkey: {}", hex::encode(&record.key_data))?;
+        writeln!(output, "\tkey text: {}", record.key_text)?;
+        if record.redacted {
+            writeln!(output, "\tvalue: <redacted; use --force to show>")?;
+        } else {
+            writeln!(output, "\tvalue: {}", hex::encode(&record.value))?;
+            writeln!(output, "\tvalue text: {}", record.value_text)?;
+        }
+    }
+    write!(output, "---\n{} record(s)", records.len())?;
+    Ok(output)
+}
+
+fn write_compact_size(buf: &mut Vec<u8>, size: u64) {
+    match size {
+        0..=0xfc => buf.push(size as u8),
+        0xfd..=0xffff => {
+            buf.push(0xfd);
+            buf.extend_from_slice(&(size as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            buf.push(0xfe);
+            buf.extend_from_slice(&(size as u32).to_le_bytes());
+        }
+        _ => {
+            buf.push(0xff);
+            buf.extend_from_slice(&size.to_le_bytes());
+        }
+    }
+}
+
+/// Writes `records` to a new BerkeleyDB file in the layout zcashd uses, by
+/// feeding them to `db_load` in `db_dump` format.
+///
+/// `db_load` creates the file in a private directory next to `path`, from
+/// which it is moved into place once only the current user can read it.
+pub fn export_wallet(records: &[RawRecord], path: &Path) -> Result<()> {
+    if path.exists() {
+        bail!("Refusing to overwrite existing file: {}", path.display());
+    }
+    if records.iter().any(RawRecord::is_redacted) {
+        bail!("Refusing to export redacted records");
+    }
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let private_dir = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let mut dir_builder = DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        dir_builder.mode(0o700);
+    }
+    dir_builder.create(&private_dir).with_context(|| {
+        format!("Failed to create directory: {}", private_dir.display())
+    })?;
+    let result = load_into(records, &private_dir.join(file_name), path);
+    let _ = std::fs::remove_dir_all(&private_dir);
+    result
+}
+
+/// Runs `db_load` to create `loaded`, then restricts it to the current user
+/// and moves it to `path`.
+fn load_into(records: &[RawRecord], loaded: &Path, path: &Path) -> Result<()> {
+    let mut dump = String::from(
+        "VERSION=3\nformat=bytevalue\ndatabase=main\ntype=btree\nHEADER=END\n",
+    );
+    for record in records {
+        writeln!(dump, " {}", hex::encode(record.raw_key()))?;
+        writeln!(dump, " {}", hex::encode(&record.value))?;
+    }
+    dump.push_str("DATA=END\n");
+
+    let mut child = Command::new("db_load")
+        .arg(loaded)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Running db_load; is BerkeleyDB installed?")?;
+    child
+        .stdin
+        .take()
+        .expect("db_load stdin is piped")
+        .write_all(dump.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("db_load failed with {}", status);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(
+            loaded,
+            std::fs::Permissions::from_mode(0o600),
+        )?;
+    }
+    if path.exists() {
+        bail!("Refusing to overwrite existing file: {}", path.display());
+    }
+    std::fs::rename(loaded, path).with_context(|| {
+        format!("Failed to create file: {}", path.display())
+    })?;
+    Ok(())
+}
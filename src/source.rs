@@ -0,0 +1,226 @@
+use std::{
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+/// A wallet to read, either from a file or already in memory.
+///
+/// Wallets in memory are never written to disk unless the caller allows it:
+/// front-ends that need a file, such as zcashd's BerkeleyDB, are given an
+/// anonymous in-memory file instead, which only Linux provides; see
+/// [`with_memory_file`].
+#[derive(Clone)]
+pub enum WalletSource {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl WalletSource {
+    /// Reads a whole wallet into memory.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .context("Failed to read input")?;
+        Ok(Self::Bytes(bytes))
+    }
+
+    /// The path of a wallet file; `None` for a wallet in memory.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            WalletSource::File(path) => Some(path),
+            WalletSource::Bytes(_) => None,
+        }
+    }
+
+    /// The contents of the wallet.
+    pub fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            WalletSource::File(path) => {
+                std::fs::read(path).with_context(|| {
+                    format!("Failed to read input file: {}", path.display())
+                })
+            }
+            WalletSource::Bytes(bytes) => Ok(bytes.clone()),
+        }
+    }
+
+    /// Calls `f` with the path of the wallet file, or for a wallet in
+    /// memory, with that of a file holding it; see [`with_memory_file`] for
+    /// `allow_disk`.
+    pub fn with_path<T>(
+        &self,
+        allow_disk: bool,
+        f: impl FnOnce(&Path) -> Result<T>,
+    ) -> Result<T> {
+        match self {
+            WalletSource::File(path) => f(path),
+            WalletSource::Bytes(bytes) => {
+                with_memory_file(bytes, allow_disk, f)
+            }
+        }
+    }
+}
+
+// The contents are secret, so only their size is shown.
+impl fmt::Debug for WalletSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletSource::File(path) => {
+                f.debug_tuple("File").field(path).finish()
+            }
+            WalletSource::Bytes(bytes) => {
+                write!(f, "Bytes({} bytes)", bytes.len())
+            }
+        }
+    }
+}
+
+impl fmt::Display for WalletSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletSource::File(path) => write!(f, "{}", path.display()),
+            WalletSource::Bytes(bytes) => {
+                write!(f, "<{} bytes in memory>", bytes.len())
+            }
+        }
+    }
+}
+
+impl From<PathBuf> for WalletSource {
+    fn from(path: PathBuf) -> Self { Self::File(path) }
+}
+
+impl From<&Path> for WalletSource {
+    fn from(path: &Path) -> Self { Self::File(path.to_path_buf()) }
+}
+
+impl From<&PathBuf> for WalletSource {
+    fn from(path: &PathBuf) -> Self { Self::File(path.clone()) }
+}
+
+impl From<&str> for WalletSource {
+    fn from(path: &str) -> Self { Self::File(path.into()) }
+}
+
+impl From<&String> for WalletSource {
+    fn from(path: &String) -> Self { Self::File(path.into()) }
+}
+
+impl From<Vec<u8>> for WalletSource {
+    fn from(bytes: Vec<u8>) -> Self { Self::Bytes(bytes) }
+}
+
+impl From<&[u8]> for WalletSource {
+    fn from(bytes: &[u8]) -> Self { Self::Bytes(bytes.to_vec()) }
+}
+
+/// Calls `f` with the path of a file holding `bytes`, for tools that can
+/// only read wallets from a file.
+///
+/// This is an anonymous in-memory file, which only Linux provides. Elsewhere,
+/// or if the kernel lacks `memfd_create`, this fails unless `allow_disk` is
+/// set, in which case the plaintext wallet is written to a temporary file,
+/// in a directory only the current user can enter on Unix, and removed as
+/// soon as `f` returns.
+pub fn with_memory_file<T>(
+    bytes: &[u8],
+    allow_disk: bool,
+    f: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+    #[cfg(target_os = "linux")]
+    if let Some(file) = memory_file(bytes)? {
+        use std::os::fd::AsRawFd;
+
+        // The descriptor is open in this process, so child processes can
+        // reach it through our /proc entry. It is closed when `file` is
+        // dropped.
+        let path = PathBuf::from(format!(
+            "/proc/{}/fd/{}",
+            std::process::id(),
+            file.as_raw_fd()
+        ));
+        return f(&path);
+    }
+    if !allow_disk {
+        bail!(
+            "This system can't hold the wallet in an anonymous in-memory \
+             file, and writing it to a temporary file was not allowed"
+        );
+    }
+    with_private_file(bytes, f)
+}
+
+/// An anonymous in-memory file holding `bytes`; `None` if the kernel
+/// doesn't support them.
+#[cfg(target_os = "linux")]
+fn memory_file(bytes: &[u8]) -> Result<Option<std::fs::File>> {
+    use std::{fs::File, io::Write, os::fd::FromRawFd};
+
+    // The descriptor isn't inherited by child processes: they reach the
+    // file through our /proc entry instead.
+    // SAFETY: the name is a valid NUL-terminated string.
+    let fd = unsafe {
+        libc::memfd_create(c"zmigrate-wallet".as_ptr(), libc::MFD_CLOEXEC)
+    };
+    if fd < 0 {
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() == Some(libc::ENOSYS) {
+            return Ok(None);
+        }
+        return Err(error).context("Failed to create an in-memory file");
+    }
+    // SAFETY: `fd` is a newly created descriptor that nothing else owns.
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(bytes)
+        .context("Failed to write to an in-memory file")?;
+    Ok(Some(file))
+}
+
+/// Calls `f` with the path of a file holding `bytes`, in a new directory
+/// only the current user can enter on Unix, and removes both once `f`
+/// returns. Elsewhere the file has the temporary directory's permissions.
+fn with_private_file<T>(
+    bytes: &[u8],
+    f: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+    use std::{
+        fs::{DirBuilder, OpenOptions},
+        io::Write,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "zmigrate-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    // Fails rather than reuse a directory someone else created.
+    builder.create(&dir).with_context(|| {
+        format!("Failed to create temporary directory: {}", dir.display())
+    })?;
+
+    let path = dir.join("wallet");
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&path)
+            .and_then(|mut file| file.write_all(bytes))
+            .with_context(|| {
+                format!("Failed to write temporary file: {}", path.display())
+            })?;
+        f(&path)
+    })();
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
@@ -0,0 +1,292 @@
+use anyhow::{Result, anyhow, bail};
+use bc_envelope::prelude::*;
+use tracing::warn;
+use zewif::{Account, ProtocolAddress, Zewif, ZewifWallet};
+
+use crate::{keys::WalletKeys, metadata};
+
+/// Which part of a `Zewif` [`split`] extracts.
+#[derive(Debug, Clone, Default)]
+pub struct SplitOptions {
+    wallet: Option<usize>,
+    account: Option<String>,
+    keep_seed: bool,
+}
+
+impl SplitOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Extracts the wallet at this index, or looks for the account only in
+    /// that wallet.
+    pub fn with_wallet(mut self, wallet: Option<usize>) -> Self {
+        self.wallet = wallet;
+        self
+    }
+
+    /// Extracts the one account matching this index within its wallet,
+    /// name, or unified full viewing key.
+    pub fn with_account(mut self, account: Option<String>) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Keeps the wallet's seed when extracting an account. The seed derives
+    /// the keys of every account of the wallet, so it is dropped by default.
+    pub fn with_keep_seed(mut self, keep_seed: bool) -> Self {
+        self.keep_seed = keep_seed;
+        self
+    }
+
+    pub fn wallet(&self) -> Option<usize> { self.wallet }
+
+    pub fn account(&self) -> Option<&str> { self.account.as_deref() }
+
+    pub fn keep_seed(&self) -> bool { self.keep_seed }
+}
+
+/// Extracts one wallet or account of `zewif` into a `Zewif` of its own,
+/// with only the transactions its accounts reference.
+///
+/// When the input's one wallet is extracted, its metadata is carried over
+/// as is. Otherwise only what concerns the extracted part is, so as not to
+/// expose other wallets or accounts: the transaction filter's criteria, the
+/// sources of the extracted wallet, the estimated birthdays of the extracted
+/// accounts, and birthday overrides. Everything else, such as the data a
+/// source format kept for all its accounts (`sqlite-data` and the like),
+/// the Sprout addresses excluded and attachments added by others, is left
+/// out. What was extracted, and the metadata left out, is recorded in the
+/// metadata as `split`.
+///
+/// A warning is logged if the output holds no key material, as happens when
+/// an account whose keys all derive from the wallet's seed is extracted
+/// without `keep_seed`.
+pub fn split(zewif: &Zewif, options: &SplitOptions) -> Result<Zewif> {
+    let wallets: Vec<(usize, &ZewifWallet)> = match options.wallet {
+        Some(index) => {
+            let wallet = zewif.wallets().get(index).ok_or_else(|| {
+                anyhow!(
+                    "No wallet {}; the input has {} wallets",
+                    index,
+                    zewif.wallets().len()
+                )
+            })?;
+            vec![(index, wallet)]
+        }
+        None => zewif.wallets().iter().enumerate().collect(),
+    };
+
+    let mut payload = Envelope::new("split");
+    let mut seed_dropped = false;
+    let (wallet_index, wallet) = match options.account() {
+        None => {
+            let [(index, wallet)] = wallets[..] else {
+                bail!(
+                    "The input has {} wallets; choose one with --wallet",
+                    wallets.len()
+                );
+            };
+            payload = payload.add_assertion("wallet", index as u64);
+            (index, wallet.clone())
+        }
+        Some(selector) => {
+            let (wallet_index, wallet, account_index, account) =
+                find_account(&wallets, selector)?;
+            payload = payload
+                .add_assertion("wallet", wallet_index as u64)
+                .add_assertion("account", account_index as u64)
+                .add_assertion("seedKept", options.keep_seed);
+            let mut split_wallet = ZewifWallet::new(wallet.network());
+            if options.keep_seed
+                && let Some(seed_material) = wallet.seed_material()
+            {
+                split_wallet.set_seed_material(seed_material.clone());
+            }
+            split_wallet.add_account(account.clone());
+            seed_dropped =
+                !options.keep_seed && wallet.seed_material().is_some();
+            (wallet_index, split_wallet)
+        }
+    };
+    if !has_keys(&wallet) {
+        if seed_dropped {
+            warn!(
+                "The extracted account holds no keys of its own; they derive from the wallet's seed, which --keep-seed keeps"
+            );
+        } else {
+            warn!("The extracted wallet holds no spending or viewing keys");
+        }
+    }
+
+    let mut split = Zewif::new();
+    for txid in wallet
+        .accounts()
+        .iter()
+        .flat_map(|a| a.relevant_transactions())
+    {
+        if let Some(tx) = zewif.transactions().get(txid) {
+            split.add_transaction(*txid, tx.clone());
+        }
+    }
+    let birthdays: Vec<u32> = wallet
+        .accounts()
+        .iter()
+        .filter_map(|a| a.birthday_height().map(u32::from))
+        .collect();
+    split.add_wallet(wallet);
+    let whole_input = options.account.is_none() && zewif.wallets().len() == 1;
+    let left_out = if whole_input {
+        metadata::copy(zewif, &mut split)?;
+        Vec::new()
+    } else {
+        let left_out = metadata::copy_if(zewif, &mut split, |conforms_to| {
+            metadata::kind(conforms_to)
+                .is_some_and(|kind| CARRIED_OVER.contains(&kind))
+        })?;
+        rewrite_metadata(zewif, &mut split, wallet_index, &birthdays)?;
+        left_out
+            .into_iter()
+            .filter(|c| {
+                !metadata::kind(Some(c.as_str()))
+                    .is_some_and(|kind| REWRITTEN.contains(&kind))
+            })
+            .collect()
+    };
+    for conforms_to in left_out {
+        payload = payload.add_assertion("metadataLeftOut", conforms_to);
+    }
+    metadata::record(&mut split, "split", payload);
+    Ok(split)
+}
+
+/// The kinds of `zmigrate` records carried over as they are when part of the
+/// input is extracted, as they say nothing about the rest of it.
+const CARRIED_OVER: [&str; 2] = ["birthday-override", "split"];
+
+/// The kinds of `zmigrate` records rewritten to describe only the extracted
+/// part when part of the input is extracted.
+const REWRITTEN: [&str; 3] =
+    ["merged-sources", "transaction-filter", "birthday-estimate"];
+
+/// The criteria of a transaction filter, which apply to every account alike.
+const FILTER_CRITERIA: [&str; 4] = [
+    "excludeSpent",
+    "rawTransactionsRemoved",
+    "sinceHeight",
+    "untilHeight",
+];
+
+/// Records in `split` the parts of `zewif`'s `merged-sources`,
+/// `transaction-filter` and `birthday-estimate` records that concern the
+/// wallet at `wallet_index`, or its accounts with these birthdays.
+fn rewrite_metadata(
+    zewif: &Zewif,
+    split: &mut Zewif,
+    wallet_index: usize,
+    birthdays: &[u32],
+) -> Result<()> {
+    // The extracted wallet becomes the output's first, and the number of
+    // duplicate transactions, which counts those of other wallets, is
+    // dropped.
+    for merged in metadata::payloads(zewif, "merged-sources")? {
+        let mut payload = Envelope::new("mergedSources");
+        for entry in merged.objects_for_predicate("wallet") {
+            if entry.extract_subject::<u64>()? == wallet_index as u64 {
+                let mut wallet = Envelope::new(0u64);
+                for source in entry.objects_for_predicate("source") {
+                    wallet = wallet.add_assertion("source", source);
+                }
+                payload = payload.add_assertion("wallet", wallet);
+            }
+        }
+        metadata::record(split, "merged-sources", payload);
+    }
+
+    // The number of transactions removed counts those of other accounts,
+    // and the account filtered on may be another one.
+    for filter in metadata::payloads(zewif, "transaction-filter")? {
+        let mut payload = Envelope::new("transactionFilter");
+        for predicate in FILTER_CRITERIA {
+            for object in filter.objects_for_predicate(predicate) {
+                payload = payload.add_assertion(predicate, object);
+            }
+        }
+        metadata::record(split, "transaction-filter", payload);
+    }
+
+    for estimate in metadata::payloads(zewif, "birthday-estimate")? {
+        let mut heights = Vec::new();
+        for height in estimate.objects_for_predicate("height") {
+            let height = height.extract_subject::<u32>()?;
+            if birthdays.contains(&height) {
+                heights.push(height);
+            }
+        }
+        if !heights.is_empty() {
+            let mut payload = Envelope::new("birthdayEstimate");
+            for height in heights {
+                payload = payload.add_assertion("height", height);
+            }
+            metadata::record(split, "birthday-estimate", payload);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `wallet` holds any key material: a seed, or a spending or
+/// viewing key stored with one of its addresses.
+pub fn has_keys(wallet: &ZewifWallet) -> bool {
+    wallet.seed_material().is_some()
+        || wallet
+            .accounts()
+            .iter()
+            .flat_map(|a| a.addresses())
+            .any(|address| match address.address() {
+                ProtocolAddress::Shielded(shielded) => {
+                    shielded.spending_key().is_some()
+                        || shielded.incoming_viewing_key().is_some()
+                }
+                ProtocolAddress::Transparent(transparent) => {
+                    transparent.spend_authority().is_some()
+                }
+                ProtocolAddress::Unified(_) => false,
+            })
+}
+
+/// The one account among `wallets` matching `selector`, with its wallet and
+/// their indices.
+fn find_account<'a>(
+    wallets: &[(usize, &'a ZewifWallet)],
+    selector: &str,
+) -> Result<(usize, &'a ZewifWallet, usize, &'a Account)> {
+    let mut matches = Vec::new();
+    for &(wallet_index, wallet) in wallets {
+        let keys = WalletKeys::new(wallet)?;
+        for (account_index, account) in wallet.accounts().iter().enumerate() {
+            if account_matches(&keys, account_index, account, selector)? {
+                matches.push((wallet_index, wallet, account_index, account));
+            }
+        }
+    }
+    match matches[..] {
+        [found] => Ok(found),
+        [] => bail!("No account matches {}", selector),
+        _ => bail!(
+            "{} accounts match {}; choose the wallet with --wallet",
+            matches.len(),
+            selector
+        ),
+    }
+}
+
+/// Whether `selector` is the index of `account` within its wallet, its
+/// name, or its unified full viewing key.
+pub fn account_matches(
+    keys: &WalletKeys,
+    account_index: usize,
+    account: &Account,
+    selector: &str,
+) -> Result<bool> {
+    Ok(selector.parse() == Ok(account_index)
+        || account.name() == selector
+        || keys.is_account_ufvk(account, selector)?)
+}
@@ -0,0 +1,106 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use rpassword::prompt_password;
+
+use crate::{
+    exec::Exec,
+    file_args::{FileArgsLike, create_secret_file},
+    format::{PasswordFn, Registry, WriteOptions},
+    input_args::InputArgs,
+    progress::Progress,
+    split::{self, SplitOptions},
+};
+
+/// Extract one wallet or account into a wallet file of its own
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    input_args: InputArgs,
+
+    /// Index of the wallet to extract, or to look for the account in
+    #[arg(long)]
+    wallet: Option<usize>,
+
+    /// The account to extract: its index within its wallet, its name, or
+    /// its unified full viewing key
+    #[arg(long)]
+    account: Option<String>,
+
+    /// Keep the wallet's seed when extracting an account, although it
+    /// derives the keys of every account of the wallet
+    #[arg(long, requires = "account")]
+    keep_seed: bool,
+
+    /// Output format
+    #[arg(
+        long,
+        default_value = "zewif",
+        value_parser = Registry::builtin().writer_parser()
+    )]
+    to: String,
+
+    /// Compress the output
+    #[arg(long)]
+    compress: bool,
+
+    /// Encrypt the output
+    #[arg(long)]
+    encrypt: bool,
+
+    /// Output file path
+    output_file: PathBuf,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf { self.input_args.file() }
+}
+
+impl Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        let zewif = self.input_args.load_zewif()?;
+        let options = SplitOptions::new()
+            .with_wallet(self.wallet)
+            .with_account(self.account.clone())
+            .with_keep_seed(self.keep_seed);
+        let split = split::split(&zewif, &options)?;
+
+        let password: Option<PasswordFn> = if self.encrypt {
+            Some(Box::new(|| {
+                Ok(prompt_password("Enter encryption password: ")?)
+            }))
+        } else {
+            None
+        };
+        let mut write_options = WriteOptions::new()
+            .with_compress(self.compress)
+            .with_password(password);
+        let encoded = Registry::builtin().writer(&self.to)?.write(
+            &split,
+            &mut write_options,
+            &Progress::quiet(),
+        )?;
+        // The output may hold keys, so it is never written over another
+        // file, and only the current user can read it.
+        create_secret_file(&self.output_file)?
+            .write_all(&encoded)
+            .with_context(|| {
+                format!(
+                    "Failed to write output file: {}",
+                    self.output_file.display()
+                )
+            })?;
+        Ok(format!(
+            "Wrote {} accounts and {} transactions to {}",
+            split
+                .wallets()
+                .iter()
+                .map(|w| w.accounts().len())
+                .sum::<usize>(),
+            split.transactions().len(),
+            self.output_file.display()
+        ))
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashSet, fmt};
+
+use bc_envelope::prelude::*;
+use zcash_address::ZcashAddress;
+use zcash_protocol::PoolType;
+use zewif::Zewif;
+use zewif_zcashd::ZcashdWallet;
+
+use crate::metadata;
+
+/// A Sprout note received by the wallet that has not been spent by any
+/// transaction the wallet knows about.
+#[derive(Debug, Clone)]
+pub struct SproutNote {
+    txid: String,
+    js_index: u64,
+    output_index: u8,
+    address: String,
+}
+
+impl SproutNote {
+    pub fn txid(&self) -> &str { &self.txid }
+
+    pub fn js_index(&self) -> u64 { self.js_index }
+
+    pub fn output_index(&self) -> u8 { self.output_index }
+
+    pub fn address(&self) -> &str { &self.address }
+}
+
+/// The Sprout keys and unspent Sprout notes found in a zcashd wallet.
+///
+/// Sprout funds cannot be spent by most wallets a migration targets, so they
+/// must be moved to a newer pool with `zcashd` before the wallet is
+/// abandoned.
+#[derive(Debug, Clone, Default)]
+pub struct SproutReport {
+    addresses: Vec<String>,
+    unspent_notes: Vec<SproutNote>,
+    excluded: bool,
+}
+
+impl SproutReport {
+    /// The addresses of the wallet's Sprout spending keys.
+    pub fn addresses(&self) -> &[String] { &self.addresses }
+
+    pub fn unspent_notes(&self) -> &[SproutNote] { &self.unspent_notes }
+
+    /// Whether the Sprout addresses were dropped from the migrated `Zewif`.
+    pub fn excluded(&self) -> bool { self.excluded }
+
+    /// Whether the wallet holds any Sprout keys or notes.
+    pub fn has_sprout(&self) -> bool {
+        !self.addresses.is_empty() || !self.unspent_notes.is_empty()
+    }
+}
+
+impl fmt::Display for SproutReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sprout Report")?;
+        writeln!(f, "- Sprout spending keys: {}", self.addresses.len())?;
+        writeln!(f, "- Unspent Sprout notes: {}", self.unspent_notes.len())?;
+        if !self.has_sprout() {
+            return write!(f, "✅ No Sprout keys or notes");
+        }
+        for note in &self.unspent_notes {
+            writeln!(
+                f,
+                "⚠️  unspent note to {} in transaction {} (joinsplit {}, output {})",
+                note.address, note.txid, note.js_index, note.output_index
+            )?;
+        }
+        if self.excluded {
+            writeln!(
+                f,
+                "⚠️  {} Sprout address(es) were excluded from the output",
+                self.addresses.len()
+            )?;
+        }
+        write!(
+            f,
+            "🛑 Most wallets cannot spend Sprout funds; move them to Sapling with zcashd before abandoning this wallet"
+        )
+    }
+}
+
+/// Finds the Sprout spending keys in `wallet`, and the Sprout notes it
+/// received that none of its transactions spend.
+pub fn detect_sprout(wallet: &ZcashdWallet) -> SproutReport {
+    let network = wallet.network();
+    let mut addresses: Vec<String> = wallet
+        .sprout_keys()
+        .map(|keys| keys.keys().map(|a| a.to_string(network)).collect())
+        .unwrap_or_default();
+    addresses.sort();
+
+    let spent: HashSet<_> = wallet
+        .transactions()
+        .values()
+        .filter_map(|tx| tx.join_splits())
+        .flat_map(|join_splits| join_splits.descriptions())
+        .flat_map(|description| description.nullifiers())
+        .collect();
+
+    let mut unspent_notes = Vec::new();
+    for (txid, tx) in wallet.transactions() {
+        let Some(note_data) = tx.sprout_note_data() else {
+            continue;
+        };
+        for (outpoint, note) in note_data {
+            // zcashd only computes a nullifier for notes it holds the spending
+            // key for; without one the note can't be shown to be spent.
+            if note.nullifier().is_some_and(|nf| spent.contains(&nf)) {
+                continue;
+            }
+            unspent_notes.push(SproutNote {
+                txid: txid.to_string(),
+                js_index: outpoint.js(),
+                output_index: outpoint.n(),
+                address: note.address().to_string(network),
+            });
+        }
+    }
+    unspent_notes.sort_by(|a, b| {
+        (&a.txid, a.js_index, a.output_index).cmp(&(
+            &b.txid,
+            b.js_index,
+            b.output_index,
+        ))
+    });
+
+    SproutReport {
+        addresses,
+        unspent_notes,
+        excluded: false,
+    }
+}
+
+/// Whether `address` is a Sprout payment address.
+pub fn is_sprout_address(address: &str) -> bool {
+    // Sprout is the only address kind that can receive into none of the
+    // transparent, Sapling or Orchard pools.
+    ZcashAddress::try_from_encoded(address).is_ok_and(|address| {
+        ![PoolType::Transparent, PoolType::SAPLING, PoolType::ORCHARD]
+            .into_iter()
+            .any(|pool| address.can_receive_as(pool))
+    })
+}
+
+/// Removes every Sprout address from `zewif`, and records the exclusion in
+/// its metadata.
+///
+/// Transactions are kept, including their JoinSplits and any Sprout notes
+/// they hold: a transaction that moves funds into or out of Sprout is also
+/// part of the history of the wallet's other pools, and dropping it would
+/// leave that history incomplete.
+pub fn drop_sprout(zewif: &mut Zewif, report: &mut SproutReport) {
+    for wallet in zewif.wallets_mut() {
+        for account in wallet.accounts_mut() {
+            account
+                .addresses_mut()
+                .retain(|address| !is_sprout_address(&address.as_string()));
+        }
+    }
+
+    let mut payload = Envelope::new("sproutExcluded")
+        .add_assertion("unspentNotes", report.unspent_notes.len() as u64);
+    for address in &report.addresses {
+        payload = payload.add_assertion("address", address.as_str());
+    }
+    metadata::record(zewif, "sprout-excluded", payload);
+    report.excluded = true;
+}
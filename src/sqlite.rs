@@ -0,0 +1,645 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use bc_envelope::prelude::*;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use sapling_crypto::SaplingIvk;
+use zcash_keys::address::Address as DecodedAddress;
+use zcash_protocol::consensus::NetworkType;
+use zewif::{
+    Account, Address, BlockHeight, Data, Network, ProtocolAddress,
+    ShieldedAddress, Transaction, TransparentAddress, TxId, UnifiedAddress,
+    Zewif, ZewifWallet,
+};
+
+use crate::{balance::Pool, keys, metadata, network::address_network};
+
+/// The pool of a sent note, from the code `zcash_client_sqlite` stores.
+fn pool_from_code(code: i64) -> rusqlite::Result<Pool> {
+    match code {
+        0 => Ok(Pool::Transparent),
+        2 => Ok(Pool::Sapling),
+        3 => Ok(Pool::Orchard),
+        _ => Err(rusqlite::Error::IntegralValueOutOfRange(2, code)),
+    }
+}
+
+/// An account in a `zcash_client_sqlite` database.
+#[derive(Debug, Clone)]
+pub struct SqliteAccount {
+    id: i64,
+    name: Option<String>,
+    uuid: Option<Vec<u8>>,
+    seed_fingerprint: Option<Vec<u8>>,
+    account_index: Option<u32>,
+    ufvk: Option<String>,
+    uivk: Option<String>,
+    birthday_height: u32,
+    addresses: Vec<String>,
+}
+
+impl SqliteAccount {
+    pub fn id(&self) -> i64 { self.id }
+
+    pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+
+    /// The fingerprint of the seed the account is derived from, unless its
+    /// viewing key was imported.
+    pub fn seed_fingerprint(&self) -> Option<&[u8]> {
+        self.seed_fingerprint.as_deref()
+    }
+
+    /// The ZIP-32 account index derived from the seed.
+    pub fn account_index(&self) -> Option<u32> { self.account_index }
+
+    pub fn ufvk(&self) -> Option<&str> { self.ufvk.as_deref() }
+
+    pub fn uivk(&self) -> Option<&str> { self.uivk.as_deref() }
+
+    /// The Sapling incoming viewing key, from the unified full viewing key or
+    /// failing that the unified incoming viewing key.
+    pub fn sapling_ivk(&self, network: NetworkType) -> Option<SaplingIvk> {
+        self.ufvk
+            .iter()
+            .chain(&self.uivk)
+            .find_map(|key| keys::unified_sapling_ivk(network, key))
+    }
+
+    /// The height from which the wallet scans the chain for this account.
+    pub fn birthday_height(&self) -> u32 { self.birthday_height }
+
+    /// The account's addresses, including the transparent receivers of its
+    /// unified addresses.
+    pub fn addresses(&self) -> &[String] { &self.addresses }
+}
+
+/// A transaction relevant to the wallet.
+#[derive(Debug, Clone)]
+pub struct SqliteTransaction {
+    txid: [u8; 32],
+    mined_height: Option<u32>,
+    expiry_height: Option<u32>,
+    fee: Option<u64>,
+    raw: Option<Vec<u8>>,
+}
+
+impl SqliteTransaction {
+    pub fn mined_height(&self) -> Option<u32> { self.mined_height }
+
+    pub fn raw(&self) -> Option<&[u8]> { self.raw.as_deref() }
+}
+
+/// A shielded note received by an account.
+#[derive(Debug, Clone)]
+pub struct SqliteReceivedNote {
+    account: i64,
+    txid: [u8; 32],
+    pool: Pool,
+    output_index: u32,
+    value: u64,
+    is_change: bool,
+    memo: Option<Vec<u8>>,
+    spent_in: Option<[u8; 32]>,
+}
+
+impl SqliteReceivedNote {
+    pub fn account(&self) -> i64 { self.account }
+
+    pub fn pool(&self) -> Pool { self.pool }
+
+    pub fn value(&self) -> u64 { self.value }
+
+    pub fn is_spent(&self) -> bool { self.spent_in.is_some() }
+
+    pub fn memo(&self) -> Option<String> { memo_text(self.memo.as_deref()) }
+}
+
+/// An output of a transaction sent from an account.
+#[derive(Debug, Clone)]
+pub struct SqliteSentNote {
+    account: i64,
+    txid: [u8; 32],
+    pool: Pool,
+    output_index: u32,
+    to_address: Option<String>,
+    to_account: Option<i64>,
+    value: u64,
+    memo: Option<Vec<u8>>,
+}
+
+impl SqliteSentNote {
+    pub fn account(&self) -> i64 { self.account }
+
+    pub fn pool(&self) -> Pool { self.pool }
+
+    pub fn value(&self) -> u64 { self.value }
+
+    pub fn memo(&self) -> Option<String> { memo_text(self.memo.as_deref()) }
+}
+
+/// The contents of a `zcash_client_sqlite` wallet database, as used by
+/// Zashi and other wallets built on librustzcash.
+#[derive(Debug, Clone)]
+pub struct SqliteWallet {
+    accounts: Vec<SqliteAccount>,
+    transactions: Vec<SqliteTransaction>,
+    received_notes: Vec<SqliteReceivedNote>,
+    sent_notes: Vec<SqliteSentNote>,
+}
+
+impl SqliteWallet {
+    /// Reads a `zcash_client_sqlite` database, such as `data.db`, without
+    /// modifying it.
+    pub fn from_file(file: &Path) -> Result<Self> {
+        let connection =
+            Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .with_context(|| {
+                    format!("Opening wallet database: {}", file.display())
+                })?;
+        Self::from_connection(&connection)
+    }
+
+    pub fn from_connection(db: &Connection) -> Result<Self> {
+        for table in ["accounts", "addresses", "transactions"] {
+            if !has_table(db, table)? {
+                bail!("Not a zcash_client_sqlite database: no {} table", table);
+            }
+        }
+        Ok(Self {
+            accounts: read_accounts(db).context("Reading accounts")?,
+            transactions: read_transactions(db)
+                .context("Reading transactions")?,
+            received_notes: read_received_notes(db)
+                .context("Reading received notes")?,
+            sent_notes: read_sent_notes(db).context("Reading sent notes")?,
+        })
+    }
+
+    pub fn accounts(&self) -> &[SqliteAccount] { &self.accounts }
+
+    pub fn transactions(&self) -> &[SqliteTransaction] { &self.transactions }
+
+    pub fn received_notes(&self) -> &[SqliteReceivedNote] {
+        &self.received_notes
+    }
+
+    pub fn sent_notes(&self) -> &[SqliteSentNote] { &self.sent_notes }
+
+    /// The network of the database, from the prefix of its first address.
+    /// `zcash_client_sqlite` is told the network when the database is opened
+    /// and doesn't record it.
+    pub fn network(&self) -> Result<Network> {
+        let Some(address) =
+            self.accounts.iter().flat_map(|a| &a.addresses).next()
+        else {
+            bail!("Wallet database has no addresses");
+        };
+        match address_network(address) {
+            Some(NetworkType::Main) => Ok(Network::Main),
+            Some(NetworkType::Test) => Ok(Network::Test),
+            Some(NetworkType::Regtest) => Ok(Network::Regtest),
+            None => {
+                bail!("Can't determine the network of address {}", address)
+            }
+        }
+    }
+
+    /// The transactions in which `account` received or sent funds.
+    fn account_transactions(&self, account: i64) -> Vec<[u8; 32]> {
+        let received = self
+            .received_notes
+            .iter()
+            .filter(|n| n.account == account)
+            .flat_map(|n| std::iter::once(n.txid).chain(n.spent_in));
+        let sent = self
+            .sent_notes
+            .iter()
+            .filter(|n| n.account == account)
+            .map(|n| n.txid);
+        let mut txids: Vec<[u8; 32]> = Vec::new();
+        for txid in received.chain(sent) {
+            if !txids.contains(&txid) {
+                txids.push(txid);
+            }
+        }
+        txids
+    }
+}
+
+fn has_table(db: &Connection, name: &str) -> Result<bool> {
+    Ok(db
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn has_column(db: &Connection, table: &str, column: &str) -> Result<bool> {
+    Ok(db
+        .query_row(
+            "SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// The first of `candidates` that is a column of `table`, for columns that
+/// were renamed between schema versions.
+fn find_column(
+    db: &Connection,
+    table: &str,
+    candidates: &[&'static str],
+) -> Result<Option<&'static str>> {
+    for &column in candidates {
+        if has_column(db, table, column)? {
+            return Ok(Some(column));
+        }
+    }
+    Ok(None)
+}
+
+/// The first of `candidates` that is a column of `table`, failing if there
+/// is none.
+fn require_column(
+    db: &Connection,
+    table: &str,
+    candidates: &[&'static str],
+) -> Result<&'static str> {
+    find_column(db, table, candidates)?.ok_or_else(|| {
+        anyhow!("No {} column in {} table", candidates[0], table)
+    })
+}
+
+fn txid(bytes: Vec<u8>) -> rusqlite::Result<[u8; 32]> {
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| {
+        rusqlite::Error::InvalidColumnType(
+            0,
+            format!("txid of {} bytes", len),
+            rusqlite::types::Type::Blob,
+        )
+    })
+}
+
+/// The text of a ZIP-302 memo, or `None` if it is empty or not text.
+fn memo_text(memo: Option<&[u8]>) -> Option<String> {
+    let memo = memo?;
+    // A first byte above 0xF4 marks a memo that is not UTF-8 text.
+    if memo.first().is_none_or(|&b| b > 0xF4) {
+        return None;
+    }
+    let end = memo.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    let text = std::str::from_utf8(&memo[..end]).ok()?;
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn read_accounts(db: &Connection) -> Result<Vec<SqliteAccount>> {
+    // Older schema versions key accounts by their ZIP-32 index in an
+    // `account` column, and lack the columns for imported keys, names and
+    // UUIDs.
+    let id = require_column(db, "accounts", &["id", "account"])?;
+    let optional = |column: &'static str| -> Result<&'static str> {
+        Ok(find_column(db, "accounts", &[column])?.unwrap_or("NULL"))
+    };
+    let account_index =
+        match find_column(db, "accounts", &["hd_account_index"])? {
+            Some(column) => column,
+            None if id == "account" => "account",
+            None => "NULL",
+        };
+    let mut statement = db.prepare(&format!(
+        "SELECT {id}, {}, {}, {}, {account_index}, {}, {}, birthday_height
+         FROM accounts ORDER BY {id}",
+        optional("name")?,
+        optional("uuid")?,
+        optional("hd_seed_fingerprint")?,
+        optional("ufvk")?,
+        optional("uivk")?,
+    ))?;
+    let mut accounts = statement
+        .query_map([], |row| {
+            Ok(SqliteAccount {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                uuid: row.get(2)?,
+                seed_fingerprint: row.get(3)?,
+                account_index: row.get(4)?,
+                ufvk: row.get(5)?,
+                uivk: row.get(6)?,
+                birthday_height: row.get(7)?,
+                addresses: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let account = require_column(db, "addresses", &["account_id", "account"])?;
+    let transparent =
+        find_column(db, "addresses", &["cached_transparent_receiver_address"])?
+            .unwrap_or("NULL");
+    let mut statement = db.prepare(&format!(
+        "SELECT address, {transparent}
+         FROM addresses WHERE {account} = ?1 ORDER BY rowid"
+    ))?;
+    for account in &mut accounts {
+        let rows = statement
+            .query_map(params![account.id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (address, transparent) in rows {
+            for address in std::iter::once(address).chain(transparent) {
+                if !account.addresses.contains(&address) {
+                    account.addresses.push(address);
+                }
+            }
+        }
+    }
+    Ok(accounts)
+}
+
+fn read_transactions(db: &Connection) -> Result<Vec<SqliteTransaction>> {
+    let mut statement = db.prepare(
+        "SELECT txid, block, expiry_height, fee, raw
+         FROM transactions ORDER BY block IS NULL, block, id_tx",
+    )?;
+    let transactions = statement
+        .query_map([], |row| {
+            Ok(SqliteTransaction {
+                txid: txid(row.get(0)?)?,
+                mined_height: row.get(1)?,
+                expiry_height: row.get(2)?,
+                fee: row.get(3)?,
+                raw: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(transactions)
+}
+
+fn read_received_notes(db: &Connection) -> Result<Vec<SqliteReceivedNote>> {
+    let mut notes = Vec::new();
+    for (pool, prefix, index) in [
+        (Pool::Sapling, "sapling", "output_index"),
+        (Pool::Orchard, "orchard", "action_index"),
+    ] {
+        let table = format!("{}_received_notes", prefix);
+        if !has_table(db, &table)? {
+            continue;
+        }
+        let id = require_column(db, &table, &["id", "id_note"])?;
+        let account = require_column(db, &table, &["account_id", "account"])?;
+        // Spends are kept in a table of their own; older schema versions
+        // recorded the one spending transaction in a `spent` column.
+        let spends = format!("{}_received_note_spends", prefix);
+        let spent_in = if has_table(db, &spends)? {
+            format!(
+                "(SELECT st.txid FROM {spends} s
+                  JOIN transactions st ON s.transaction_id = st.id_tx
+                  WHERE s.{prefix}_received_note_id = n.{id}
+                  ORDER BY st.block IS NULL, st.block LIMIT 1)"
+            )
+        } else if has_column(db, &table, "spent")? {
+            "(SELECT st.txid FROM transactions st WHERE st.id_tx = n.spent)"
+                .to_string()
+        } else {
+            "NULL".to_string()
+        };
+        let mut statement = db.prepare(&format!(
+            "SELECT n.{account}, t.txid, n.{index}, n.value, n.is_change,
+                    n.memo, {spent_in}
+             FROM {table} n JOIN transactions t ON n.tx = t.id_tx
+             ORDER BY n.{id}"
+        ))?;
+        let rows = statement.query_map([], |row| {
+            Ok(SqliteReceivedNote {
+                account: row.get(0)?,
+                txid: txid(row.get(1)?)?,
+                pool,
+                output_index: row.get(2)?,
+                value: row.get(3)?,
+                is_change: row.get(4)?,
+                memo: row.get(5)?,
+                spent_in: row
+                    .get::<_, Option<Vec<u8>>>(6)?
+                    .map(txid)
+                    .transpose()?,
+            })
+        })?;
+        notes.extend(rows.collect::<rusqlite::Result<Vec<_>>>()?);
+    }
+    Ok(notes)
+}
+
+fn read_sent_notes(db: &Connection) -> Result<Vec<SqliteSentNote>> {
+    if !has_table(db, "sent_notes")? {
+        return Ok(Vec::new());
+    }
+    let id = require_column(db, "sent_notes", &["id", "id_note"])?;
+    let from_account =
+        require_column(db, "sent_notes", &["from_account_id", "from_account"])?;
+    let to_account =
+        find_column(db, "sent_notes", &["to_account_id", "to_account"])?
+            .map_or("NULL".to_string(), |column| format!("n.{column}"));
+    let mut statement = db.prepare(&format!(
+        "SELECT n.{from_account}, t.txid, n.output_pool, n.output_index,
+                n.to_address, {to_account}, n.value, n.memo
+         FROM sent_notes n JOIN transactions t ON n.tx = t.id_tx
+         ORDER BY n.{id}"
+    ))?;
+    let notes = statement
+        .query_map([], |row| {
+            Ok(SqliteSentNote {
+                account: row.get(0)?,
+                txid: txid(row.get(1)?)?,
+                pool: pool_from_code(row.get(2)?)?,
+                output_index: row.get(3)?,
+                to_address: row.get(4)?,
+                to_account: row.get(5)?,
+                value: row.get(6)?,
+                memo: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(notes)
+}
+
+/// The Zewif form of an address stored by `zcash_client_sqlite`, with the
+/// account's Sapling incoming viewing key if it derives the address.
+fn protocol_address(
+    network: NetworkType,
+    address: &str,
+    ivk: Option<&SaplingIvk>,
+) -> Result<ProtocolAddress> {
+    let decoded = keys::decode_address(network, address)
+        .map_err(|e| anyhow!("Invalid address {}: {}", address, e))?;
+    Ok(match decoded {
+        DecodedAddress::Sapling(decoded) => {
+            let mut shielded = ShieldedAddress::new(address.to_string());
+            if let Some(ivk) = ivk
+                && ivk.to_payment_address(*decoded.diversifier()).as_ref()
+                    == Some(&decoded)
+            {
+                shielded.set_incoming_viewing_key(keys::zewif_sapling_ivk(ivk));
+            }
+            ProtocolAddress::Shielded(shielded)
+        }
+        DecodedAddress::Unified(_) => ProtocolAddress::Unified(Box::new(
+            UnifiedAddress::new(address.to_string()),
+        )),
+        DecodedAddress::Transparent(_) | DecodedAddress::Tex(_) => {
+            ProtocolAddress::Transparent(TransparentAddress::new(
+                address.to_string(),
+            ))
+        }
+    })
+}
+
+/// Migrates a `zcash_client_sqlite` database to Zewif.
+///
+/// The database holds no seeds, only their fingerprints: accounts derived
+/// from the same seed become accounts of one wallet without seed material,
+/// and each account with an imported viewing key becomes a wallet of its
+/// own. Each account's scanning birthday becomes its birthday height.
+/// Addresses are decoded, failing on any that don't belong to the network,
+/// and Sapling addresses get the incoming viewing key of their account's
+/// unified viewing key. Data Zewif has no place for (the unified viewing
+/// keys themselves, seed fingerprints, received and sent notes, and memos)
+/// is recorded in the metadata as `sqlite-data`.
+///
+/// The schema read is that of `zcash_client_sqlite`'s migrations; it has not
+/// been checked against a database written by `zcash_client_sqlite` itself.
+pub fn migrate_to_zewif(wallet: &SqliteWallet) -> Result<Zewif> {
+    let network = wallet.network()?;
+    let network_type = keys::network_type(network);
+    let mut zewif = Zewif::new();
+
+    let mut fingerprints: Vec<Option<&[u8]>> = Vec::new();
+    let mut wallets: Vec<ZewifWallet> = Vec::new();
+    for saccount in &wallet.accounts {
+        let position = saccount
+            .seed_fingerprint
+            .as_deref()
+            .and_then(|fp| fingerprints.iter().position(|f| *f == Some(fp)));
+        let zwallet = match position {
+            Some(position) => &mut wallets[position],
+            None => {
+                fingerprints.push(saccount.seed_fingerprint.as_deref());
+                wallets.push(ZewifWallet::new(network));
+                wallets.last_mut().unwrap()
+            }
+        };
+
+        let mut account = Account::new();
+        if let Some(name) = &saccount.name {
+            account.set_name(name.clone());
+        }
+        if let Some(index) = saccount.account_index {
+            account.set_zip32_account_id(index);
+        }
+        account.set_birthday_height(Some(BlockHeight::from(
+            saccount.birthday_height,
+        )));
+        let ivk = saccount.sapling_ivk(network_type);
+        for address in &saccount.addresses {
+            account.add_address(Address::new(protocol_address(
+                network_type,
+                address,
+                ivk.as_ref(),
+            )?));
+        }
+        for txid in wallet.account_transactions(saccount.id) {
+            account.add_relevant_transaction(TxId::from_bytes(txid));
+        }
+        zwallet.add_account(account);
+    }
+
+    for zwallet in wallets {
+        zewif.add_wallet(zwallet);
+    }
+    for stx in &wallet.transactions {
+        let txid = TxId::from_bytes(stx.txid);
+        let mut tx = Transaction::new(txid);
+        if let Some(height) = stx.mined_height {
+            tx.set_mined_height(BlockHeight::from(height));
+        }
+        if let Some(raw) = &stx.raw {
+            tx.set_raw(Data::from(raw.clone()));
+        }
+        zewif.add_transaction(txid, tx);
+    }
+    metadata::record(&mut zewif, "sqlite-data", sqlite_data(wallet));
+    Ok(zewif)
+}
+
+/// Describes what `zcash_client_sqlite` stores that Zewif has no place for.
+fn sqlite_data(wallet: &SqliteWallet) -> Envelope {
+    let mut payload = Envelope::new("sqliteData");
+    for account in &wallet.accounts {
+        let mut entry = Envelope::new(account.id as u64);
+        if let Some(ufvk) = &account.ufvk {
+            entry = entry.add_assertion("ufvk", ufvk.as_str());
+        }
+        if let Some(uivk) = &account.uivk {
+            entry = entry.add_assertion("uivk", uivk.as_str());
+        }
+        if let Some(uuid) = &account.uuid {
+            entry = entry.add_assertion("uuid", hex::encode(uuid));
+        }
+        if let Some(fingerprint) = &account.seed_fingerprint {
+            entry = entry
+                .add_assertion("seedFingerprint", hex::encode(fingerprint));
+        }
+        payload = payload.add_assertion("account", entry);
+    }
+    for tx in &wallet.transactions {
+        if tx.expiry_height.is_none() && tx.fee.is_none() {
+            continue;
+        }
+        let mut entry = Envelope::new(hex::encode(tx.txid));
+        if let Some(expiry) = tx.expiry_height {
+            entry = entry.add_assertion("expiryHeight", expiry);
+        }
+        if let Some(fee) = tx.fee {
+            entry = entry.add_assertion("fee", fee);
+        }
+        payload = payload.add_assertion("transaction", entry);
+    }
+    for note in &wallet.received_notes {
+        let mut entry = Envelope::new(hex::encode(note.txid))
+            .add_assertion("account", note.account as u64)
+            .add_assertion("pool", note.pool.to_string())
+            .add_assertion("outputIndex", note.output_index)
+            .add_assertion("value", note.value)
+            .add_assertion("isChange", note.is_change);
+        if let Some(memo) = note.memo() {
+            entry = entry.add_assertion("memo", memo);
+        }
+        if let Some(spent_in) = note.spent_in {
+            entry = entry.add_assertion("spentIn", hex::encode(spent_in));
+        }
+        payload = payload.add_assertion("receivedNote", entry);
+    }
+    for note in &wallet.sent_notes {
+        let mut entry = Envelope::new(hex::encode(note.txid))
+            .add_assertion("account", note.account as u64)
+            .add_assertion("pool", note.pool.to_string())
+            .add_assertion("outputIndex", note.output_index)
+            .add_assertion("value", note.value);
+        if let Some(address) = &note.to_address {
+            entry = entry.add_assertion("toAddress", address.as_str());
+        }
+        if let Some(account) = note.to_account {
+            entry = entry.add_assertion("toAccount", account as u64);
+        }
+        if let Some(memo) = note.memo() {
+            entry = entry.add_assertion("memo", memo);
+        }
+        payload = payload.add_assertion("sentNote", entry);
+    }
+    payload
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use zewif::Zewif;
+
+use crate::{
+    format::{ReadOptions, ReadOutcome, WalletReader},
+    progress::{Progress, ReadStage, Stage},
+    source::WalletSource,
+    sqlite::{self, SqliteWallet},
+};
+
+const READ_SQLITE: Stage =
+    Stage::Read(ReadStage::new("read_sqlite", "Reading wallet database"));
+
+/// Reads `zcash_client_sqlite` wallet databases.
+pub struct SqliteReader;
+
+impl WalletReader for SqliteReader {
+    fn name(&self) -> &'static str { "sqlite" }
+
+    fn help(&self) -> &'static str {
+        "Input from a zcash_client_sqlite wallet database"
+    }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        let zewif = input.with_path(options.allow_disk(), |path| {
+            migrate_sqlite(path, progress)
+        })?;
+        Ok(ReadOutcome::new(zewif))
+    }
+}
+
+/// Migrates a `zcash_client_sqlite` wallet database to Zewif.
+pub fn migrate_sqlite(file: &Path, progress: &Progress) -> Result<Zewif> {
+    let stage = progress.start(READ_SQLITE);
+    let wallet = SqliteWallet::from_file(file)?;
+    stage.finish_with_count(wallet.accounts().len(), "accounts");
+
+    let stage = progress.start(Stage::Migrate);
+    let zewif =
+        sqlite::migrate_to_zewif(&wallet).context("Migrating to Zewif")?;
+    stage.finish_with_count(zewif.transactions().len(), "transactions");
+    Ok(zewif)
+}
@@ -0,0 +1,463 @@
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use zcash_keys::{
+    address::Address as DecodedAddress, keys::UnifiedFullViewingKey,
+};
+use zewif::{
+    ProtocolAddress, ShieldedAddress, TransparentAddress,
+    TransparentSpendAuthority, UnifiedAddress, Zewif,
+};
+
+use crate::{
+    keys::{
+        WalletKeys, sapling_ivk, sapling_spending_key,
+        transparent_derivation_matches, transparent_key_compression,
+    },
+    sprout::is_sprout_address,
+};
+
+/// The kind of address that was checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    Transparent,
+    Sapling,
+    Sprout,
+    Unified,
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AddressKind::Transparent => "transparent",
+            AddressKind::Sapling => "sapling",
+            AddressKind::Sprout => "sprout",
+            AddressKind::Unified => "unified",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The wallet material an address was re-derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    /// The wallet's HD seed, via the account's ZIP-32 index or the address's
+    /// stored HD derivation path.
+    Seed,
+
+    /// A spending key stored alongside the address.
+    SpendingKey,
+
+    /// An incoming viewing key stored alongside the address.
+    ViewingKey,
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            KeySource::Seed => "HD seed",
+            KeySource::SpendingKey => "spending key",
+            KeySource::ViewingKey => "viewing key",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The outcome of checking one address against the wallet's key material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationStatus {
+    /// The address was re-derived from wallet material.
+    Derived(KeySource),
+
+    /// The wallet holds key material for the address, but none of it derives
+    /// the address.
+    Mismatch(String),
+
+    /// The wallet holds no key material the address could be derived from.
+    NoKeyMaterial,
+
+    /// The stored address string could not be decoded, so it could not be
+    /// checked.
+    Undecodable(String),
+
+    /// The address is of a kind that is not checked, such as Sprout.
+    Skipped(String),
+}
+
+impl VerificationStatus {
+    /// Whether this outcome should be reported as a problem.
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            VerificationStatus::Derived(_) | VerificationStatus::Skipped(_)
+        )
+    }
+}
+
+impl fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationStatus::Derived(source) => {
+                write!(f, "derived from {}", source)
+            }
+            VerificationStatus::Mismatch(reason) => {
+                write!(f, "NOT derivable: {}", reason)
+            }
+            VerificationStatus::NoKeyMaterial => {
+                write!(f, "NOT derivable: no key material in wallet")
+            }
+            VerificationStatus::Undecodable(reason) => {
+                write!(f, "undecodable address: {}", reason)
+            }
+            VerificationStatus::Skipped(reason) => {
+                write!(f, "not checked: {}", reason)
+            }
+        }
+    }
+}
+
+/// The result of checking a single stored address.
+#[derive(Debug, Clone)]
+pub struct AddressVerification {
+    wallet_index: usize,
+    account_index: usize,
+    kind: AddressKind,
+    address: String,
+    status: VerificationStatus,
+}
+
+impl AddressVerification {
+    pub fn wallet_index(&self) -> usize { self.wallet_index }
+
+    pub fn account_index(&self) -> usize { self.account_index }
+
+    pub fn kind(&self) -> AddressKind { self.kind }
+
+    pub fn address(&self) -> &str { &self.address }
+
+    pub fn status(&self) -> &VerificationStatus { &self.status }
+}
+
+/// Results of a key derivation consistency check over a `Zewif`.
+#[derive(Debug, Clone, Default)]
+pub struct VerificationReport {
+    results: Vec<AddressVerification>,
+}
+
+impl VerificationReport {
+    /// All checked addresses, in wallet/account/address order.
+    pub fn results(&self) -> &[AddressVerification] { &self.results }
+
+    /// Addresses that could not be derived from wallet material.
+    pub fn failures(&self) -> impl Iterator<Item = &AddressVerification> {
+        self.results.iter().filter(|r| r.status.is_failure())
+    }
+
+    /// Addresses of a kind that is not checked.
+    pub fn skipped(&self) -> impl Iterator<Item = &AddressVerification> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, VerificationStatus::Skipped(_)))
+    }
+
+    /// Whether every checked address was derived from wallet material.
+    pub fn is_clean(&self) -> bool { self.failures().next().is_none() }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Key Derivation Report")?;
+        let failures: Vec<_> = self.failures().collect();
+        let checked = self.results.len() - self.skipped().count();
+        writeln!(
+            f,
+            "- Addresses: {}/{} derived from wallet material",
+            checked - failures.len(),
+            checked
+        )?;
+        let skipped = self.skipped().count();
+        if skipped > 0 {
+            writeln!(
+                f,
+                "- Sprout addresses: {} not checked; Sprout keys are not \
+                 re-derived",
+                skipped
+            )?;
+        }
+        if failures.is_empty() {
+            write!(f, "✅ All addresses derived from wallet material")?;
+        } else {
+            for failure in &failures {
+                writeln!(
+                    f,
+                    "❌ wallet {} account {} {} address {}: {}",
+                    failure.wallet_index,
+                    failure.account_index,
+                    failure.kind,
+                    failure.address,
+                    failure.status
+                )?;
+            }
+            write!(f, "🛑 {} address(es) not derivable", failures.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-derives every transparent, Sapling and Orchard address in `zewif` from
+/// the spending keys, viewing keys and HD seed stored in the same wallet, and
+/// reports any address that cannot be derived.
+///
+/// Sprout addresses are reported as skipped rather than checked.
+pub fn verify_zewif(zewif: &Zewif) -> Result<VerificationReport> {
+    let mut report = VerificationReport::default();
+    for (wallet_index, wallet) in zewif.wallets().iter().enumerate() {
+        let keys = WalletKeys::new(wallet).with_context(|| {
+            format!("Preparing key material for wallet {}", wallet_index)
+        })?;
+        let checker = WalletChecker { keys };
+        for (account_index, account) in wallet.accounts().iter().enumerate() {
+            let ufvk =
+                checker.keys.account_ufvk(account).with_context(|| {
+                    format!(
+                        "Deriving keys for wallet {} account {}",
+                        wallet_index, account_index
+                    )
+                })?;
+            for address in account.addresses() {
+                let (kind, status) =
+                    checker.check_address(address.address(), ufvk.as_ref());
+                report.results.push(AddressVerification {
+                    wallet_index,
+                    account_index,
+                    kind,
+                    address: address.as_string(),
+                    status,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Fails with a summary of the report if any address was not derivable.
+pub fn ensure_clean(report: &VerificationReport) -> Result<()> {
+    if !report.is_clean() {
+        bail!(
+            "Key derivation check failed for {} address(es)",
+            report.failures().count()
+        );
+    }
+    Ok(())
+}
+
+/// Checks addresses against the key material of one wallet.
+struct WalletChecker {
+    keys: WalletKeys,
+}
+
+impl WalletChecker {
+    fn check_address(
+        &self,
+        address: &ProtocolAddress,
+        ufvk: Option<&UnifiedFullViewingKey>,
+    ) -> (AddressKind, VerificationStatus) {
+        match address {
+            ProtocolAddress::Transparent(transparent) => (
+                AddressKind::Transparent,
+                self.check_transparent(transparent, ufvk),
+            ),
+            ProtocolAddress::Shielded(shielded)
+                if is_sprout_address(shielded.address()) =>
+            {
+                (
+                    AddressKind::Sprout,
+                    VerificationStatus::Skipped(
+                        "Sprout keys are not re-derived".to_string(),
+                    ),
+                )
+            }
+            ProtocolAddress::Shielded(shielded) => {
+                (AddressKind::Sapling, self.check_sapling(shielded, ufvk))
+            }
+            ProtocolAddress::Unified(unified) => {
+                (AddressKind::Unified, self.check_unified(unified, ufvk))
+            }
+        }
+    }
+
+    fn check_transparent(
+        &self,
+        address: &TransparentAddress,
+        ufvk: Option<&UnifiedFullViewingKey>,
+    ) -> VerificationStatus {
+        let decoded = match self.keys.decode_address(address.address()) {
+            Ok(DecodedAddress::Transparent(decoded)) => decoded,
+            Ok(_) => {
+                return VerificationStatus::Undecodable(
+                    "not a transparent address".to_string(),
+                );
+            }
+            Err(e) => return VerificationStatus::Undecodable(e),
+        };
+
+        let mut had_material = false;
+        if let Some(TransparentSpendAuthority::SpendingKey(key)) =
+            address.spend_authority()
+        {
+            had_material = true;
+            if transparent_key_compression(key.as_ref(), &decoded).is_some() {
+                return VerificationStatus::Derived(KeySource::SpendingKey);
+            }
+        }
+        if let (Some(ufvk), Some(derivation)) =
+            (ufvk, address.derivation_info())
+        {
+            had_material = true;
+            if transparent_derivation_matches(
+                ufvk,
+                u32::from(derivation.change()),
+                u32::from(derivation.address_index()),
+                &decoded,
+            ) {
+                return VerificationStatus::Derived(KeySource::Seed);
+            }
+        }
+        if had_material {
+            VerificationStatus::Mismatch(
+                "stored keys derive a different address".to_string(),
+            )
+        } else {
+            VerificationStatus::NoKeyMaterial
+        }
+    }
+
+    fn check_sapling(
+        &self,
+        address: &ShieldedAddress,
+        ufvk: Option<&UnifiedFullViewingKey>,
+    ) -> VerificationStatus {
+        let decoded = match self.keys.decode_address(address.address()) {
+            Ok(DecodedAddress::Sapling(decoded)) => decoded,
+            Ok(_) => {
+                return VerificationStatus::Undecodable(
+                    "not a Sapling address".to_string(),
+                );
+            }
+            Err(e) => return VerificationStatus::Undecodable(e),
+        };
+
+        let mut had_material = false;
+        if let Some(key) = address.spending_key() {
+            had_material = true;
+            if let Some(xsk) = sapling_spending_key(key)
+                && xsk
+                    .to_diversifiable_full_viewing_key()
+                    .decrypt_diversifier(&decoded)
+                    .is_some()
+            {
+                return VerificationStatus::Derived(KeySource::SpendingKey);
+            }
+        }
+        if let Some(path) = address.hd_derivation_path()
+            && self.keys.seed().is_some()
+        {
+            had_material = true;
+            if let Some(xsk) = self.keys.sapling_key_at_path(path)
+                && xsk
+                    .to_diversifiable_full_viewing_key()
+                    .decrypt_diversifier(&decoded)
+                    .is_some()
+            {
+                return VerificationStatus::Derived(KeySource::Seed);
+            }
+        }
+        if let Some(ufvk) = ufvk
+            && let Some(dfvk) = ufvk.sapling()
+        {
+            had_material = true;
+            if dfvk.decrypt_diversifier(&decoded).is_some() {
+                return VerificationStatus::Derived(KeySource::Seed);
+            }
+        }
+        if let Some(ivk) = address.incoming_viewing_key() {
+            had_material = true;
+            if sapling_ivk_matches(ivk.as_ref(), &decoded) {
+                return VerificationStatus::Derived(KeySource::ViewingKey);
+            }
+        }
+        if had_material {
+            VerificationStatus::Mismatch(
+                "stored keys derive a different address".to_string(),
+            )
+        } else {
+            VerificationStatus::NoKeyMaterial
+        }
+    }
+
+    fn check_unified(
+        &self,
+        address: &UnifiedAddress,
+        ufvk: Option<&UnifiedFullViewingKey>,
+    ) -> VerificationStatus {
+        let decoded = match self.keys.decode_address(address.address()) {
+            Ok(DecodedAddress::Unified(decoded)) => decoded,
+            Ok(_) => {
+                return VerificationStatus::Undecodable(
+                    "not a unified address".to_string(),
+                );
+            }
+            Err(e) => return VerificationStatus::Undecodable(e),
+        };
+        let Some(ufvk) = ufvk else {
+            return VerificationStatus::NoKeyMaterial;
+        };
+
+        // Every receiver in the unified address must belong to the account.
+        let mut unmatched = Vec::new();
+        if let Some(orchard) = decoded.orchard()
+            && !ufvk
+                .orchard()
+                .is_some_and(|fvk| fvk.scope_for_address(orchard).is_some())
+        {
+            unmatched.push("Orchard");
+        }
+        if let Some(sapling) = decoded.sapling()
+            && !ufvk
+                .sapling()
+                .is_some_and(|dfvk| dfvk.decrypt_diversifier(sapling).is_some())
+        {
+            unmatched.push("Sapling");
+        }
+        if let Some(transparent) = decoded.transparent() {
+            let matches = address.diversifier_index().is_some_and(|index| {
+                u32::try_from(index.clone()).is_ok_and(|index| {
+                    transparent_derivation_matches(ufvk, 0, index, transparent)
+                })
+            });
+            if !matches {
+                unmatched.push("transparent");
+            }
+        }
+
+        if unmatched.is_empty() {
+            VerificationStatus::Derived(KeySource::Seed)
+        } else {
+            VerificationStatus::Mismatch(format!(
+                "{} receiver(s) not derived from account keys",
+                unmatched.join(", ")
+            ))
+        }
+    }
+}
+
+/// Whether a Sapling incoming viewing key derives `address` for the address's
+/// own diversifier.
+fn sapling_ivk_matches(
+    ivk: &[u8],
+    address: &sapling_crypto::PaymentAddress,
+) -> bool {
+    sapling_ivk(ivk).is_some_and(|ivk| {
+        ivk.to_payment_address(*address.diversifier()).as_ref() == Some(address)
+    })
+}
@@ -0,0 +1,567 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use sapling_crypto::{SaplingIvk, zip32::ExtendedSpendingKey};
+use zcash_keys::address::Address as DecodedAddress;
+use zcash_protocol::consensus::NetworkType;
+use zewif::{
+    Account, Address, BlockHeight, Network, ProtocolAddress, SeedMaterial,
+    ShieldedAddress, Transaction, TransparentAddress,
+    TransparentSpendAuthority, TransparentSpendingKey, TxId, Zewif,
+    ZewifWallet,
+};
+use zip32::Scope;
+
+use crate::{keys, metadata, network::address_network};
+
+/// A YWallet account, with the keys and addresses YWallet stores for it.
+#[derive(Debug, Clone)]
+pub struct YAccount {
+    id: i64,
+    name: String,
+    seed: Option<String>,
+    index: u32,
+    sapling_sk: Option<String>,
+    sapling_fvk: String,
+    sapling_address: String,
+    transparent_sk: Option<String>,
+    transparent_address: Option<String>,
+    orchard_fvk: Option<Vec<u8>>,
+    ufvk: Option<String>,
+}
+
+impl YAccount {
+    pub fn id(&self) -> i64 { self.id }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The account's BIP-39 seed phrase, unless its keys were imported.
+    pub fn seed(&self) -> Option<&str> { self.seed.as_deref() }
+
+    /// The ZIP-32 account index derived from the seed.
+    pub fn index(&self) -> u32 { self.index }
+
+    pub fn sapling_address(&self) -> &str { &self.sapling_address }
+
+    pub fn transparent_address(&self) -> Option<&str> {
+        self.transparent_address.as_deref()
+    }
+
+    pub fn ufvk(&self) -> Option<&str> { self.ufvk.as_deref() }
+
+    /// The Sapling spending key, unless the account is view-only, or the key
+    /// doesn't decode or doesn't derive the account's Sapling address.
+    pub fn sapling_spending_key(
+        &self,
+        network: NetworkType,
+    ) -> Option<ExtendedSpendingKey> {
+        let xsk = self
+            .sapling_sk
+            .as_deref()
+            .and_then(|sk| keys::decode_sapling_spending_key(network, sk))?;
+        let ivk = xsk
+            .to_diversifiable_full_viewing_key()
+            .to_ivk(Scope::External);
+        self.derives_sapling_address(network, &ivk).then_some(xsk)
+    }
+
+    /// The Sapling incoming viewing key, from the spending key or failing
+    /// that the full viewing key, if it derives the account's Sapling
+    /// address.
+    pub fn sapling_ivk(&self, network: NetworkType) -> Option<SaplingIvk> {
+        if let Some(xsk) = self.sapling_spending_key(network) {
+            return Some(
+                xsk.to_diversifiable_full_viewing_key()
+                    .to_ivk(Scope::External),
+            );
+        }
+        let ivk = keys::decode_sapling_viewing_key(network, &self.sapling_fvk)?
+            .to_diversifiable_full_viewing_key()
+            .to_ivk(Scope::External);
+        self.derives_sapling_address(network, &ivk).then_some(ivk)
+    }
+
+    /// The secret key of the transparent address, if YWallet stored it, it
+    /// decodes, and it controls the address.
+    pub fn transparent_secret_key(
+        &self,
+        network: NetworkType,
+    ) -> Option<[u8; 32]> {
+        let key = self
+            .transparent_sk
+            .as_deref()
+            .and_then(|sk| keys::decode_transparent_secret_key(network, sk))?;
+        let address = self.transparent_address.as_deref()?;
+        let Ok(DecodedAddress::Transparent(address)) =
+            keys::decode_address(network, address)
+        else {
+            return None;
+        };
+        keys::transparent_key_compression(&key, &address)
+            .is_some()
+            .then_some(key)
+    }
+
+    /// Whether `ivk` derives the account's Sapling address, for the
+    /// address's own diversifier.
+    fn derives_sapling_address(
+        &self,
+        network: NetworkType,
+        ivk: &SaplingIvk,
+    ) -> bool {
+        match keys::decode_address(network, &self.sapling_address) {
+            Ok(DecodedAddress::Sapling(address)) => {
+                ivk.to_payment_address(*address.diversifier()).as_ref()
+                    == Some(&address)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A note received by a YWallet account.
+#[derive(Debug, Clone)]
+pub struct YNote {
+    account: i64,
+    txid: [u8; 32],
+    output_index: u32,
+    height: u32,
+    value: u64,
+    orchard: bool,
+    spent_height: Option<u32>,
+}
+
+impl YNote {
+    pub fn account(&self) -> i64 { self.account }
+
+    pub fn value(&self) -> u64 { self.value }
+
+    pub fn is_spent(&self) -> bool { self.spent_height.is_some() }
+}
+
+/// A transaction as YWallet records it for one account.
+#[derive(Debug, Clone)]
+pub struct YTransaction {
+    account: i64,
+    txid: [u8; 32],
+    height: u32,
+    timestamp: u32,
+    value: i64,
+    address: Option<String>,
+    memo: Option<String>,
+}
+
+impl YTransaction {
+    pub fn account(&self) -> i64 { self.account }
+
+    pub fn height(&self) -> u32 { self.height }
+
+    pub fn memo(&self) -> Option<&str> { self.memo.as_deref() }
+}
+
+/// An address book entry.
+#[derive(Debug, Clone)]
+pub struct YContact {
+    name: String,
+    address: String,
+}
+
+impl YContact {
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn address(&self) -> &str { &self.address }
+}
+
+/// The contents of a YWallet database.
+#[derive(Debug, Clone)]
+pub struct YWallet {
+    accounts: Vec<YAccount>,
+    notes: Vec<YNote>,
+    transactions: Vec<YTransaction>,
+    contacts: Vec<YContact>,
+}
+
+impl YWallet {
+    /// Reads a YWallet database, such as `zec.db`, without modifying it.
+    pub fn from_file(file: &Path) -> Result<Self> {
+        let connection =
+            Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .with_context(|| {
+                    format!("Opening YWallet database: {}", file.display())
+                })?;
+        Self::from_connection(&connection)
+    }
+
+    pub fn from_connection(db: &Connection) -> Result<Self> {
+        if !has_table(db, "accounts")? || !has_table(db, "transactions")? {
+            bail!("Not a YWallet database: no accounts or transactions table");
+        }
+        Ok(Self {
+            accounts: read_accounts(db).context("Reading YWallet accounts")?,
+            notes: read_notes(db).context("Reading YWallet notes")?,
+            transactions: read_transactions(db)
+                .context("Reading YWallet transactions")?,
+            contacts: read_contacts(db).context("Reading YWallet contacts")?,
+        })
+    }
+
+    pub fn accounts(&self) -> &[YAccount] { &self.accounts }
+
+    pub fn notes(&self) -> &[YNote] { &self.notes }
+
+    pub fn transactions(&self) -> &[YTransaction] { &self.transactions }
+
+    pub fn contacts(&self) -> &[YContact] { &self.contacts }
+
+    /// The network of the database, from the prefix of its first account's
+    /// address. YWallet keeps one database per network and doesn't record
+    /// which.
+    pub fn network(&self) -> Result<Network> {
+        let Some(account) = self.accounts.first() else {
+            bail!("YWallet database has no accounts");
+        };
+        match address_network(&account.sapling_address) {
+            Some(NetworkType::Main) => Ok(Network::Main),
+            Some(NetworkType::Test) => Ok(Network::Test),
+            Some(NetworkType::Regtest) => Ok(Network::Regtest),
+            None => bail!(
+                "Can't determine the network of YWallet address {}",
+                account.sapling_address
+            ),
+        }
+    }
+}
+
+fn has_table(db: &Connection, name: &str) -> Result<bool> {
+    Ok(db
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn txid(bytes: Vec<u8>) -> rusqlite::Result<[u8; 32]> {
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| {
+        rusqlite::Error::InvalidColumnType(
+            0,
+            format!("txid of {} bytes", len),
+            rusqlite::types::Type::Blob,
+        )
+    })
+}
+
+fn read_accounts(db: &Connection) -> Result<Vec<YAccount>> {
+    let mut statement = db.prepare(
+        "SELECT id_account, name, seed, aindex, sk, ivk, address
+         FROM accounts ORDER BY id_account",
+    )?;
+    let mut accounts = statement
+        .query_map([], |row| {
+            Ok(YAccount {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                seed: row.get(2)?,
+                index: row.get(3)?,
+                sapling_sk: row.get(4)?,
+                sapling_fvk: row.get(5)?,
+                sapling_address: row.get(6)?,
+                transparent_sk: None,
+                transparent_address: None,
+                orchard_fvk: None,
+                ufvk: None,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Older databases lack the tables for keys added in later releases.
+    let has_taddrs = has_table(db, "taddrs")?;
+    let has_orchard_addrs = has_table(db, "orchard_addrs")?;
+    let has_ufvks = has_table(db, "ufvks")?;
+    for account in &mut accounts {
+        if has_taddrs
+            && let Some((sk, address)) = db
+                .query_row(
+                    "SELECT sk, address FROM taddrs WHERE account = ?1",
+                    params![account.id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?
+        {
+            account.transparent_sk = sk;
+            account.transparent_address = Some(address);
+        }
+        if has_orchard_addrs {
+            account.orchard_fvk = db
+                .query_row(
+                    "SELECT fvk FROM orchard_addrs WHERE account = ?1",
+                    params![account.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+        }
+        if has_ufvks {
+            account.ufvk = db
+                .query_row(
+                    "SELECT ufvk FROM ufvks WHERE account = ?1",
+                    params![account.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+        }
+    }
+    Ok(accounts)
+}
+
+fn read_notes(db: &Connection) -> Result<Vec<YNote>> {
+    if !has_table(db, "received_notes")? {
+        return Ok(Vec::new());
+    }
+    let mut statement = db.prepare(
+        "SELECT n.account, t.txid, n.output_index, n.height, n.value,
+                n.orchard, n.spent
+         FROM received_notes n JOIN transactions t ON n.tx = t.id_tx
+         ORDER BY n.id_note",
+    )?;
+    let notes = statement
+        .query_map([], |row| {
+            Ok(YNote {
+                account: row.get(0)?,
+                txid: txid(row.get(1)?)?,
+                output_index: row.get(2)?,
+                height: row.get(3)?,
+                value: row.get(4)?,
+                orchard: row.get(5)?,
+                spent_height: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(notes)
+}
+
+fn read_transactions(db: &Connection) -> Result<Vec<YTransaction>> {
+    let mut statement = db.prepare(
+        "SELECT account, txid, height, timestamp, value, address, memo
+         FROM transactions ORDER BY height, id_tx",
+    )?;
+    let transactions = statement
+        .query_map([], |row| {
+            Ok(YTransaction {
+                account: row.get(0)?,
+                txid: txid(row.get(1)?)?,
+                height: row.get(2)?,
+                timestamp: row.get(3)?,
+                value: row.get(4)?,
+                address: row.get(5)?,
+                memo: row
+                    .get::<_, Option<String>>(6)?
+                    .filter(|memo| !memo.is_empty()),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(transactions)
+}
+
+fn read_contacts(db: &Connection) -> Result<Vec<YContact>> {
+    if !has_table(db, "contacts")? {
+        return Ok(Vec::new());
+    }
+    let mut statement =
+        db.prepare("SELECT name, address FROM contacts ORDER BY id")?;
+    let contacts = statement
+        .query_map([], |row| {
+            Ok(YContact {
+                name: row.get(0)?,
+                address: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(contacts)
+}
+
+/// Migrates a YWallet database to Zewif.
+///
+/// Accounts sharing a seed phrase become accounts of one wallet, and each
+/// account with imported keys becomes a wallet of its own. Sapling spending
+/// and incoming viewing keys and transparent secret keys go in the
+/// addresses' own fields, if they derive those addresses. Data Zewif has no
+/// place for (keys that don't decode or don't derive the account's
+/// addresses, full viewing keys, Orchard keys and UFVKs, received notes,
+/// memos and contacts) is recorded in the metadata as `ywallet-data`.
+///
+/// The schema read is that of YWallet's published source; it has not been
+/// checked against a database written by YWallet itself.
+pub fn migrate_to_zewif(ywallet: &YWallet) -> Result<Zewif> {
+    let network = ywallet.network()?;
+    let network_type = keys::network_type(network);
+    let mut zewif = Zewif::new();
+
+    // YWallet records a transaction once for each account it concerns.
+    let mut seen = HashSet::new();
+    let mut transactions: Vec<(TxId, Transaction)> = Vec::new();
+    for ytx in &ywallet.transactions {
+        if seen.insert(ytx.txid) {
+            let txid = TxId::from_bytes(ytx.txid);
+            let mut tx = Transaction::new(txid);
+            tx.set_mined_height(BlockHeight::from(ytx.height));
+            transactions.push((txid, tx));
+        }
+    }
+
+    let mut seeds: Vec<Option<&str>> = Vec::new();
+    let mut wallets: Vec<ZewifWallet> = Vec::new();
+    for yaccount in &ywallet.accounts {
+        let position = yaccount
+            .seed
+            .as_deref()
+            .and_then(|seed| seeds.iter().position(|s| *s == Some(seed)));
+        let wallet = match position {
+            Some(position) => &mut wallets[position],
+            None => {
+                let mut wallet = ZewifWallet::new(network);
+                if let Some(seed) = &yaccount.seed {
+                    wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(
+                        zewif::Bip39Mnemonic::new(seed.clone(), None),
+                    ));
+                }
+                seeds.push(yaccount.seed.as_deref());
+                wallets.push(wallet);
+                wallets.last_mut().unwrap()
+            }
+        };
+
+        let mut account = Account::new();
+        account.set_name(yaccount.name.clone());
+        if yaccount.seed.is_some() {
+            account.set_zip32_account_id(yaccount.index);
+        }
+        let mut sapling =
+            ShieldedAddress::new(yaccount.sapling_address.clone());
+        if let Some(xsk) = yaccount.sapling_spending_key(network_type) {
+            sapling.set_spending_key(keys::zewif_sapling_spending_key(&xsk));
+        }
+        if let Some(ivk) = yaccount.sapling_ivk(network_type) {
+            sapling.set_incoming_viewing_key(keys::zewif_sapling_ivk(&ivk));
+        }
+        account.add_address(Address::new(ProtocolAddress::Shielded(sapling)));
+        if let Some(address) = &yaccount.transparent_address {
+            let mut transparent = TransparentAddress::new(address.clone());
+            if let Some(key) = yaccount.transparent_secret_key(network_type) {
+                transparent.set_spend_authority(
+                    TransparentSpendAuthority::SpendingKey(
+                        TransparentSpendingKey::new(key),
+                    ),
+                );
+            }
+            account.add_address(Address::new(ProtocolAddress::Transparent(
+                transparent,
+            )));
+        }
+        for ytx in ywallet
+            .transactions
+            .iter()
+            .filter(|t| t.account == yaccount.id)
+        {
+            account.add_relevant_transaction(TxId::from_bytes(ytx.txid));
+        }
+        wallet.add_account(account);
+    }
+
+    for wallet in wallets {
+        zewif.add_wallet(wallet);
+    }
+    for (txid, tx) in transactions {
+        zewif.add_transaction(txid, tx);
+    }
+    metadata::record(
+        &mut zewif,
+        "ywallet-data",
+        ywallet_data(ywallet, network_type),
+    );
+    Ok(zewif)
+}
+
+/// Describes what YWallet stores that Zewif has no place for: the keys
+/// that don't decode or don't derive the account's addresses, or that Zewif
+/// doesn't hold, such as full viewing keys, and the notes, memos and
+/// contacts.
+fn ywallet_data(ywallet: &YWallet, network: NetworkType) -> Envelope {
+    let mut payload = Envelope::new("ywalletData");
+    for account in &ywallet.accounts {
+        let mut entry = Envelope::new(account.id as u64);
+        // A spending key that derives the address stands in for the viewing
+        // key.
+        match (&account.sapling_sk, account.sapling_spending_key(network)) {
+            (Some(sk), None) => {
+                entry = entry
+                    .add_assertion("saplingSpendingKey", sk.as_str())
+                    .add_assertion(
+                        "saplingViewingKey",
+                        account.sapling_fvk.as_str(),
+                    );
+            }
+            (None, _) => {
+                entry = entry.add_assertion(
+                    "saplingViewingKey",
+                    account.sapling_fvk.as_str(),
+                );
+            }
+            (Some(_), Some(_)) => {}
+        }
+        if let Some(sk) = &account.transparent_sk
+            && account.transparent_secret_key(network).is_none()
+        {
+            entry = entry.add_assertion("transparentSpendingKey", sk.as_str());
+        }
+        if let Some(fvk) = &account.orchard_fvk {
+            entry = entry.add_assertion("orchardViewingKey", hex::encode(fvk));
+        }
+        if let Some(ufvk) = &account.ufvk {
+            entry = entry.add_assertion("ufvk", ufvk.as_str());
+        }
+        if !entry.assertions().is_empty() {
+            payload = payload.add_assertion("account", entry);
+        }
+    }
+    for note in &ywallet.notes {
+        let mut entry = Envelope::new(hex::encode(note.txid))
+            .add_assertion("account", note.account as u64)
+            .add_assertion("outputIndex", note.output_index)
+            .add_assertion(
+                "pool",
+                if note.orchard { "orchard" } else { "sapling" },
+            )
+            .add_assertion("height", note.height)
+            .add_assertion("value", note.value);
+        if let Some(spent) = note.spent_height {
+            entry = entry.add_assertion("spentHeight", spent);
+        }
+        payload = payload.add_assertion("note", entry);
+    }
+    for tx in &ywallet.transactions {
+        let mut entry = Envelope::new(hex::encode(tx.txid))
+            .add_assertion("account", tx.account as u64)
+            .add_assertion("timestamp", tx.timestamp)
+            .add_assertion("value", tx.value);
+        if let Some(address) = &tx.address {
+            entry = entry.add_assertion("address", address.as_str());
+        }
+        if let Some(memo) = &tx.memo {
+            entry = entry.add_assertion("memo", memo.as_str());
+        }
+        payload = payload.add_assertion("transaction", entry);
+    }
+    for contact in &ywallet.contacts {
+        payload = payload.add_assertion(
+            "contact",
+            Envelope::new(contact.name.as_str())
+                .add_assertion("address", contact.address.as_str()),
+        );
+    }
+    payload
+}
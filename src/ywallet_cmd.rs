@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use zewif::Zewif;
+
+use crate::{
+    format::{ReadOptions, ReadOutcome, WalletReader},
+    progress::{Progress, ReadStage, Stage},
+    source::WalletSource,
+    ywallet::{self, YWallet},
+};
+
+const READ_YWALLET: Stage =
+    Stage::Read(ReadStage::new("read_ywallet", "Reading YWallet database"));
+
+/// Reads YWallet SQLite databases.
+pub struct YwalletReader;
+
+impl WalletReader for YwalletReader {
+    fn name(&self) -> &'static str { "ywallet" }
+
+    fn help(&self) -> &'static str { "Input from a YWallet database" }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        let zewif = input.with_path(options.allow_disk(), |path| {
+            migrate_ywallet(path, progress)
+        })?;
+        Ok(ReadOutcome::new(zewif))
+    }
+}
+
+/// Migrates a YWallet database to Zewif.
+pub fn migrate_ywallet(file: &Path, progress: &Progress) -> Result<Zewif> {
+    let stage = progress.start(READ_YWALLET);
+    let ywallet = YWallet::from_file(file)?;
+    stage.finish_with_count(ywallet.accounts().len(), "accounts");
+
+    let stage = progress.start(Stage::Migrate);
+    let zewif =
+        ywallet::migrate_to_zewif(&ywallet).context("Migrating to Zewif")?;
+    stage.finish_with_count(zewif.transactions().len(), "transactions");
+    Ok(zewif)
+}
@@ -6,27 +6,81 @@ use std::{
 
 use anyhow::{Context, Result};
 use bc_envelope::prelude::*;
-use clap::Args;
+use clap::{Args, Subcommand};
+use tracing::{info_span, warn};
 use zewif::{BlockHeight, Zewif};
 use zewif_zcashd::{
     BDBDump, DBKey, ZcashdDump, ZcashdParser, ZcashdWallet, migrate_to_zewif,
 };
 
-use crate::file_args::{FileArgs, FileArgsLike};
+use crate::{
+    balance::SaplingNullifiers,
+    birthday,
+    file_args::{FileArgs, FileArgsLike},
+    format::{ReadOptions, ReadOutcome, WalletReader},
+    inspect::{self, Inspection},
+    network,
+    progress::{Progress, ReadStage, Stage},
+    records_cmd,
+    source::{WalletSource, with_memory_file},
+    sprout,
+};
+
+const READ_BDB: Stage =
+    Stage::Read(ReadStage::new("read_bdb", "Reading BerkeleyDB file"));
+const PARSE_DUMP: Stage =
+    Stage::Read(ReadStage::new("parse_dump", "Parsing zcashd records"));
+const PARSE_WALLET: Stage =
+    Stage::Read(ReadStage::new("parse_wallet", "Parsing zcashd wallet"));
 
 /// Process a zcashd wallet file
 #[derive(Debug, Args)]
 #[group(skip)]
 pub struct CommandArgs {
+    #[command(subcommand)]
+    command: ZcashdCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ZcashdCommand {
+    /// Dump a zcashd wallet file as migrated to Zewif
+    Dump(DumpArgs),
+
+    /// List the raw BerkeleyDB records of a zcashd wallet file
+    Records(records_cmd::CommandArgs),
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        match &self.command {
+            ZcashdCommand::Dump(args) => args.file(),
+            ZcashdCommand::Records(args) => args.file(),
+        }
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self) -> Result<String> {
+        match &self.command {
+            ZcashdCommand::Dump(args) => args.exec(),
+            ZcashdCommand::Records(args) => args.exec(),
+        }
+    }
+}
+
+/// Dump a zcashd wallet file as migrated to Zewif
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct DumpArgs {
     #[command(flatten)]
     file_args: FileArgs,
 }
 
-impl FileArgsLike for CommandArgs {
+impl FileArgsLike for DumpArgs {
     fn file(&self) -> &PathBuf { &self.file_args.file }
 }
 
-impl crate::exec::Exec for CommandArgs {
+impl crate::exec::Exec for DumpArgs {
     fn exec(&self) -> Result<String> { dump_wallet(self.file()) }
 }
 
@@ -125,40 +179,176 @@ fn output_envelope(envelope: &Envelope, output: &mut String) {
     writeln!(output, "---").unwrap();
 }
 
+fn output_sprout_report(
+    zcashd_wallet: &ZcashdWallet,
+    output: &mut String,
+) -> Result<()> {
+    let report = sprout::detect_sprout(zcashd_wallet);
+    if report.has_sprout() {
+        writeln!(output, "{}", report)?;
+        writeln!(output, "---")?;
+    }
+    Ok(())
+}
+
 pub fn zcashd_to_zewif(file: &Path) -> Result<Zewif> {
+    migrate_zcashd(file, false, &Progress::quiet())
+        .map(|outcome| outcome.into_parts().0)
+}
+
+/// Migrates a zcashd wallet held in memory to Zewif, without writing it to
+/// disk.
+pub fn zcashd_bytes_to_zewif(bytes: &[u8]) -> Result<Zewif> {
+    migrate_zcashd_bytes(bytes, false, false, &Progress::quiet())
+        .map(|outcome| outcome.into_parts().0)
+}
+
+/// Migrates a zcashd wallet held in memory; see [`migrate_zcashd`], and
+/// [`with_memory_file`] for `allow_disk`.
+pub fn migrate_zcashd_bytes(
+    bytes: &[u8],
+    drop_sprout: bool,
+    allow_disk: bool,
+    progress: &Progress,
+) -> Result<ReadOutcome> {
+    with_memory_file(bytes, allow_disk, |file| {
+        migrate_zcashd(file, drop_sprout, progress)
+    })
+}
+
+/// Migrates a zcashd wallet file to Zewif, reporting any Sprout keys and
+/// unspent Sprout notes it holds, along with the nullifiers zcashd keeps for
+/// its Sapling notes. Each account is given a birthday
+/// estimated from its transactions and the wallet's key creation times; see
+/// [`birthday::estimate_birthdays`]. If `drop_sprout` is set, the Sprout
+/// addresses are left out of the result and their exclusion is recorded in
+/// its metadata.
+pub fn migrate_zcashd(
+    file: &Path,
+    drop_sprout: bool,
+    progress: &Progress,
+) -> Result<ReadOutcome> {
+    let _span = info_span!("zcashd_migrate", file = %file.display()).entered();
+    let stage = progress.start(READ_BDB);
     let db_dump =
         BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    stage.finish();
 
+    let stage = progress.start(PARSE_DUMP);
     let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump, true)
         .context("Parsing Zcashd dump")?;
+    stage.finish_with_count(zcashd_dump.records().len(), "records");
 
+    let stage = progress.start(PARSE_WALLET);
     let (zcashd_wallet, unparsed_keys) =
         ZcashdParser::parse_dump(&zcashd_dump, true)
             .context("Parsing Zcashd dump")?;
+    stage.finish_with_count(zcashd_wallet.transactions().len(), "transactions");
 
-    let zewif = migrate_to_zewif(&zcashd_wallet, BlockHeight::default())
+    let stage = progress.start(Stage::Migrate);
+    let mut zewif = migrate_to_zewif(&zcashd_wallet, BlockHeight::default())
         .context("Migrating to Zewif")?;
+    birthday::estimate_birthdays(
+        &mut zewif,
+        birthday::earliest_key_time(&zcashd_dump),
+    );
+    stage.finish_with_count(zewif.transactions().len(), "transactions");
 
     if !unparsed_keys.is_empty() {
         anyhow::bail!("Unparsed keys: {:?}", unparsed_keys);
     }
 
-    Ok(zewif)
+    let mut sprout_report = sprout::detect_sprout(&zcashd_wallet);
+    if sprout_report.has_sprout() {
+        warn!(
+            addresses = sprout_report.addresses().len(),
+            unspent_notes = sprout_report.unspent_notes().len(),
+            "Wallet holds Sprout funds"
+        );
+    }
+    if drop_sprout {
+        sprout::drop_sprout(&mut zewif, &mut sprout_report);
+    }
+
+    Ok(ReadOutcome::new(zewif)
+        .with_sprout_report(sprout_report)
+        .with_sapling_nullifiers(SaplingNullifiers::from_zcashd(
+            &zcashd_wallet,
+        )))
+}
+
+/// Reads zcashd `wallet.dat` files.
+pub struct ZcashdReader;
+
+impl WalletReader for ZcashdReader {
+    fn name(&self) -> &'static str { "zcashd" }
+
+    fn help(&self) -> &'static str { "Input from a `zcashd` wallet" }
+
+    fn drops_sprout(&self) -> bool { true }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        let drop_sprout = options.drop_sprout();
+        match input {
+            WalletSource::File(path) => {
+                migrate_zcashd(path, drop_sprout, progress)
+            }
+            WalletSource::Bytes(bytes) => migrate_zcashd_bytes(
+                bytes,
+                drop_sprout,
+                options.allow_disk(),
+                progress,
+            ),
+        }
+    }
+
+    fn inspect(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Result<Inspection> {
+        input.with_path(options.allow_disk(), inspect::inspect_zcashd)
+    }
+}
+
+/// Dumps a zcashd wallet held in memory, without writing it to disk; see
+/// [`dump_wallet`].
+pub fn dump_wallet_bytes(bytes: &[u8]) -> Result<String> {
+    with_memory_file(bytes, false, dump_wallet)
 }
 
 pub fn dump_wallet(file: &Path) -> Result<String> {
+    let _span = info_span!("zcashd_dump", file = %file.display()).entered();
+    let progress = Progress::quiet();
+
+    let stage = progress.start(READ_BDB);
     let db_dump =
         BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    stage.finish();
 
+    let stage = progress.start(PARSE_DUMP);
     let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump, true)
         .context("Parsing Zcashd dump")?;
+    stage.finish_with_count(zcashd_dump.records().len(), "records");
 
+    let stage = progress.start(PARSE_WALLET);
     let (zcashd_wallet, unparsed_keys) =
         ZcashdParser::parse_dump(&zcashd_dump, true)
             .context("Parsing Zcashd dump")?;
+    stage.finish_with_count(zcashd_wallet.transactions().len(), "transactions");
+    if !unparsed_keys.is_empty() {
+        warn!(count = unparsed_keys.len(), "Some records were not parsed");
+    }
 
+    let stage = progress.start(Stage::Migrate);
     let zewif = migrate_to_zewif(&zcashd_wallet, BlockHeight::default())
         .context("Migrating to Zewif")?;
+    stage.finish_with_count(zewif.transactions().len(), "transactions");
 
     let envelope = Envelope::from(zewif.clone());
 
@@ -169,7 +359,13 @@ pub fn dump_wallet(file: &Path) -> Result<String> {
     // output_zewif_debug(&zewif, &mut output);
     // output_migration_quality_report(&zcashd_wallet, &zewif, &mut output);
     output_envelope(&envelope, &mut output);
+    output_sprout_report(&zcashd_wallet, &mut output)?;
 
+    writeln!(
+        output,
+        "Network: {}",
+        network::network_name(zcashd_wallet.network())
+    )?;
     writeln!(output, "✅ Success")?;
 
     Ok(output)
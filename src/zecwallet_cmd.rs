@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, info_span, warn};
+use zewif::{Data, Network, Zewif};
+use zewif_zecwallet::{ZecWalletLite, ZecWalletParser, migrate_to_zewif};
+
+use crate::{
+    error::ZewifInputError,
+    format::{ReadOptions, ReadOutcome, WalletReader},
+    inspect::{self, Inspection},
+    progress::{Progress, ReadStage, Stage},
+    source::WalletSource,
+};
+
+const PARSE_ZECWALLET: Stage = Stage::Read(ReadStage::new(
+    "parse_zecwallet",
+    "Parsing ZecWallet Lite wallet",
+));
+const DECRYPT: Stage =
+    Stage::Read(ReadStage::new("decrypt", "Decrypting wallet keys"));
+
+/// Reads ZecWallet Lite wallet files, including those whose keys are
+/// encrypted.
+pub struct ZecwalletReader;
+
+impl WalletReader for ZecwalletReader {
+    fn name(&self) -> &'static str { "zecwallet" }
+
+    fn help(&self) -> &'static str { "Input from a ZecWallet Lite wallet" }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        migrate_zecwallet_bytes(&input.read()?, options, progress)
+            .map(ReadOutcome::new)
+    }
+
+    fn inspect(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Result<Inspection> {
+        input.with_path(options.allow_disk(), inspect::inspect_zecwallet)
+    }
+}
+
+/// Migrates a ZecWallet Lite wallet file to Zewif, asking `options` for the
+/// password if the wallet's keys are encrypted.
+pub fn migrate_zecwallet(
+    file: &Path,
+    options: &mut ReadOptions,
+    progress: &Progress,
+) -> Result<Zewif> {
+    let _span =
+        info_span!("zecwallet_migrate", file = %file.display()).entered();
+    let bytes = std::fs::read(file).with_context(|| {
+        format!("Failed to read input file: {}", file.display())
+    })?;
+    migrate_zecwallet_bytes(&bytes, options, progress)
+}
+
+/// Migrates a ZecWallet Lite wallet held in memory; see
+/// [`migrate_zecwallet`].
+pub fn migrate_zecwallet_bytes(
+    bytes: &[u8],
+    options: &mut ReadOptions,
+    progress: &Progress,
+) -> Result<Zewif> {
+    let stage = progress.start(PARSE_ZECWALLET);
+    let mut wallet = parse_wallet(bytes, options.strict())?;
+    stage.finish();
+
+    if wallet.is_encrypted() {
+        let password = options.take_password()?()?;
+        let stage = progress.start(DECRYPT);
+        if let Err(e) = wallet.unlock(&password) {
+            debug!(error = %e, "Unlocking ZecWallet Lite wallet failed");
+            if is_wrong_password(&e) {
+                return Err(ZewifInputError::WrongPassword)
+                    .context("Unlocking ZecWallet Lite wallet keys");
+            }
+            return Err(e).context("Unlocking ZecWallet Lite wallet keys");
+        }
+        stage.finish();
+    }
+
+    let stage = progress.start(Stage::Migrate);
+    let zewif = migrate_to_zewif(&wallet).context("Migrating to Zewif")?;
+    stage.finish_with_count(zewif.transactions().len(), "transactions");
+    Ok(zewif)
+}
+
+/// Whether a failure to unlock a ZecWallet Lite wallet's keys means the
+/// password was wrong: the encrypted seed didn't decrypt. Keys that decrypt
+/// but don't match the wallet, and other failures, are left as they are.
+///
+/// The parser only describes the failure in its message, which like
+/// ZecWallet Lite's own asks whether the password is correct.
+pub fn is_wrong_password(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        message.contains("decrypt") || message.contains("password")
+    })
+}
+
+/// The network a ZecWallet Lite wallet file belongs to.
+pub fn wallet_network(file: &Path) -> Result<Network> {
+    Ok(parse_wallet(&std::fs::read(file)?, false)?.network())
+}
+
+/// The serialization version of a ZecWallet Lite wallet file, and whether
+/// its keys are encrypted.
+pub fn wallet_version(file: &Path) -> Result<(u64, bool)> {
+    let wallet = parse_wallet(&std::fs::read(file)?, false)?;
+    Ok((wallet.version(), wallet.is_encrypted()))
+}
+
+/// Parses a ZecWallet Lite wallet without unlocking its keys. With `strict`,
+/// bytes left over after parsing are an error.
+fn parse_wallet(bytes: &[u8], strict: bool) -> Result<ZecWalletLite> {
+    let data = Data::from(bytes.to_vec());
+    let mut parser = ZecWalletParser::new(&data);
+    let wallet = parser.parse().context("Parsing ZecWallet Lite wallet")?;
+    debug!(
+        version = wallet.version(),
+        encrypted = wallet.is_encrypted(),
+        "Read ZecWallet Lite wallet"
+    );
+    let remaining = wallet.remaining();
+    if remaining != 0 {
+        let offset = bytes.len() - remaining;
+        warn!(
+            remaining,
+            offset, "ZecWallet Lite wallet has unparsed bytes"
+        );
+        if strict {
+            bail!(
+                "{} unparsed bytes at offset {} (0x{:x})",
+                remaining,
+                offset,
+                offset
+            );
+        }
+    }
+    Ok(wallet)
+}
@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use bc_envelope::prelude::*;
+use zewif::{Account, Transaction, Zewif, ZewifEnvelope, ZewifWallet};
+
+use crate::{
+    error::ZewifInputError,
+    format::{
+        ReadOptions, ReadOutcome, WalletReader, WalletWriter, WriteOptions,
+    },
+    inspect::{self, Inspection},
+    progress::{Progress, ReadStage, Stage},
+    source::WalletSource,
+};
+
+const READ_ZEWIF: Stage =
+    Stage::Read(ReadStage::new("read_zewif", "Reading Zewif file"));
+const OPEN: Stage =
+    Stage::Read(ReadStage::new("open", "Opening Zewif envelope"));
+const DECODE: Stage = Stage::Read(ReadStage::new("decode", "Decoding Zewif"));
+
+/// The Zewif format version this build can read.
+pub const ZEWIF_VERSION: u32 = 1;
+
+/// A Zewif file as read, before it is opened.
+pub struct ZewifFile {
+    envelope: ZewifEnvelope,
+}
+
+impl ZewifFile {
+    /// The envelope, which may still be encrypted and/or compressed.
+    pub fn envelope(&self) -> &ZewifEnvelope { &self.envelope }
+
+    pub fn is_encrypted(&self) -> bool { self.envelope.is_encrypted() }
+
+    pub fn is_compressed(&self) -> bool { self.envelope.is_compressed() }
+
+    /// Decrypts and uncompresses the envelope as needed.
+    ///
+    /// `password` is only called if the envelope is encrypted. Decryption
+    /// is authenticated, so it fails for a wrong password; damaged
+    /// ciphertext fails the same way and is reported as a wrong password
+    /// too.
+    pub fn open(
+        mut self,
+        password: impl FnOnce() -> Result<String>,
+    ) -> Result<ZewifEnvelope> {
+        if self.envelope.is_encrypted() {
+            let key = ZewifEnvelope::derive_encryption_key(password()?);
+            self.envelope
+                .decrypt(&key)
+                .map_err(|_| ZewifInputError::WrongPassword)?;
+        }
+        if self.envelope.is_compressed() {
+            self.envelope
+                .uncompress()
+                .map_err(|e| ZewifInputError::Corrupt(e.to_string()))?;
+        }
+        Ok(self.envelope)
+    }
+}
+
+/// Reads a Zewif file; see [`ZewifFile::open`].
+pub fn read_zewif_file(file: &Path) -> Result<ZewifFile> {
+    let input_data = std::fs::read(file).with_context(|| {
+        format!("Failed to read input file: {}", file.display())
+    })?;
+    zewif_file_from_bytes(&input_data)
+}
+
+/// Reads a Zewif file held in memory; see [`read_zewif_file`].
+pub fn zewif_file_from_bytes(bytes: &[u8]) -> Result<ZewifFile> {
+    let envelope = Envelope::try_from_cbor_data(bytes.to_vec())
+        .map_err(|e| ZewifInputError::InvalidCbor(e.to_string()))?;
+    let envelope = ZewifEnvelope::new(envelope)
+        .map_err(|e| ZewifInputError::NotZewif(e.to_string()))?;
+    Ok(ZewifFile { envelope })
+}
+
+/// Decodes an opened (decrypted and uncompressed) envelope into a `Zewif`.
+pub fn decode_zewif(envelope: &Envelope) -> Result<Zewif> {
+    check_version(envelope)?;
+    let zewif = Zewif::try_from(envelope.clone())
+        .map_err(|e| locate_decode_error(envelope, e.to_string()))?;
+    Ok(zewif)
+}
+
+/// The format version declared by an opened Zewif envelope.
+///
+/// The zewif crate doesn't record a format version yet, so `zmigrate`
+/// writes none; envelopes that don't declare one are treated as the current
+/// version.
+pub fn zewif_version(envelope: &Envelope) -> Result<u32, ZewifInputError> {
+    let found: Option<u32> = envelope
+        .extract_optional_object_for_predicate("version")
+        .map_err(|e| ZewifInputError::Decode {
+            path: vec!["zewif".to_string(), "version".to_string()],
+            message: e.to_string(),
+        })?;
+    Ok(found.unwrap_or(ZEWIF_VERSION))
+}
+
+fn check_version(envelope: &Envelope) -> Result<(), ZewifInputError> {
+    let found = zewif_version(envelope)?;
+    if found != ZEWIF_VERSION {
+        return Err(ZewifInputError::UnsupportedVersion {
+            found,
+            supported: ZEWIF_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// Re-decodes the components of a Zewif envelope one at a time to find the
+/// innermost one responsible for a decoding failure.
+fn locate_decode_error(
+    envelope: &Envelope,
+    message: String,
+) -> ZewifInputError {
+    let mut path = vec!["zewif".to_string()];
+
+    for (i, wallet) in
+        envelope.objects_for_predicate("wallet").iter().enumerate()
+    {
+        if let Err(wallet_error) = ZewifWallet::try_from(wallet.clone()) {
+            path.push(format!("wallet[{}]", i));
+            for (j, account) in
+                wallet.objects_for_predicate("account").iter().enumerate()
+            {
+                if let Err(account_error) = Account::try_from(account.clone()) {
+                    path.push(format!("account[{}]", j));
+                    return ZewifInputError::Decode {
+                        path,
+                        message: account_error.to_string(),
+                    };
+                }
+            }
+            return ZewifInputError::Decode {
+                path,
+                message: wallet_error.to_string(),
+            };
+        }
+    }
+
+    for (i, transaction) in envelope
+        .objects_for_predicate("transaction")
+        .iter()
+        .enumerate()
+    {
+        if let Err(transaction_error) =
+            Transaction::try_from(transaction.clone())
+        {
+            path.push(format!("transaction[{}]", i));
+            return ZewifInputError::Decode {
+                path,
+                message: transaction_error.to_string(),
+            };
+        }
+    }
+
+    ZewifInputError::Decode { path, message }
+}
+
+/// Reads Zewif files, decrypting and uncompressing them as needed.
+pub struct ZewifReader;
+
+impl WalletReader for ZewifReader {
+    fn name(&self) -> &'static str { "zewif" }
+
+    fn help(&self) -> &'static str { "Input from a `zewif` wallet" }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        let ze = open_input(input, options, progress)?;
+        let stage = progress.start(DECODE);
+        let zewif = decode_zewif(ze.envelope())?;
+        stage.finish_with_count(zewif.transactions().len(), "transactions");
+        Ok(ReadOutcome::new(zewif))
+    }
+
+    fn inspect(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Result<Inspection> {
+        // The contents of an encrypted file are only shown when a password
+        // is given.
+        inspect::inspect_zewif_file(
+            zewif_file_from_bytes(&input.read()?)?,
+            options.take_password().ok(),
+        )
+    }
+
+    fn envelope(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Option<Result<Envelope>> {
+        Some(
+            open_input(input, options, progress)
+                .map(|ze| ze.envelope().clone()),
+        )
+    }
+}
+
+fn open_input(
+    input: &WalletSource,
+    options: &mut ReadOptions,
+    progress: &Progress,
+) -> Result<ZewifEnvelope> {
+    let stage = progress.start(READ_ZEWIF);
+    let file = zewif_file_from_bytes(&input.read()?)?;
+    stage.finish();
+    let stage = progress.start(OPEN);
+    let ze = file.open(|| options.take_password()?())?;
+    stage.finish();
+    Ok(ze)
+}
+
+/// Writes migrated wallets as Zewif envelopes, optionally compressed and
+/// encrypted.
+#[derive(Debug, Clone, Copy)]
+pub enum EnvelopeWriter {
+    /// Zewif binary
+    Binary,
+
+    /// Zewif UR
+    UR,
+
+    /// Envelope Notation
+    Format,
+}
+
+impl EnvelopeWriter {
+    pub const ALL: [EnvelopeWriter; 3] = [
+        EnvelopeWriter::Binary,
+        EnvelopeWriter::UR,
+        EnvelopeWriter::Format,
+    ];
+}
+
+impl WalletWriter for EnvelopeWriter {
+    fn name(&self) -> &'static str {
+        match self {
+            EnvelopeWriter::Binary => "zewif",
+            EnvelopeWriter::UR => "ur",
+            EnvelopeWriter::Format => "format",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            EnvelopeWriter::Binary => "Output in Zewif binary format (default)",
+            EnvelopeWriter::UR => "Output in Zewif UR format",
+            EnvelopeWriter::Format => "Output as Envelope Notation",
+        }
+    }
+
+    fn write(
+        &self,
+        zewif: &Zewif,
+        options: &mut WriteOptions,
+        progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        self.write_envelope(Envelope::from(zewif.clone()), options, progress)
+    }
+
+    fn writes_envelopes(&self) -> bool { true }
+
+    fn write_envelope(
+        &self,
+        envelope: Envelope,
+        options: &mut WriteOptions,
+        progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        let mut ze = ZewifEnvelope::new(envelope)?;
+        if options.compress() {
+            let stage = progress.start(Stage::Compress);
+            ze.compress()?;
+            stage.finish();
+        }
+        let envelope = match options.take_password() {
+            Some(password) => {
+                let password = password()?;
+                let stage = progress.start(Stage::Encrypt);
+                let key = ZewifEnvelope::derive_encryption_key(password);
+                ze.encrypt(&key)?;
+                stage.finish();
+                ze.envelope().clone()
+            }
+            None => ze.envelope().clone(),
+        };
+        Ok(match self {
+            EnvelopeWriter::Binary => envelope.to_cbor_data(),
+            EnvelopeWriter::UR => {
+                format!("{}\n", envelope.ur_string()).into_bytes()
+            }
+            EnvelopeWriter::Format => {
+                format!("{}\n", envelope.format()).into_bytes()
+            }
+        })
+    }
+}
@@ -1,10 +1,25 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use clap::Args;
+use tracing::{debug, info_span, warn};
+use zewif::{Data, Network, Zewif};
 use zewif_zingo::ZingoParser;
 
-use crate::file_args::{FileArgs, FileArgsLike};
+use crate::{
+    file_args::{FileArgs, FileArgsLike},
+    format::{
+        ReadOptions, ReadOutcome, WalletReader, WalletWriter, WriteOptions,
+    },
+    inspect::{self, Inspection},
+    network,
+    progress::Progress,
+    source::WalletSource,
+    zingo_export::export_zingo,
+};
 
 /// Process a zingo wallet file
 #[derive(Debug, Args)]
@@ -12,6 +27,11 @@ use crate::file_args::{FileArgs, FileArgsLike};
 pub struct CommandArgs {
     #[command(flatten)]
     file_args: FileArgs,
+
+    /// Fail if any bytes are left over after parsing, rather than reporting
+    /// a partial success
+    #[arg(long)]
+    strict: bool,
 }
 
 impl FileArgsLike for CommandArgs {
@@ -21,24 +41,292 @@ impl FileArgsLike for CommandArgs {
 impl crate::exec::Exec for CommandArgs {
     fn exec(&self) -> Result<String> {
         let file = self.file();
-        dump_wallet(file)
+        dump_wallet_checked(file, self.strict)
+    }
+}
+
+/// Reads zingo wallet files. These can only be dumped for now.
+pub struct ZingoReader;
+
+impl WalletReader for ZingoReader {
+    fn name(&self) -> &'static str { "zingo" }
+
+    fn help(&self) -> &'static str { "Input from a `zingo` wallet" }
+
+    fn migrates(&self) -> bool { false }
+
+    fn read(
+        &self,
+        _input: &WalletSource,
+        _options: &mut ReadOptions,
+        _progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        bail!("Zingo wallets cannot yet be migrated to Zewif; use --to dump")
+    }
+
+    fn dump(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Option<Result<String>> {
+        Some(dump_checked(input, options))
+    }
+
+    fn inspect(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+    ) -> Result<Inspection> {
+        input.with_path(options.allow_disk(), inspect::inspect_zingo)
     }
 }
 
+/// Writes zingo wallet files, checking that the zingo parser reads back
+/// what was written.
+pub struct ZingoWriter;
+
+impl WalletWriter for ZingoWriter {
+    fn name(&self) -> &'static str { "zingo" }
+
+    fn help(&self) -> &'static str { "Output as a `zingo` wallet" }
+
+    fn write(
+        &self,
+        zewif: &Zewif,
+        options: &mut WriteOptions,
+        _progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        if options.compress() || options.encrypt() {
+            bail!("Zingo wallets cannot be compressed or encrypted");
+        }
+        let bytes = export_zingo(zewif, options.rescan())?;
+        check_wallet_parses(&bytes)
+            .context("The exported zingo wallet does not parse")?;
+        Ok(bytes)
+    }
+}
+
+/// Dumps a zingo wallet, failing unless it belongs to the expected network.
+fn dump_checked(input: &WalletSource, options: &ReadOptions) -> Result<String> {
+    let bytes = input.read()?;
+    if let Some(expected) = options.expect_network() {
+        let network = wallet_network_bytes(&bytes)?;
+        if network != expected.into() {
+            bail!(
+                "Expected a {} wallet, but the input is a {} wallet",
+                network::network_name(expected.into()),
+                network::network_name(network)
+            );
+        }
+    }
+    dump_wallet_bytes(&bytes, options.strict())
+}
+
+/// The oldest zingo wallet serialization version the parser reads.
+pub const MIN_ZINGO_VERSION: u64 = 26;
+
+/// The newest zingo wallet serialization version the parser reads.
+pub const MAX_ZINGO_VERSION: u64 = 30;
+
+/// The versions between [`MIN_ZINGO_VERSION`] and [`MAX_ZINGO_VERSION`] that
+/// have test fixtures. Wallets of other versions in that range are read, but
+/// with a warning.
+pub const TESTED_ZINGO_VERSIONS: [u64; 4] = [26, 27, 28, 30];
+
+/// The most unparsed bytes shown in a dump.
+const MAX_HEX_DUMP_BYTES: usize = 1024;
+
 pub fn dump_wallet(file: &Path) -> Result<String> {
-    let file_data = std::fs::read(file)?.into();
-    let mut parser = ZingoParser::new(&file_data);
-    let wallet = parser.parse()?;
+    dump_wallet_checked(file, false)
+}
+
+/// Dumps a zingo wallet. If bytes are left over after parsing, the dump shows
+/// where parsing stopped and what was left, and with `strict` it fails
+/// instead.
+pub fn dump_wallet_checked(file: &Path, strict: bool) -> Result<String> {
+    let _span = info_span!("zingo_dump", file = %file.display()).entered();
+    let bytes = {
+        let _span = info_span!("stage", stage = "read_zingo").entered();
+        std::fs::read(file)?
+    };
+    dump_wallet_bytes(&bytes, strict)
+}
+
+/// Dumps a zingo wallet held in memory; see [`dump_wallet_checked`].
+pub fn dump_wallet_bytes(bytes: &[u8], strict: bool) -> Result<String> {
+    dump_wallet_bytes_tested(bytes, strict, &TESTED_ZINGO_VERSIONS)
+}
+
+/// Dumps a zingo wallet held in memory, warning unless its version is one of
+/// `tested_versions`.
+pub fn dump_wallet_bytes_tested(
+    bytes: &[u8],
+    strict: bool,
+    tested_versions: &[u64],
+) -> Result<String> {
+    let (file_data, version) = check_wallet_bytes(bytes)?;
+    debug!(version, bytes = file_data.len(), "Read zingo wallet");
+    let tested = tested_versions.contains(&version);
+    if !tested {
+        warn!(version, "Zingo wallet version has no test fixture");
+    }
+    let wallet = {
+        let _span = info_span!("stage", stage = "parse_zingo").entered();
+        let mut parser = ZingoParser::new(&file_data);
+        parser.parse()?
+    };
     let mut dump = format!("{:#?}", wallet);
     let remaining = wallet.remaining();
+    let offset = file_data.len() - remaining;
+    let last_parsed = last_parsed_path(&dump);
+    if remaining != 0 {
+        warn!(remaining, offset, last_parsed = %last_parsed, "Zingo wallet has unparsed bytes");
+    }
+    if remaining != 0 && strict {
+        bail!(
+            "{} unparsed bytes at offset {} (0x{:x}) after {}",
+            remaining,
+            offset,
+            offset,
+            last_parsed
+        );
+    }
     dump.push_str("\n---\n");
+    writeln!(dump, "Version: {}", version)?;
+    if !tested {
+        writeln!(
+            dump,
+            "⚠️ Version {} has not been tested against a real wallet",
+            version
+        )?;
+    }
+    writeln!(dump, "Network: {}", network::network_name(wallet.network()))?;
     if remaining == 0 {
         dump.push_str("✅ Success");
     } else {
-        dump.push_str(&format!(
-            "🛑 Unparsed bytes: {}\n✅ Success (partial)",
-            remaining
-        ));
+        writeln!(dump, "🛑 Unparsed bytes: {}", remaining)?;
+        writeln!(
+            dump,
+            "Parsing stopped at offset {} (0x{:x})",
+            offset, offset
+        )?;
+        writeln!(dump, "Last parsed: {}", last_parsed)?;
+        dump.push_str(&hex_dump(&file_data.as_ref()[offset..], offset));
+        dump.push_str("✅ Success (partial)");
     }
     Ok(dump)
 }
+
+/// The field path of the last value in a pretty-printed `Debug` dump, such
+/// as `ZingoWallet.transactions.sapling_notes`.
+///
+/// The wallet's structures are declared in the order they are serialized,
+/// so this is the last structure the parser read.
+fn last_parsed_path(debug: &str) -> String {
+    let mut path: Vec<(usize, &str)> = Vec::new();
+    for line in debug.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let name = match trimmed.split_once(": ") {
+            Some((name, _)) => name,
+            // The opening line of the root structure, e.g. `ZingoWallet {`.
+            None if indent == 0 => trimmed.trim_end_matches(" {"),
+            None => continue,
+        };
+        if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        while path.last().is_some_and(|&(i, _)| i >= indent) {
+            path.pop();
+        }
+        path.push((indent, name));
+    }
+    let names: Vec<&str> = path.iter().map(|&(_, name)| name).collect();
+    if names.is_empty() {
+        "nothing".to_string()
+    } else {
+        names.join(".")
+    }
+}
+
+/// Formats `bytes` as a hex and ASCII dump, 16 bytes per line, labelled with
+/// file offsets starting at `offset`.
+fn hex_dump(bytes: &[u8], offset: usize) -> String {
+    let mut dump = String::new();
+    let shown = bytes.len().min(MAX_HEX_DUMP_BYTES);
+    for (i, chunk) in bytes[..shown].chunks(16).enumerate() {
+        let hex: Vec<String> =
+            chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect();
+        writeln!(
+            dump,
+            "{:08x}  {:<47}  |{}|",
+            offset + i * 16,
+            hex.join(" "),
+            ascii
+        )
+        .unwrap();
+    }
+    if shown < bytes.len() {
+        writeln!(dump, "... {} more bytes", bytes.len() - shown).unwrap();
+    }
+    dump
+}
+
+/// The network a zingo wallet file belongs to.
+pub fn wallet_network(file: &Path) -> Result<Network> {
+    wallet_network_bytes(&std::fs::read(file)?)
+}
+
+/// The network a zingo wallet held in memory belongs to.
+pub fn wallet_network_bytes(bytes: &[u8]) -> Result<Network> {
+    let (file_data, _) = check_wallet_bytes(bytes)?;
+    let mut parser = ZingoParser::new(&file_data);
+    Ok(parser.parse()?.network())
+}
+
+/// The serialization version of a zingo wallet file, failing if the parser
+/// doesn't support it.
+pub fn wallet_version(file: &Path) -> Result<u64> {
+    wallet_version_bytes(&std::fs::read(file)?)
+}
+
+/// The serialization version of a zingo wallet held in memory; see
+/// [`wallet_version`].
+pub fn wallet_version_bytes(bytes: &[u8]) -> Result<u64> {
+    check_wallet_bytes(bytes).map(|(_, version)| version)
+}
+
+/// Parses a zingo wallet held in memory, failing if any bytes are left over.
+pub fn check_wallet_parses(bytes: &[u8]) -> Result<()> {
+    let (file_data, _) = check_wallet_bytes(bytes)?;
+    let mut parser = ZingoParser::new(&file_data);
+    let remaining = parser.parse()?.remaining();
+    if remaining != 0 {
+        bail!("{} unparsed bytes", remaining);
+    }
+    Ok(())
+}
+
+/// Reads the serialization version of a zingo wallet, failing if the parser
+/// doesn't support that version.
+fn check_wallet_bytes(bytes: &[u8]) -> Result<(Data, u64)> {
+    // A zingo wallet starts with its serialization version as a
+    // little-endian u64.
+    let Some(header) = bytes.first_chunk::<8>() else {
+        bail!("File is too short to be a zingo wallet");
+    };
+    let version = u64::from_le_bytes(*header);
+    if !(MIN_ZINGO_VERSION..=MAX_ZINGO_VERSION).contains(&version) {
+        bail!(
+            "unsupported zingo wallet version {} (supported: {} to {})",
+            version,
+            MIN_ZINGO_VERSION,
+            MAX_ZINGO_VERSION
+        );
+    }
+    Ok((bytes.to_vec().into(), version))
+}
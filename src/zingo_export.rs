@@ -0,0 +1,243 @@
+use anyhow::{Result, bail};
+use tracing::warn;
+use zcash_keys::keys::{Era, UnifiedSpendingKey};
+use zcash_protocol::consensus::{self, NetworkUpgrade, Parameters};
+use zewif::{Network, SeedMaterial, Zewif, ZewifWallet};
+
+use crate::{
+    keys::{WalletKeys, parse_mnemonic},
+    keys_cmd::wallet_birthday,
+};
+
+/// The zingo wallet serialization version written.
+const WALLET_VERSION: u64 = 30;
+
+/// The version of the wallet capability (the wallet's keys) written.
+const CAPABILITY_VERSION: u8 = 4;
+
+const KEY_STORE_VERSION: u8 = 0;
+
+/// Marks a key store holding a unified spending key.
+const KEY_TYPE_SPEND: u8 = 2;
+
+const RECEIVER_SELECTION_VERSION: u8 = 1;
+
+/// Orchard, Sapling and transparent receivers, as bit flags.
+const ALL_RECEIVERS: u8 = 0b111;
+
+const TX_MAP_VERSION: u64 = 22;
+
+const WALLET_OPTIONS_VERSION: u64 = 2;
+
+/// Download the memos of the wallet's own transactions.
+const DOWNLOAD_WALLET_MEMOS: u8 = 1;
+
+/// zingolib's default limit on the number of outputs of a transaction it
+/// trial-decrypts.
+const TRANSACTION_SIZE_FILTER: u32 = 500;
+
+const PRICE_INFO_VERSION: u64 = 20;
+
+/// Encodes a `Zewif` as a zingo wallet file.
+///
+/// A zingo wallet holds one ZIP-32 account of one BIP-39 seed, so `zewif`
+/// must contain exactly one wallet with a mnemonic, and that wallet at most
+/// one ZIP-32 account (account 0 is used if it has none). The wallet is
+/// written with its unified spending key, seed and birthday.
+///
+/// Transaction records are not written: zingolib's records hold the notes
+/// and witnesses it spends from, which Zewif doesn't carry, so zingo has to
+/// rebuild them by rescanning from the birthday. An input with transactions
+/// is therefore only exported if `rescan` accepts that they are left out.
+/// A warning is logged when the input has accounts that the file leaves
+/// out: those of other wallets, and those with imported keys rather than a
+/// ZIP-32 index.
+pub fn export_zingo(zewif: &Zewif, rescan: bool) -> Result<Vec<u8>> {
+    if !zewif.transactions().is_empty() && !rescan {
+        bail!(
+            "The input has {} transactions, which zingo wallets are not written with; pass --rescan to write the wallet without them, for zingo to rebuild by rescanning",
+            zewif.transactions().len()
+        );
+    }
+    let wallet = seed_wallet(zewif)?;
+    let Some(SeedMaterial::Bip39Mnemonic(mnemonic)) = wallet.seed_material()
+    else {
+        unreachable!("seed_wallet only returns wallets with a mnemonic");
+    };
+    let parsed = parse_mnemonic(mnemonic.mnemonic())?;
+    // Zingo keeps only the entropy and reads it back as an English phrase,
+    // whose seed differs from that of the same entropy in another language.
+    if parsed.language() != "English" {
+        bail!(
+            "Zingo wallets hold English mnemonics, but the wallet's mnemonic is in {}",
+            parsed.language()
+        );
+    }
+    let entropy = parsed.entropy().to_vec();
+
+    let account_ids: Vec<u32> = wallet
+        .accounts()
+        .iter()
+        .filter_map(|a| a.zip32_account_id())
+        .collect();
+    let account_id = match account_ids[..] {
+        [] => 0,
+        [account_id] => account_id,
+        _ => bail!(
+            "Zingo wallets hold one account, but the wallet has {} ZIP-32 accounts",
+            account_ids.len()
+        ),
+    };
+    let exported = wallet
+        .accounts()
+        .iter()
+        .filter(|a| a.zip32_account_id() == Some(account_id))
+        .count();
+    let dropped = zewif
+        .wallets()
+        .iter()
+        .map(|w| w.accounts().len())
+        .sum::<usize>()
+        - exported;
+    if dropped > 0 {
+        warn!(
+            count = dropped,
+            "Accounts of other wallets or with imported keys are not exported to the zingo wallet"
+        );
+    }
+    if !zewif.transactions().is_empty() {
+        warn!(
+            count = zewif.transactions().len(),
+            "Transaction records are not exported; zingo will rebuild them by rescanning from the birthday"
+        );
+    }
+
+    let usk = WalletKeys::new(wallet)?
+        .account_usk(account_id)?
+        .expect("the wallet has a seed");
+    let birthday = match wallet_birthday(zewif, wallet)? {
+        Some((height, _)) => u32::from(height),
+        None => sapling_activation_height(wallet.network()),
+    };
+
+    let mut out = Vec::new();
+    out.extend(WALLET_VERSION.to_le_bytes());
+    write_capability(&mut out, &usk);
+    // No cached blocks: zingo fetches those it needs when it syncs.
+    write_compact_size(&mut out, 0);
+    write_tx_map(&mut out);
+    write_string(&mut out, chain_name(wallet.network()));
+    write_wallet_options(&mut out);
+    out.extend(u64::from(birthday).to_le_bytes());
+    // No verified commitment tree state.
+    out.push(0);
+    write_price_info(&mut out);
+    write_compact_size(&mut out, entropy.len());
+    out.extend(&entropy);
+    out.extend(account_id.to_le_bytes());
+    Ok(out)
+}
+
+/// The one wallet in `zewif` with a BIP-39 mnemonic.
+fn seed_wallet(zewif: &Zewif) -> Result<&ZewifWallet> {
+    let wallets: Vec<&ZewifWallet> = zewif
+        .wallets()
+        .iter()
+        .filter(|w| {
+            matches!(w.seed_material(), Some(SeedMaterial::Bip39Mnemonic(_)))
+        })
+        .collect();
+    match wallets[..] {
+        [wallet] => Ok(wallet),
+        [] => bail!("Zingo wallets need a BIP-39 mnemonic seed"),
+        _ => bail!(
+            "Zingo wallets hold one seed, but the input has {} wallets with a mnemonic",
+            wallets.len()
+        ),
+    }
+}
+
+/// The name zingolib gives the network.
+fn chain_name(network: Network) -> &'static str {
+    match network {
+        Network::Main => "main",
+        Network::Test => "test",
+        Network::Regtest => "regtest",
+    }
+}
+
+/// The earliest height a wallet without a known birthday can have funds at.
+fn sapling_activation_height(network: Network) -> u32 {
+    let height =
+        match network {
+            Network::Main => consensus::MAIN_NETWORK
+                .activation_height(NetworkUpgrade::Sapling),
+            Network::Test => consensus::TEST_NETWORK
+                .activation_height(NetworkUpgrade::Sapling),
+            Network::Regtest => None,
+        };
+    height.map_or(1, u32::from)
+}
+
+/// Writes a Bitcoin-style variable-length size.
+fn write_compact_size(out: &mut Vec<u8>, size: usize) {
+    match size {
+        0..0xfd => out.push(size as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend((size as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend((size as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend((size as u64).to_le_bytes());
+        }
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend((s.len() as u64).to_le_bytes());
+    out.extend(s.as_bytes());
+}
+
+/// Writes the wallet's keys, and the receivers of the one unified address
+/// zingo derives from them.
+fn write_capability(out: &mut Vec<u8>, usk: &UnifiedSpendingKey) {
+    out.push(CAPABILITY_VERSION);
+    // No rejection addresses.
+    out.extend(0u32.to_le_bytes());
+    out.push(KEY_STORE_VERSION);
+    out.push(KEY_TYPE_SPEND);
+    let usk_bytes = usk.to_bytes(Era::Orchard);
+    write_compact_size(out, usk_bytes.len());
+    out.extend(usk_bytes);
+    write_compact_size(out, 1);
+    out.push(RECEIVER_SELECTION_VERSION);
+    out.push(ALL_RECEIVERS);
+}
+
+/// Writes an empty transaction map, with no witness trees.
+fn write_tx_map(out: &mut Vec<u8>) {
+    out.extend(TX_MAP_VERSION.to_le_bytes());
+    write_compact_size(out, 0);
+    out.push(0);
+}
+
+/// Writes zingolib's default wallet options.
+fn write_wallet_options(out: &mut Vec<u8>) {
+    out.extend(WALLET_OPTIONS_VERSION.to_le_bytes());
+    out.push(DOWNLOAD_WALLET_MEMOS);
+    out.push(1);
+    out.extend(TRANSACTION_SIZE_FILTER.to_le_bytes());
+}
+
+/// Writes price information with no prices fetched.
+fn write_price_info(out: &mut Vec<u8>) {
+    out.extend(PRICE_INFO_VERSION.to_le_bytes());
+    // No time of the last price fetch, and no retries.
+    out.push(0);
+    out.extend(0u64.to_le_bytes());
+}
@@ -0,0 +1,344 @@
+"""Generates data.db, a small zcash_client_sqlite database for tests.
+
+Only the tables and columns zmigrate reads are created. The schema follows
+zcash_client_sqlite's migrations, but the database was not written by
+zcash_client_sqlite itself. Each account's viewing keys are real unified
+keys holding a Sapling component built from random points, and its Sapling
+address is derived from them; the transparent addresses are validly encoded
+placeholders.
+"""
+
+import hashlib
+import os
+import sqlite3
+
+BECH32_CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l"
+B58_ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+
+
+def bech32_polymod(values):
+    generator = [0x3B6A57B2, 0x26508E6D, 0x1EA119FA, 0x3D4233DD, 0x2A1462B3]
+    chk = 1
+    for value in values:
+        top = chk >> 25
+        chk = (chk & 0x1FFFFFF) << 5 ^ value
+        for i in range(5):
+            chk ^= generator[i] if (top >> i) & 1 else 0
+    return chk
+
+
+def bech32_encode(hrp, data, constant=1):
+    values = convertbits(data, 8, 5)
+    expanded = [ord(x) >> 5 for x in hrp] + [0] + [ord(x) & 31 for x in hrp]
+    polymod = bech32_polymod(expanded + values + [0] * 6) ^ constant
+    checksum = [(polymod >> 5 * (5 - i)) & 31 for i in range(6)]
+    return hrp + "1" + "".join(BECH32_CHARSET[d] for d in values + checksum)
+
+
+def convertbits(data, frombits, tobits):
+    acc, bits, ret = 0, 0, []
+    for value in data:
+        acc = (acc << frombits) | value
+        bits += frombits
+        while bits >= tobits:
+            bits -= tobits
+            ret.append((acc >> bits) & ((1 << tobits) - 1))
+    if bits:
+        ret.append((acc << (tobits - bits)) & ((1 << tobits) - 1))
+    return ret
+
+
+def base58check(payload):
+    check = hashlib.sha256(hashlib.sha256(payload).digest()).digest()[:4]
+    data = payload + check
+    n = int.from_bytes(data, "big")
+    out = ""
+    while n:
+        n, r = divmod(n, 58)
+        out = B58_ALPHABET[r] + out
+    pad = len(data) - len(data.lstrip(b"\0"))
+    return "1" * pad + out
+
+
+def digest(label, size):
+    return hashlib.sha256(label.encode()).digest()[:size]
+
+
+# The Jubjub curve, as used by Sapling.
+JUBJUB_Q = 0x73EDA753299D7D483339D80809A1D80553BDA402FFFE5BFEFFFFFFFF00000001
+JUBJUB_D = -10240 * pow(10241, -1, JUBJUB_Q) % JUBJUB_Q
+URS = b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0"
+
+
+def fq_sqrt(a):
+    """Tonelli-Shanks square root modulo JUBJUB_Q, or None."""
+    q = JUBJUB_Q
+    a %= q
+    if a == 0:
+        return 0
+    if pow(a, (q - 1) // 2, q) != 1:
+        return None
+    s, t = 0, q - 1
+    while t % 2 == 0:
+        s, t = s + 1, t // 2
+    z = 2
+    while pow(z, (q - 1) // 2, q) != q - 1:
+        z += 1
+    m, c, x, b = s, pow(z, t, q), pow(a, (t + 1) // 2, q), pow(a, t, q)
+    while b != 1:
+        i, b2 = 0, b
+        while b2 != 1:
+            i, b2 = i + 1, b2 * b2 % q
+        f = pow(c, 1 << (m - i - 1), q)
+        m, c, x, b = i, f * f % q, x * f % q, b * f * f % q
+    return x
+
+
+def point_add(p, r):
+    q = JUBJUB_Q
+    (u1, v1), (u2, v2) = p, r
+    t = JUBJUB_D * u1 * u2 * v1 * v2 % q
+    return (
+        (u1 * v2 + v1 * u2) * pow(1 + t, -1, q) % q,
+        (v1 * v2 + u1 * u2) * pow(1 - t, -1, q) % q,
+    )
+
+
+def point_mul(k, p):
+    acc = (0, 1)
+    while k:
+        if k & 1:
+            acc = point_add(acc, p)
+        p, k = point_add(p, p), k >> 1
+    return acc
+
+
+def point_decode(data):
+    n = int.from_bytes(data, "little")
+    sign, v = n >> 255, n & ((1 << 255) - 1)
+    if v >= JUBJUB_Q:
+        return None
+    u = fq_sqrt((v * v - 1) * pow(1 + JUBJUB_D * v * v, -1, JUBJUB_Q))
+    if u is None or (u == 0 and sign):
+        return None
+    return (u if u & 1 == sign else JUBJUB_Q - u, v)
+
+
+def point_encode(p):
+    u, v = p
+    return (v | (u & 1) << 255).to_bytes(32, "little")
+
+
+def group_hash(personal, message):
+    """Sapling's GroupHash into the prime-order subgroup, or None."""
+    h = hashlib.blake2s(URS + message, person=personal).digest()
+    p = point_decode(h)
+    if p is None:
+        return None
+    p = point_mul(8, p)
+    return None if p == (0, 1) else p
+
+
+def subgroup_point(label):
+    """A point of the prime-order subgroup, hashed from `label`."""
+    counter = 0
+    while True:
+        point = group_hash(b"Zcash_gd", digest("%s/%d" % (label, counter), 32))
+        if point is not None:
+            return point_encode(point)
+        counter += 1
+
+
+def sapling_keys(label):
+    """Sapling (ak, nk, ovk, dk, ivk) from random subgroup points."""
+    ak = subgroup_point(label + "/ak")
+    nk = subgroup_point(label + "/nk")
+    h = bytearray(hashlib.blake2s(ak + nk, person=b"Zcashivk").digest())
+    h[31] &= 0x07
+    return ak, nk, digest(label + "/ovk", 32), digest(label + "/dk", 32), bytes(h)
+
+
+def sapling_address(label, ivk=None):
+    """A Sapling address for the first diversifier that is valid, derived
+    from `ivk` or from a throwaway key."""
+    if ivk is None:
+        ivk = sapling_keys(label)[4]
+    counter = 0
+    while True:
+        d = digest("%s/d%d" % (label, counter), 11)
+        g_d = group_hash(b"Zcash_gd", d)
+        if g_d is not None:
+            pk_d = point_mul(int.from_bytes(ivk, "little"), g_d)
+            return bech32_encode("zs", d + point_encode(pk_d))
+        counter += 1
+
+
+def f4jumble(message):
+    """The ZIP-316 F4Jumble permutation."""
+    left = min(64, len(message) // 2)
+    right = len(message) - left
+
+    def h(i, u):
+        person = b"UA_F4Jumble_H" + bytes([i, 0, 0])
+        return hashlib.blake2b(u, digest_size=left, person=person).digest()
+
+    def g(i, u):
+        out = b""
+        for j in range((right + 63) // 64):
+            person = b"UA_F4Jumble_G" + bytes([i]) + j.to_bytes(2, "little")
+            out += hashlib.blake2b(u, person=person).digest()
+        return out[:right]
+
+    def xor(a, b):
+        return bytes(x ^ y for x, y in zip(a, b))
+
+    a, b = message[:left], message[left:]
+    x = xor(b, g(0, a))
+    y = xor(a, h(0, x))
+    d = xor(x, g(1, y))
+    c = xor(y, h(1, d))
+    return c + d
+
+
+def unified_encoding(hrp, items):
+    """A ZIP-316 unified encoding of (typecode, data) items."""
+    # Every item is short enough for one-byte CompactSize lengths.
+    raw = b""
+    for typecode, data in sorted(items):
+        raw += bytes([typecode, len(data)]) + data
+    padding = hrp.encode() + bytes(16 - len(hrp))
+    return bech32_encode(hrp, f4jumble(raw + padding), 0x2BC830A3)
+
+
+def unified_viewing_keys(label):
+    """A Sapling-only UFVK and UIVK for mainnet."""
+    ak, nk, ovk, dk, ivk = sapling_keys(label)
+    ufvk = unified_encoding("uview", [(2, ak + nk + ovk + dk)])
+    uivk = unified_encoding("uivk", [(2, dk + ivk)])
+    return ufvk, uivk, ivk
+
+
+def transparent_address(label):
+    return base58check(bytes([0x1C, 0xB8]) + digest(label, 20))
+
+
+def memo(text):
+    data = text.encode()
+    return data + bytes(512 - len(data))
+
+
+def main():
+    path = os.path.join(os.path.dirname(__file__), "data.db")
+    if os.path.exists(path):
+        os.remove(path)
+    db = sqlite3.connect(path)
+    db.executescript(
+        """
+        CREATE TABLE accounts (id INTEGER PRIMARY KEY, name TEXT,
+            uuid BLOB NOT NULL, account_kind INTEGER NOT NULL DEFAULT 0,
+            key_source TEXT, hd_seed_fingerprint BLOB,
+            hd_account_index INTEGER, ufvk TEXT, uivk TEXT NOT NULL,
+            birthday_height INTEGER NOT NULL,
+            recover_until_height INTEGER, has_spend_key INTEGER NOT NULL);
+        CREATE TABLE addresses (id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL, diversifier_index_be BLOB,
+            address TEXT NOT NULL,
+            cached_transparent_receiver_address TEXT);
+        CREATE TABLE transactions (id_tx INTEGER PRIMARY KEY,
+            txid BLOB NOT NULL UNIQUE, created TEXT, block INTEGER,
+            mined_height INTEGER, tx_index INTEGER, expiry_height INTEGER,
+            raw BLOB, fee INTEGER);
+        CREATE TABLE sapling_received_notes (id INTEGER PRIMARY KEY,
+            tx INTEGER NOT NULL, output_index INTEGER NOT NULL,
+            account_id INTEGER NOT NULL, diversifier BLOB NOT NULL,
+            value INTEGER NOT NULL, rcm BLOB NOT NULL, nf BLOB UNIQUE,
+            is_change INTEGER NOT NULL, memo BLOB,
+            commitment_tree_position INTEGER,
+            recipient_key_scope INTEGER);
+        CREATE TABLE sapling_received_note_spends (
+            sapling_received_note_id INTEGER NOT NULL,
+            transaction_id INTEGER NOT NULL);
+        CREATE TABLE orchard_received_notes (id INTEGER PRIMARY KEY,
+            tx INTEGER NOT NULL, action_index INTEGER NOT NULL,
+            account_id INTEGER NOT NULL, diversifier BLOB NOT NULL,
+            value INTEGER NOT NULL, rho BLOB NOT NULL, rseed BLOB NOT NULL,
+            nf BLOB UNIQUE, is_change INTEGER NOT NULL, memo BLOB,
+            commitment_tree_position INTEGER,
+            recipient_key_scope INTEGER);
+        CREATE TABLE orchard_received_note_spends (
+            orchard_received_note_id INTEGER NOT NULL,
+            transaction_id INTEGER NOT NULL);
+        CREATE TABLE sent_notes (id INTEGER PRIMARY KEY,
+            tx INTEGER NOT NULL, output_pool INTEGER NOT NULL,
+            output_index INTEGER NOT NULL, from_account_id INTEGER NOT NULL,
+            to_address TEXT, to_account_id INTEGER, value INTEGER NOT NULL,
+            memo BLOB);
+        """
+    )
+    fingerprint = digest("seed", 32)
+    accounts = [
+        (1, "Default", 0, fingerprint, 0, 2_000_000),
+        (2, "Savings", 0, fingerprint, 1, 2_000_000),
+        (3, None, 1, None, None, 2_100_000),
+    ]
+    for id, name, kind, fp, index, birthday in accounts:
+        # The imported account has only an incoming viewing key.
+        ufvk, uivk, ivk = unified_viewing_keys("account%d" % id)
+        db.execute(
+            "INSERT INTO accounts VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (id, name, digest("uuid%d" % id, 16), kind, None, fp, index,
+             ufvk if kind == 0 else None, uivk, birthday, None,
+             1 if kind == 0 else 0),
+        )
+        db.execute(
+            "INSERT INTO addresses VALUES (?, ?, ?, ?, ?)",
+            (id, id, bytes(11), sapling_address("account%d" % id, ivk),
+             transparent_address("account%d" % id) if kind == 0 else None),
+        )
+    transactions = [
+        (1, 2_000_010, 1_000),
+        (2, 2_000_020, 10_000),
+        (3, 2_100_005, None),
+        (4, None, 10_000),
+    ]
+    for id, height, fee in transactions:
+        db.execute(
+            "INSERT INTO transactions VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (id, digest("tx%d" % id, 32), None, height, height, 0,
+             None if height is None else height + 40, None, fee),
+        )
+    sapling_notes = [
+        (1, 1, 1, 50_000_000, 0, "Hello from the faucet"),
+        (2, 2, 1, 39_990_000, 1, None),
+        (3, 3, 3, 1_000_000, 0, None),
+    ]
+    for id, tx, account, value, change, text in sapling_notes:
+        db.execute(
+            "INSERT INTO sapling_received_notes VALUES "
+            "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (id, tx, 0, account, digest("d%d" % id, 11), value,
+             digest("rcm%d" % id, 32), digest("nf%d" % id, 32), change,
+             None if text is None else memo(text), id, 0),
+        )
+    db.execute(
+        "INSERT INTO orchard_received_notes VALUES "
+        "(1, 2, 1, 2, ?, 10000000, ?, ?, ?, 0, ?, 1, 0)",
+        (digest("od1", 11), digest("rho1", 32), digest("rseed1", 32),
+         digest("onf1", 32), memo("Savings")),
+    )
+    db.execute("INSERT INTO sapling_received_note_spends VALUES (1, 2)")
+    sent_notes = [
+        (1, 2, 3, 1, 1, None, 2, 10_000_000, memo("Savings")),
+        (2, 4, 2, 0, 1, sapling_address("alice"), None, 5_000_000, None),
+    ]
+    for note in sent_notes:
+        db.execute(
+            "INSERT INTO sent_notes VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)", note
+        )
+    db.commit()
+    db.execute("VACUUM")
+    db.close()
+
+
+if __name__ == "__main__":
+    main()
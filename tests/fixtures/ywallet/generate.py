@@ -0,0 +1,468 @@
+"""Generates zec.db, a small YWallet database for tests.
+
+The two seed accounts use the BIP-39 test mnemonic, and their keys and
+addresses are derived from it as YWallet derives them: the Sapling key at
+m/32'/133'/index' with its default address, and the transparent key at
+m/44'/133'/index'/0/0. The second account's transparent secret key is
+validly encoded but belongs to another index, so it doesn't derive the
+account's address. The imported account's Sapling spending key is derived
+from the seed too, but its address is a placeholder it doesn't derive, and
+its full viewing key is a placeholder that doesn't decode.
+
+The schema follows YWallet's source; it has not been checked against a
+database written by YWallet itself.
+"""
+
+import hashlib
+import hmac
+import os
+import sqlite3
+
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+
+MNEMONIC = " ".join(["abandon"] * 11 + ["about"])
+
+BECH32_CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l"
+B58_ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+
+
+def bech32_polymod(values):
+    generator = [0x3B6A57B2, 0x26508E6D, 0x1EA119FA, 0x3D4233DD, 0x2A1462B3]
+    chk = 1
+    for value in values:
+        top = chk >> 25
+        chk = (chk & 0x1FFFFFF) << 5 ^ value
+        for i in range(5):
+            chk ^= generator[i] if (top >> i) & 1 else 0
+    return chk
+
+
+def bech32_encode(hrp, data):
+    values = convertbits(data, 8, 5)
+    expanded = [ord(x) >> 5 for x in hrp] + [0] + [ord(x) & 31 for x in hrp]
+    polymod = bech32_polymod(expanded + values + [0] * 6) ^ 1
+    checksum = [(polymod >> 5 * (5 - i)) & 31 for i in range(6)]
+    return hrp + "1" + "".join(BECH32_CHARSET[d] for d in values + checksum)
+
+
+def convertbits(data, frombits, tobits):
+    acc, bits, ret = 0, 0, []
+    for value in data:
+        acc = (acc << frombits) | value
+        bits += frombits
+        while bits >= tobits:
+            bits -= tobits
+            ret.append((acc >> bits) & ((1 << tobits) - 1))
+    if bits:
+        ret.append((acc << (tobits - bits)) & ((1 << tobits) - 1))
+    return ret
+
+
+def base58check(payload):
+    check = hashlib.sha256(hashlib.sha256(payload).digest()).digest()[:4]
+    data = payload + check
+    n = int.from_bytes(data, "big")
+    out = ""
+    while n:
+        n, r = divmod(n, 58)
+        out = B58_ALPHABET[r] + out
+    pad = len(data) - len(data.lstrip(b"\0"))
+    return "1" * pad + out
+
+
+def digest(label, size):
+    return hashlib.sha256(label.encode()).digest()[:size]
+
+
+def placeholder_sapling_address(label):
+    """A validly encoded Sapling address that no key derives."""
+    return bech32_encode("zs", digest(label + "/d", 11) + digest(label, 32))
+
+
+# The Jubjub curve and Sapling's key components.
+JUBJUB_Q = 0x73EDA753299D7D483339D80809A1D80553BDA402FFFE5BFEFFFFFFFF00000001
+JUBJUB_R = 0x0E7DB4EA6533AFA906673B0101343B00A6682093CCC81082D0970E5ED6F72CB7
+JUBJUB_D = -10240 * pow(10241, -1, JUBJUB_Q) % JUBJUB_Q
+URS = b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0"
+
+
+def fq_sqrt(a):
+    """Tonelli-Shanks square root modulo JUBJUB_Q, or None."""
+    q = JUBJUB_Q
+    a %= q
+    if a == 0:
+        return 0
+    if pow(a, (q - 1) // 2, q) != 1:
+        return None
+    s, t = 0, q - 1
+    while t % 2 == 0:
+        s, t = s + 1, t // 2
+    z = 2
+    while pow(z, (q - 1) // 2, q) != q - 1:
+        z += 1
+    m, c, x, b = s, pow(z, t, q), pow(a, (t + 1) // 2, q), pow(a, t, q)
+    while b != 1:
+        i, b2 = 0, b
+        while b2 != 1:
+            i, b2 = i + 1, b2 * b2 % q
+        f = pow(c, 1 << (m - i - 1), q)
+        m, c, x, b = i, f * f % q, x * f % q, b * f * f % q
+    return x
+
+
+def point_add(p, r):
+    q = JUBJUB_Q
+    (u1, v1), (u2, v2) = p, r
+    t = JUBJUB_D * u1 * u2 * v1 * v2 % q
+    return (
+        (u1 * v2 + v1 * u2) * pow(1 + t, -1, q) % q,
+        (v1 * v2 + u1 * u2) * pow(1 - t, -1, q) % q,
+    )
+
+
+def point_mul(k, p):
+    acc = (0, 1)
+    while k:
+        if k & 1:
+            acc = point_add(acc, p)
+        p, k = point_add(p, p), k >> 1
+    return acc
+
+
+def point_decode(data):
+    n = int.from_bytes(data, "little")
+    sign, v = n >> 255, n & ((1 << 255) - 1)
+    if v >= JUBJUB_Q:
+        return None
+    u = fq_sqrt((v * v - 1) * pow(1 + JUBJUB_D * v * v, -1, JUBJUB_Q))
+    if u is None or (u == 0 and sign):
+        return None
+    return (u if u & 1 == sign else JUBJUB_Q - u, v)
+
+
+def point_encode(p):
+    u, v = p
+    return (v | (u & 1) << 255).to_bytes(32, "little")
+
+
+def group_hash(personal, message):
+    """Sapling's GroupHash into the prime-order subgroup, or None."""
+    h = hashlib.blake2s(URS + message, person=personal).digest()
+    p = point_decode(h)
+    if p is None:
+        return None
+    p = point_mul(8, p)
+    return None if p == (0, 1) else p
+
+
+def find_group_hash(personal, message):
+    for i in range(256):
+        p = group_hash(personal, message + bytes([i]))
+        if p is not None:
+            return p
+    raise ValueError("no group hash")
+
+
+SPENDING_KEY_GENERATOR = find_group_hash(b"Zcash_G_", b"")
+PROOF_GENERATION_KEY_GENERATOR = find_group_hash(b"Zcash_H_", b"")
+
+
+def prf_expand(key, t):
+    return hashlib.blake2b(key + t, person=b"Zcash_ExpandSeed").digest()
+
+
+def to_scalar(data):
+    return int.from_bytes(data, "little") % JUBJUB_R
+
+
+def ff1_aes256_binary(key, data):
+    """FF1-AES256 of the bits of `data`, least significant first in each
+    byte, with an empty tweak: Sapling's diversifier derivation."""
+    bits = [(byte >> i) & 1 for byte in data for i in range(8)]
+    n = len(bits)
+    u, v = n // 2, n - n // 2
+    b = (v + 7) // 8
+    d = 4 * ((b + 3) // 4) + 4
+    p = (bytes([1, 2, 1]) + (2).to_bytes(3, "big") + bytes([10, u % 256])
+         + n.to_bytes(4, "big") + bytes(4))
+
+    def num(numerals):
+        return int("".join(map(str, numerals)) or "0", 2)
+
+    def prf(x):
+        encryptor = Cipher(algorithms.AES(key), modes.CBC(bytes(16))).encryptor()
+        return (encryptor.update(x) + encryptor.finalize())[-16:]
+
+    a, b_ = bits[:u], bits[u:]
+    for i in range(10):
+        q = bytes((-b - 1) % 16) + bytes([i]) + num(b_).to_bytes(b, "big")
+        y = int.from_bytes(prf(p + q)[:d], "big")
+        m = u if i % 2 == 0 else v
+        c = (num(a) + y) % (1 << m)
+        a, b_ = b_, [int(bit) for bit in format(c, "0%db" % m)]
+    out = a + b_
+    return bytes(
+        sum(out[8 * j + i] << i for i in range(8)) for j in range(len(data))
+    )
+
+
+class SaplingKey:
+    """A ZIP-32 Sapling extended spending key."""
+
+    def __init__(self, depth, tag, index, chain, ask, nsk, ovk, dk):
+        self.depth, self.tag, self.index, self.chain = depth, tag, index, chain
+        self.ask, self.nsk, self.ovk, self.dk = ask, nsk, ovk, dk
+
+    @classmethod
+    def master(cls, seed):
+        i = hashlib.blake2b(seed, person=b"ZcashIP32Sapling").digest()
+        sk, chain = i[:32], i[32:]
+        return cls(0, bytes(4), 0, chain,
+                   to_scalar(prf_expand(sk, b"\x00")),
+                   to_scalar(prf_expand(sk, b"\x01")),
+                   prf_expand(sk, b"\x02")[:32],
+                   prf_expand(sk, b"\x10")[:32])
+
+    def fvk_parts(self):
+        ak = point_encode(point_mul(self.ask, SPENDING_KEY_GENERATOR))
+        nk = point_encode(point_mul(self.nsk, PROOF_GENERATION_KEY_GENERATOR))
+        return ak, nk
+
+    def child(self, index):
+        """The hardened child at `index`."""
+        index |= 1 << 31
+        parts = (self.ask.to_bytes(32, "little")
+                 + self.nsk.to_bytes(32, "little") + self.ovk + self.dk)
+        i = prf_expand(self.chain, b"\x11" + parts + index.to_bytes(4, "little"))
+        il, chain = i[:32], i[32:]
+        ak, nk = self.fvk_parts()
+        fingerprint = hashlib.blake2b(
+            ak + nk + self.ovk, digest_size=32, person=b"ZcashSaplingFVFP"
+        ).digest()
+        return SaplingKey(
+            self.depth + 1, fingerprint[:4], index, chain,
+            (to_scalar(prf_expand(il, b"\x13")) + self.ask) % JUBJUB_R,
+            (to_scalar(prf_expand(il, b"\x14")) + self.nsk) % JUBJUB_R,
+            prf_expand(il, b"\x15" + self.ovk)[:32],
+            prf_expand(il, b"\x16" + self.dk)[:32],
+        )
+
+    def header(self):
+        return (bytes([self.depth]) + self.tag
+                + self.index.to_bytes(4, "little") + self.chain)
+
+    def encode(self):
+        return bech32_encode("secret-extended-key-main", self.header()
+                             + self.ask.to_bytes(32, "little")
+                             + self.nsk.to_bytes(32, "little")
+                             + self.ovk + self.dk)
+
+    def encode_viewing_key(self):
+        ak, nk = self.fvk_parts()
+        return bech32_encode("zxviews", self.header() + ak + nk + self.ovk
+                             + self.dk)
+
+    def default_address(self):
+        ak, nk = self.fvk_parts()
+        ivk = bytearray(hashlib.blake2s(ak + nk, person=b"Zcashivk").digest())
+        ivk[31] &= 0x07
+        ivk = int.from_bytes(ivk, "little")
+        j = 0
+        while True:
+            d = ff1_aes256_binary(self.dk, j.to_bytes(11, "little"))
+            g_d = group_hash(b"Zcash_gd", d)
+            if g_d is not None:
+                pk_d = point_encode(point_mul(ivk, g_d))
+                return bech32_encode("zs", d + pk_d)
+            j += 1
+
+
+def sapling_key(seed, index):
+    return SaplingKey.master(seed).child(32).child(133).child(index)
+
+
+# secp256k1 and BIP-32, for transparent keys.
+SECP_P = 2**256 - 2**32 - 977
+SECP_N = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+SECP_G = (
+    0x79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798,
+    0x483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8,
+)
+
+
+def secp_add(p, r):
+    if p is None:
+        return r
+    if r is None:
+        return p
+    if p[0] == r[0] and (p[1] + r[1]) % SECP_P == 0:
+        return None
+    if p == r:
+        lam = 3 * p[0] * p[0] * pow(2 * p[1], -1, SECP_P)
+    else:
+        lam = (r[1] - p[1]) * pow(r[0] - p[0], -1, SECP_P)
+    x = (lam * lam - p[0] - r[0]) % SECP_P
+    return x, (lam * (p[0] - x) - p[1]) % SECP_P
+
+
+def secp_public_key(secret):
+    acc, p = None, SECP_G
+    while secret:
+        if secret & 1:
+            acc = secp_add(acc, p)
+        p, secret = secp_add(p, p), secret >> 1
+    return bytes([2 + (acc[1] & 1)]) + acc[0].to_bytes(32, "big")
+
+
+def bip32_key(seed, path):
+    """The secret key at a BIP-32 path of (index, hardened) pairs."""
+    i = hmac.new(b"Bitcoin seed", seed, hashlib.sha512).digest()
+    key, chain = int.from_bytes(i[:32], "big"), i[32:]
+    for index, hardened in path:
+        if hardened:
+            data = bytes(1) + key.to_bytes(32, "big")
+            index |= 1 << 31
+        else:
+            data = secp_public_key(key)
+        i = hmac.new(chain, data + index.to_bytes(4, "big"),
+                     hashlib.sha512).digest()
+        key, chain = (int.from_bytes(i[:32], "big") + key) % SECP_N, i[32:]
+    return key
+
+
+def hash160(data):
+    return hashlib.new("ripemd160", hashlib.sha256(data).digest()).digest()
+
+
+def transparent_key(seed, index):
+    """YWallet's transparent secret key and address for an account: the
+    first external key of BIP-44 account `index`."""
+    key = bip32_key(seed, [(44, True), (133, True), (index, True),
+                           (0, False), (0, False)])
+    pubkey_hash = hash160(secp_public_key(key))
+    # Wallet import format, for a compressed public key.
+    wif = base58check(bytes([0x80]) + key.to_bytes(32, "big") + bytes([1]))
+    return wif, base58check(bytes([0x1C, 0xB8]) + pubkey_hash)
+
+
+def bip39_seed(mnemonic):
+    return hashlib.pbkdf2_hmac("sha512", mnemonic.encode(), b"mnemonic", 2048)
+
+
+def self_check(seed):
+    """Checks the derivations against known values."""
+    # The SpendAuthSig basepoint, as in the reddsa crate.
+    assert point_encode(SPENDING_KEY_GENERATOR).hex() == (
+        "30b5f2aaad325630bcdddbce4d67656d05fd1cc2d037bb5375b6e96d9e01a1d7")
+    # The Zcash FF1 test vector.
+    key = bytes.fromhex(
+        "2b7e151628aed2a6abf7158809cf4f3cef4359d8d580aa4f7f036d6f04fc6a94")
+    assert ff1_aes256_binary(key, bytes(11)).hex() == "90acee3f83cde7ae5622f3"
+    # Bitcoin's m/44'/0'/0'/0/0 address for the BIP-39 test mnemonic.
+    key = bip32_key(seed, [(44, True), (0, True), (0, True), (0, False),
+                           (0, False)])
+    address = base58check(bytes(1) + hash160(secp_public_key(key)))
+    assert address == "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA", address
+
+
+def main():
+    path = os.path.join(os.path.dirname(__file__), "zec.db")
+    if os.path.exists(path):
+        os.remove(path)
+    db = sqlite3.connect(path)
+    db.executescript(
+        """
+        CREATE TABLE accounts (id_account INTEGER PRIMARY KEY,
+            name TEXT NOT NULL, seed TEXT, aindex INTEGER NOT NULL,
+            sk TEXT, ivk TEXT NOT NULL UNIQUE, address TEXT NOT NULL);
+        CREATE TABLE taddrs (account INTEGER PRIMARY KEY NOT NULL,
+            sk TEXT, address TEXT NOT NULL);
+        CREATE TABLE orchard_addrs (account INTEGER PRIMARY KEY,
+            sk BLOB, fvk BLOB NOT NULL);
+        CREATE TABLE transactions (id_tx INTEGER PRIMARY KEY,
+            account INTEGER NOT NULL, txid BLOB NOT NULL,
+            height INTEGER NOT NULL, timestamp INTEGER NOT NULL,
+            value INTEGER NOT NULL, address TEXT, memo TEXT,
+            tx_index INTEGER,
+            CONSTRAINT tx_account UNIQUE (height, tx_index, account));
+        CREATE TABLE received_notes (id_note INTEGER PRIMARY KEY,
+            account INTEGER NOT NULL, position INTEGER NOT NULL,
+            tx INTEGER NOT NULL, height INTEGER NOT NULL,
+            output_index INTEGER NOT NULL, diversifier BLOB NOT NULL,
+            value INTEGER NOT NULL, rcm BLOB NOT NULL,
+            nf BLOB NOT NULL UNIQUE, rho BLOB,
+            orchard BOOL NOT NULL DEFAULT false, spent INTEGER,
+            excluded BOOL,
+            CONSTRAINT tx_output UNIQUE (tx, orchard, output_index));
+        CREATE TABLE contacts (id INTEGER PRIMARY KEY, name TEXT NOT NULL,
+            address TEXT NOT NULL, dirty BOOL NOT NULL);
+        """
+    )
+    seed = bip39_seed(MNEMONIC)
+    self_check(seed)
+    imported = sapling_key(seed, 7)
+    accounts = [
+        # id, name, seed, index, sk, fvk, address
+        (1, "Main", MNEMONIC, 0, sapling_key(seed, 0)),
+        (2, "Savings", MNEMONIC, 1, sapling_key(seed, 1)),
+        (3, "Imported", None, 0, imported),
+    ]
+    for id, name, mnemonic, aindex, key in accounts:
+        if mnemonic is None:
+            fvk = "zxviews1placeholder%d" % id
+            address = placeholder_sapling_address("account%d" % id)
+        else:
+            fvk = key.encode_viewing_key()
+            address = key.default_address()
+        db.execute(
+            "INSERT INTO accounts VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (id, name, mnemonic, aindex, key.encode(), fvk, address),
+        )
+        if mnemonic is not None:
+            tsk, taddress = transparent_key(seed, aindex)
+            if id == 2:
+                tsk, _ = transparent_key(seed, 5)
+            db.execute(
+                "INSERT INTO taddrs VALUES (?, ?, ?)", (id, tsk, taddress)
+            )
+            db.execute(
+                "INSERT INTO orchard_addrs VALUES (?, ?, ?)",
+                (id, None, digest("orchard%d" % id, 32) * 3),
+            )
+    transactions = [
+        (1, 1, 2_000_000, 50_000_000, "Hello from the faucet"),
+        (2, 1, 2_000_100, -10_000, None),
+        (3, 2, 2_000_100, 10_000_000, "Savings"),
+        (4, 3, 2_000_200, 1_000_000, None),
+    ]
+    for id, account, height, value, memo in transactions:
+        db.execute(
+            "INSERT INTO transactions VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (id, account, digest("tx%d" % id, 32), height,
+             1_700_000_000 + id, value, None, memo, 0),
+        )
+    notes = [
+        (1, 1, 1, 50_000_000, 2_000_100),
+        (2, 1, 2, 49_990_000 - 10_000_000, None),
+        (3, 2, 3, 10_000_000, None),
+        (4, 3, 4, 1_000_000, None),
+    ]
+    for id, account, tx, value, spent in notes:
+        db.execute(
+            "INSERT INTO received_notes VALUES "
+            "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (id, account, id, tx, transactions[tx - 1][2], 0,
+             digest("d%d" % id, 11), value, digest("rcm%d" % id, 32),
+             digest("nf%d" % id, 32), None, False, spent, False),
+        )
+    db.execute(
+        "INSERT INTO contacts VALUES (1, 'Alice', ?, 0)",
+        (placeholder_sapling_address("alice"),),
+    )
+    db.commit()
+    db.execute("VACUUM")
+    db.close()
+
+
+if __name__ == "__main__":
+    main()
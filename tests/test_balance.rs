@@ -0,0 +1,122 @@
+use zmigrate::{
+    balance::{self, BalanceReport, Pool},
+    progress::Progress,
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn test_balance(path_elements: &[&str]) {
+    let path = fixtures_path(path_elements);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap_or_else(|_| {
+        panic!("Unable to process file: {:?}", path_elements)
+    });
+    let report = balance::compute_balances(&zewif, 1).unwrap();
+
+    let account_count: usize =
+        zewif.wallets().iter().map(|w| w.accounts().len()).sum();
+    assert_eq!(report.accounts().len(), account_count);
+
+    // Every unspent note must be reflected in its pool's balance.
+    for account in report.accounts() {
+        for note in account.notes() {
+            if note.spent() == Some(false) {
+                let pool_total = account.balances()[&note.pool()].total();
+                assert!(pool_total >= note.value());
+            }
+        }
+    }
+
+    let total = report.to_total_balance_json();
+    assert!(total["total"].as_str().unwrap().contains('.'));
+}
+
+#[test]
+fn test_balance_zcashd() {
+    let paths = vec![
+        vec!["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "golden-v5.6.0", "node2_wallet.dat"],
+        vec!["zcashd", "tarnished-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "wallet0.dat"],
+        vec!["zcashd", "wallet5.dat"],
+    ];
+    for path in &paths {
+        test_balance(path);
+    }
+}
+
+/// The balances of a zcashd wallet, using the Sapling nullifiers it keeps.
+fn zcashd_balances(path_elements: &[&str]) -> BalanceReport {
+    let path = fixtures_path(path_elements);
+    let outcome =
+        zcashd_cmd::migrate_zcashd(&path, false, &Progress::quiet()).unwrap();
+    balance::compute_balances_with_nullifiers(
+        outcome.zewif(),
+        outcome.sapling_nullifiers(),
+        1,
+    )
+    .unwrap()
+}
+
+/// The total of one pool over every account of a report.
+fn pool_total(report: &BalanceReport, pool: Pool) -> u64 {
+    report
+        .accounts()
+        .iter()
+        .map(|a| a.total(|p| p == pool).total())
+        .sum()
+}
+
+#[test]
+fn test_golden_balances() {
+    // Computed independently from the golden v5.6.0 fixtures' raw
+    // transactions: transparent outputs to each node's own addresses that
+    // none of its transactions spend, and the Orchard notes left from node2
+    // shielding a 10 ZEC coinbase output, less the fees of the Orchard
+    // transactions that followed.
+    let transparent = [
+        ("node0", 74_062_560_000),
+        ("node1", 47_375_000_000),
+        ("node2", 63_375_115_000),
+        ("node3", 40_625_000_000),
+    ];
+    let mut orchard = 0;
+    for (node, expected) in transparent {
+        let file = format!("{}_wallet.dat", node);
+        let report = zcashd_balances(&["zcashd", "golden-v5.6.0", &file]);
+        assert_eq!(
+            pool_total(&report, Pool::Transparent),
+            expected,
+            "{}",
+            node
+        );
+        assert_eq!(pool_total(&report, Pool::Sapling), 0, "{}", node);
+        assert_eq!(pool_total(&report, Pool::Sprout), 0, "{}", node);
+        for account in report.accounts() {
+            assert_eq!(account.unknown_spent_notes().count(), 0, "{}", node);
+        }
+        orchard += pool_total(&report, Pool::Orchard);
+    }
+    assert_eq!(orchard, 999_825_000);
+}
+
+#[test]
+fn test_sprout_balance_is_flagged() {
+    let report = zcashd_balances(&["zcashd", "sprout", "node0_wallet.dat"]);
+    assert!(report.accounts().iter().any(|a| a.sprout_addresses() > 0));
+    assert_eq!(pool_total(&report, Pool::Sprout), 0);
+    assert!(
+        report
+            .to_string()
+            .contains("Sprout balances are not computed")
+    );
+}
+
+#[test]
+fn test_format_zec() {
+    assert_eq!(balance::format_zec(0), "0.00000000");
+    assert_eq!(balance::format_zec(1), "0.00000001");
+    assert_eq!(balance::format_zec(1_250_000_000), "12.50000000");
+}
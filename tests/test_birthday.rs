@@ -0,0 +1,83 @@
+use zewif::{BlockHeight, Network, Zewif};
+use zmigrate::{
+    birthday,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_height_at_time() {
+    // 2023-01-01, when the chain was at about height 1,950,000.
+    let height = birthday::height_at_time(Network::Main, 1_672_531_200)
+        .map(u32::from)
+        .unwrap();
+    assert!(height < 1_950_000);
+    assert!(height > 1_800_000);
+
+    // Estimates never go below Sapling activation.
+    assert_eq!(
+        birthday::height_at_time(Network::Main, 1_500_000_000),
+        Some(BlockHeight::from(419_200))
+    );
+    assert_eq!(
+        birthday::height_at_time(Network::Regtest, 1_672_531_200),
+        None
+    );
+}
+
+#[test]
+fn test_estimated_birthdays() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    for account in zewif.wallets().iter().flat_map(|w| w.accounts()) {
+        let earliest = account
+            .relevant_transactions()
+            .iter()
+            .filter_map(|txid| zewif.transactions().get(txid))
+            .filter_map(|tx| tx.mined_height())
+            .min();
+        // A regtest wallet's key times give no estimate, so the birthday is
+        // that of the first transaction.
+        assert_eq!(account.birthday_height(), earliest);
+    }
+}
+
+#[test]
+fn test_key_times_ignored_off_mainnet() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    // The regtest wallets without their transactions or birthdays, so only
+    // a key time could give an estimate.
+    let mut bare = Zewif::new();
+    for wallet in zewif.wallets() {
+        bare.add_wallet(wallet.clone());
+    }
+    for wallet in bare.wallets_mut() {
+        for account in wallet.accounts_mut() {
+            account.set_birthday_height(None);
+        }
+    }
+    birthday::estimate_birthdays(&mut bare, Some(1_672_531_200));
+    for account in bare.wallets().iter().flat_map(|w| w.accounts()) {
+        assert_eq!(account.birthday_height(), None);
+    }
+}
+
+#[test]
+fn test_birthday_override() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let migration = Migrator::new("zcashd", path)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .birthday(Some(150))
+        .run(&Progress::quiet())
+        .unwrap();
+    let zewif = migration.zewif().unwrap();
+    for account in zewif.wallets().iter().flat_map(|w| w.accounts()) {
+        assert_eq!(account.birthday_height(), Some(BlockHeight::from(150)));
+    }
+}
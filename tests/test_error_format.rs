@@ -0,0 +1,162 @@
+use std::{
+    path::Path,
+    process::{Command, Output},
+};
+
+use anyhow::Context;
+use serde_json::Value;
+use zmigrate::error::{self, ZewifInputError};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_file, temp_path};
+
+fn sample_error() -> anyhow::Error {
+    Err::<(), _>(ZewifInputError::WrongPassword)
+        .context("Opening Zewif envelope")
+        .unwrap_err()
+}
+
+#[test]
+fn test_error_json() {
+    let e = sample_error();
+    let json =
+        error::error_json(&e, Some("open"), Some(Path::new("wallet.zewif")));
+    assert_eq!(json["code"], "wrong_password");
+    assert_eq!(json["exit_code"], 12);
+    assert_eq!(json["message"], "Opening Zewif envelope");
+    assert_eq!(
+        json["causes"],
+        serde_json::json!(["Wrong password: unable to decrypt the wallet"])
+    );
+    assert_eq!(json["stage"], "open");
+    assert_eq!(json["input"], "wallet.zewif");
+
+    // The whole error is a single line.
+    assert!(!json.to_string().contains('\n'));
+}
+
+#[test]
+fn test_error_json_untyped() {
+    let e = anyhow::anyhow!("Something went wrong");
+    let json = error::error_json(&e, None, None);
+    assert_eq!(json["code"], "failure");
+    assert_eq!(json["exit_code"], error::EXIT_FAILURE);
+    assert!(json["stage"].is_null());
+    assert!(json["input"].is_null());
+}
+
+#[test]
+fn test_plain_error_format() {
+    let e = sample_error();
+    let plain = error::format_error(&e, false);
+    assert!(plain.is_ascii());
+    assert!(plain.contains("Error: Opening Zewif envelope"));
+    assert!(
+        plain.contains(
+            "Caused by: Wrong password: unable to decrypt the wallet"
+        )
+    );
+
+    let styled = error::format_error(&e, true);
+    assert!(styled.contains("🔴"));
+}
+
+/// Runs zmigrate on a `.zewif` input that is not CBOR, so it fails with
+/// `invalid_cbor`.
+fn run_invalid_input(name: &str, args: &[&str], no_color: bool) -> Output {
+    let input =
+        temp_file(&format!("error-format-{}.zewif", name), b"not a zewif file");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_zmigrate"));
+    command
+        .args(args)
+        .args(["--from", "zewif", "--to", "dump"])
+        .arg(&input)
+        .arg("-")
+        .env_remove("RUST_LOG");
+    if no_color {
+        command.env("NO_COLOR", "1");
+    } else {
+        command.env_remove("NO_COLOR");
+    }
+    let output = command.output().unwrap();
+    let _ = std::fs::remove_file(&input);
+    output
+}
+
+fn json_lines(stderr: &[u8]) -> Vec<Value> {
+    String::from_utf8(stderr.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("{}: {:?}", e, line))
+        })
+        .collect()
+}
+
+#[test]
+fn test_json_error_output() {
+    let result = run_invalid_input("json", &["--error-format", "json"], false);
+    assert_eq!(result.status.code(), Some(10));
+    let lines = json_lines(&result.stderr);
+    assert_eq!(lines.len(), 1, "{:?}", lines);
+    assert_eq!(lines[0]["code"], "invalid_cbor");
+    assert_eq!(lines[0]["exit_code"], 10);
+
+    // Logs are JSON too, and the error is still the only object with a code.
+    let result = run_invalid_input(
+        "json-verbose",
+        &["--error-format", "json", "-vv"],
+        false,
+    );
+    assert_eq!(result.status.code(), Some(10));
+    let lines = json_lines(&result.stderr);
+    assert!(lines.len() > 1);
+    let errors: Vec<_> = lines
+        .iter()
+        .filter(|line| line.get("code").is_some())
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["code"], "invalid_cbor");
+}
+
+#[test]
+fn test_json_error_format_success() {
+    // Human progress and reports are left out; logs are JSON.
+    let input = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let output = temp_path("error-format-success.zewif");
+    let result = Command::new(env!("CARGO_BIN_EXE_zmigrate"))
+        .args(["--error-format", "json", "-v", "--verify"])
+        .args(["--from", "zcashd"])
+        .arg(&input)
+        .arg(&output)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&output);
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    let lines = json_lines(&result.stderr);
+    assert!(!lines.is_empty());
+    assert!(
+        lines.iter().any(|line| line["event"] == "report"
+            && line["report"] == "verification")
+    );
+}
+
+#[test]
+fn test_plain_error_output() {
+    // stderr is a pipe here, so output is plain with or without `NO_COLOR`.
+    for no_color in [false, true] {
+        let result = run_invalid_input("plain", &["-v"], no_color);
+        assert_eq!(result.status.code(), Some(10));
+        let stderr = String::from_utf8(result.stderr).unwrap();
+        assert!(stderr.contains("Error: "), "{}", stderr);
+        assert!(!stderr.contains('\x1b'), "{}", stderr);
+        assert!(!stderr.contains("🔴"), "{}", stderr);
+    }
+}
@@ -0,0 +1,93 @@
+use std::process::Command;
+
+use zewif::{Bip39Mnemonic, SeedMaterial};
+use zmigrate::{keys, keys_cmd, zcashd_cmd};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_export_seed() {
+    // zcashd v5.6.0 wallets are HD wallets with a BIP-39 mnemonic.
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    let output = keys_cmd::export_seed(&zewif).unwrap();
+
+    let mnemonic = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Mnemonic: "))
+        .expect("Missing mnemonic");
+    assert_eq!(mnemonic.split_whitespace().count(), 24);
+    assert!(output.contains("Language: "));
+    assert!(output.contains("ZIP-32 accounts: "));
+    // zcashd records no birthday, so the one shown was estimated.
+    assert!(output.contains("Birthday height: "));
+    assert!(output.contains(" (estimated)"));
+}
+
+/// Runs `zmigrate keys export-seed` on golden node0 with stdout piped.
+fn run_export_seed(force: bool) -> std::process::Output {
+    let input = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut command = Command::new(env!("CARGO_BIN_EXE_zmigrate"));
+    command.args(["keys", "export-seed", "--from", "zcashd"]);
+    if force {
+        command.arg("--force");
+    }
+    command.arg(&input).output().unwrap()
+}
+
+#[test]
+fn test_export_seed_guard() {
+    // A pipe is not a terminal, so the export is refused without --force.
+    let refused = run_export_seed(false);
+    assert!(!refused.status.success());
+    let stdout = String::from_utf8_lossy(&refused.stdout);
+    assert!(!stdout.contains("Mnemonic"), "{}", stdout);
+    assert!(
+        String::from_utf8_lossy(&refused.stderr).contains("--force"),
+        "{}",
+        String::from_utf8_lossy(&refused.stderr)
+    );
+
+    let forced = run_export_seed(true);
+    assert!(
+        forced.status.success(),
+        "{}",
+        String::from_utf8_lossy(&forced.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&forced.stdout);
+    let mnemonic = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Mnemonic: "))
+        .expect("Missing mnemonic");
+    assert_eq!(mnemonic.split_whitespace().count(), 24);
+}
+
+#[test]
+fn test_non_english_mnemonic() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    let Some(SeedMaterial::Bip39Mnemonic(english)) =
+        zewif.wallets()[0].seed_material()
+    else {
+        panic!("golden node0 has a mnemonic");
+    };
+    let entropy = keys::parse_mnemonic(english.mnemonic())
+        .unwrap()
+        .entropy()
+        .to_vec();
+    let french =
+        bip0039::Mnemonic::<bip0039::French>::from_entropy(entropy).unwrap();
+    zewif.wallets_mut()[0].set_seed_material(SeedMaterial::Bip39Mnemonic(
+        Bip39Mnemonic::new(french.phrase().to_string(), None),
+    ));
+
+    let seed_material = zewif.wallets()[0].seed_material().unwrap();
+    assert_eq!(
+        keys::seed_bytes(seed_material).unwrap(),
+        french.to_seed("").to_vec()
+    );
+    let output = keys_cmd::export_seed(&zewif).unwrap();
+    assert!(output.contains("Language: French (from the words)"));
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use bc_envelope::prelude::*;
+use zmigrate::{
+    balance::{self, BalanceReport, Pool, PoolBalance},
+    filter::TransactionFilter,
+    metadata,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, golden_zewif};
+
+#[test]
+fn test_filter_height_range() {
+    let zewif = golden_zewif();
+    let heights: Vec<u32> = zewif
+        .transactions()
+        .values()
+        .filter_map(|tx| tx.mined_height())
+        .map(u32::from)
+        .collect();
+    let middle =
+        (heights.iter().min().unwrap() + heights.iter().max().unwrap()) / 2;
+
+    let since = TransactionFilter::new()
+        .with_since_height(Some(middle))
+        .apply(&zewif)
+        .unwrap();
+    let until = TransactionFilter::new()
+        .with_until_height(Some(middle - 1))
+        .apply(&zewif)
+        .unwrap();
+    for tx in since.transactions().values() {
+        assert!(tx.mined_height().is_none_or(|h| u32::from(h) >= middle));
+    }
+    for tx in until.transactions().values() {
+        assert!(u32::from(tx.mined_height().unwrap()) < middle);
+    }
+    // The two ranges split the history between them.
+    assert_eq!(
+        since.transactions().len() + until.transactions().len(),
+        zewif.transactions().len()
+    );
+    assert_eq!(since.wallets().len(), zewif.wallets().len());
+}
+
+#[test]
+fn test_filter_no_raw_tx() {
+    let zewif = golden_zewif();
+    let filtered = TransactionFilter::new()
+        .with_no_raw_tx(true)
+        .apply(&zewif)
+        .unwrap();
+    assert_eq!(filtered.transactions().len(), zewif.transactions().len());
+    assert!(zewif.transactions().values().any(|tx| tx.raw().is_some()));
+    for (txid, original) in zewif.transactions() {
+        let stripped = &filtered.transactions()[txid];
+        assert!(stripped.raw().is_none());
+        // Everything but the raw bytes is unchanged.
+        let expected = Envelope::from(original.clone());
+        let expected =
+            match expected.optional_assertion_with_predicate("raw").unwrap() {
+                Some(raw) => expected.remove_assertion(raw),
+                None => expected,
+            };
+        assert!(Envelope::from(stripped.clone()).is_equivalent_to(&expected));
+    }
+}
+
+/// Each account's per-pool balances, in order.
+fn account_balances(
+    report: &BalanceReport,
+) -> Vec<BTreeMap<Pool, PoolBalance>> {
+    report
+        .accounts()
+        .iter()
+        .map(|a| a.balances().clone())
+        .collect()
+}
+
+#[test]
+fn test_filter_exclude_spent() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let outcome =
+        zcashd_cmd::migrate_zcashd(&path, false, &Progress::quiet()).unwrap();
+    let (zewif, nullifiers) = (outcome.zewif(), outcome.sapling_nullifiers());
+    let filtered = TransactionFilter::new()
+        .with_exclude_spent(true)
+        .apply_with_nullifiers(zewif, nullifiers)
+        .unwrap();
+    assert!(filtered.transactions().len() < zewif.transactions().len());
+
+    let before =
+        balance::compute_balances_with_nullifiers(zewif, nullifiers, 1)
+            .unwrap();
+    let after =
+        balance::compute_balances_with_nullifiers(&filtered, nullifiers, 1)
+            .unwrap();
+    assert_eq!(account_balances(&after), account_balances(&before));
+
+    // Transactions holding unspent notes, and those spending wallet notes,
+    // are kept.
+    for note in before.accounts().iter().flat_map(|a| a.notes()) {
+        if note.spent() != Some(true) {
+            assert!(filtered.transactions().contains_key(note.txid()));
+        }
+        if let Some(spender) = note.spent_by() {
+            assert!(filtered.transactions().contains_key(spender));
+        }
+    }
+}
+
+#[test]
+fn test_filter_account() {
+    let zewif = golden_zewif();
+    let account = &zewif.wallets()[0].accounts()[0];
+    let filtered = TransactionFilter::new()
+        .with_account(Some(account.name().to_string()))
+        .apply(&zewif)
+        .unwrap();
+    for wallet in filtered.wallets() {
+        assert!(wallet.accounts().iter().all(|a| a.name() == account.name()));
+    }
+    for txid in filtered.transactions().keys() {
+        assert!(
+            filtered
+                .wallets()
+                .iter()
+                .flat_map(|w| w.accounts())
+                .any(|a| a.relevant_transactions().contains(txid))
+        );
+    }
+
+    let error = TransactionFilter::new()
+        .with_account(Some("no such account".to_string()))
+        .apply(&zewif)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "No account matches no such account");
+}
+
+#[test]
+fn test_filter_recorded_in_metadata() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let migration = Migrator::new("zcashd", path)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .filter(TransactionFilter::new().with_exclude_spent(true))
+        .run(&Progress::quiet())
+        .unwrap();
+    let format = migration
+        .zewif()
+        .unwrap()
+        .attachments()
+        .add_to_envelope(Envelope::new("attachments"))
+        .format();
+    assert!(format.contains(metadata::VENDOR));
+    assert!(format.contains("transactionFilter"));
+    assert!(format.contains("excludeSpent"));
+}
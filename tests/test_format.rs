@@ -0,0 +1,165 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use anyhow::Result;
+use zewif::Zewif;
+use zmigrate::{
+    format::{
+        ReadOptions, ReadOutcome, Registry, WalletReader, WalletWriter,
+        WriteOptions,
+    },
+    migrator::{Migrator, OutputSink},
+    progress::{Progress, ReadStage, Stage},
+    source::WalletSource,
+    zcashd_cmd::ZcashdReader,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+/// Writes the number of transactions in the wallet.
+struct CountWriter;
+
+impl WalletWriter for CountWriter {
+    fn name(&self) -> &'static str { "count" }
+
+    fn help(&self) -> &'static str { "Output the number of transactions" }
+
+    fn write(
+        &self,
+        zewif: &Zewif,
+        _options: &mut WriteOptions,
+        _progress: &Progress,
+    ) -> Result<Vec<u8>> {
+        Ok(zewif.transactions().len().to_string().into_bytes())
+    }
+}
+
+/// Reads zcashd wallets under another name, in a stage of its own.
+struct RenamedReader;
+
+impl WalletReader for RenamedReader {
+    fn name(&self) -> &'static str { "renamed" }
+
+    fn help(&self) -> &'static str { "Input from a renamed `zcashd` wallet" }
+
+    fn read(
+        &self,
+        input: &WalletSource,
+        options: &mut ReadOptions,
+        progress: &Progress,
+    ) -> Result<ReadOutcome> {
+        let stage =
+            progress.start(Stage::Read(ReadStage::new("rename", "Renaming")));
+        stage.finish();
+        ZcashdReader.read(input, options, progress)
+    }
+}
+
+/// A writer that keeps everything written to it.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn test_builtin_formats() {
+    let registry = Registry::builtin();
+    let readers: Vec<_> = registry.readers().map(|r| r.name()).collect();
+    assert!(readers.contains(&"zcashd"));
+    assert!(readers.contains(&"zewif"));
+    assert_eq!(readers.contains(&"zingo"), cfg!(feature = "zingo"));
+    let writers: Vec<_> = registry.writers().map(|w| w.name()).collect();
+    let mut expected = vec!["zewif", "ur", "format"];
+    if cfg!(feature = "zingo") {
+        expected.push("zingo");
+    }
+    expected.push("dump");
+    assert_eq!(writers, expected);
+
+    let e = registry.reader("nonesuch").err().unwrap();
+    assert!(e.to_string().starts_with("Unknown input format: nonesuch"));
+}
+
+#[test]
+fn test_custom_reader() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut registry = Registry::builtin();
+    registry.register_reader(RenamedReader);
+    let reader = registry.reader("renamed").unwrap();
+    assert!(reader.migrates());
+
+    let stage = Stage::Read(ReadStage::new("rename", "Renaming"));
+    assert_eq!(stage.id(), "rename");
+    assert_eq!(stage.label(), "Renaming");
+
+    // Inspecting a wallet defaults to inspecting the migrated wallet.
+    let inspection = reader
+        .inspect(&WalletSource::from(&path), &mut ReadOptions::new())
+        .unwrap();
+    assert_eq!(inspection.format(), "renamed");
+    assert!(inspection.network().is_some());
+    let expected = ZcashdReader
+        .read(
+            &WalletSource::from(&path),
+            &mut ReadOptions::new(),
+            &Progress::quiet(),
+        )
+        .unwrap();
+    assert_eq!(
+        inspection.contents().unwrap().transactions(),
+        expected.zewif().transactions().len()
+    );
+}
+
+#[cfg(feature = "zingo")]
+#[test]
+fn test_dump_only_readers_are_not_offered_for_migration() {
+    let registry = Registry::builtin();
+    let zingo = registry.reader("zingo").unwrap();
+    assert!(!zingo.migrates());
+    let parser = registry.migrating_reader_parser();
+    let names: Vec<_> =
+        clap::builder::TypedValueParser::possible_values(&parser)
+            .unwrap()
+            .map(|value| value.get_name().to_string())
+            .collect();
+    assert!(names.contains(&"zcashd".to_string()));
+    assert!(!names.contains(&"zingo".to_string()));
+}
+
+#[test]
+fn test_custom_writer() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut registry = Registry::builtin();
+    registry.register_writer(CountWriter);
+    let buffer = Buffer::default();
+    let migration = Migrator::new("zcashd", path)
+        .registry(registry)
+        .to("count")
+        .output(OutputSink::Writer(Box::new(buffer.clone())))
+        .run(&Progress::quiet())
+        .unwrap();
+    let expected = migration.zewif().unwrap().transactions().len();
+    assert_eq!(*buffer.0.borrow(), expected.to_string().into_bytes());
+}
+
+#[test]
+fn test_drop_sprout_needs_zcashd() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let e = Migrator::new("zewif", path)
+        .drop_sprout(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "--drop-sprout does not apply to zewif wallets"
+    );
+}
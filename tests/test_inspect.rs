@@ -0,0 +1,92 @@
+use bc_envelope::prelude::*;
+use zewif::{Network, ZewifEnvelope};
+use zmigrate::{format::PasswordFn, inspect, zcashd_cmd};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_file};
+
+#[test]
+fn test_inspect_zcashd() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let inspection = inspect::inspect_zcashd(&path).unwrap();
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+
+    assert_eq!(inspection.format(), "zcashd");
+    assert_eq!(inspection.network(), Some(Network::Regtest));
+    assert_eq!(inspection.compressed(), None);
+    let counts = inspection.keyname_counts().unwrap();
+    assert!(counts.contains_key("tx"));
+    assert!(inspection.to_string().contains("- Records:"));
+
+    let contents = inspection.contents().unwrap();
+    assert_eq!(contents.transactions(), zewif.transactions().len());
+    let address_count = zewif
+        .wallets()
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .count();
+    assert_eq!(contents.addresses().values().sum::<usize>(), address_count);
+
+    let json = inspection.to_json();
+    assert_eq!(json["format"], "zcashd");
+    assert_eq!(json["network"], "regtest");
+    assert!(json["keynames"].is_object());
+}
+
+#[test]
+fn test_inspect_zewif() {
+    bc_envelope::register_tags();
+    let source =
+        fixtures_path(&["zcashd", "golden-v5.6.0", "node1_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&source).unwrap();
+    let mut ze = ZewifEnvelope::new(Envelope::from(zewif.clone())).unwrap();
+    ze.compress().unwrap();
+    let path = temp_file("inspect.zewif", &ze.envelope().to_cbor_data());
+
+    let inspection = inspect::inspect_zewif(&path, None);
+    std::fs::remove_file(&path).unwrap();
+    let inspection = inspection.unwrap();
+
+    assert_eq!(inspection.format(), "zewif");
+    assert_eq!(inspection.version(), Some("1"));
+    assert_eq!(inspection.compressed(), Some(true));
+    assert_eq!(inspection.encrypted(), Some(false));
+    assert!(inspection.keyname_counts().is_none());
+    assert_eq!(
+        inspection.contents().unwrap().transactions(),
+        zewif.transactions().len()
+    );
+}
+
+#[test]
+fn test_inspect_encrypted_zewif() {
+    bc_envelope::register_tags();
+    let source =
+        fixtures_path(&["zcashd", "golden-v5.6.0", "node1_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&source).unwrap();
+    let mut ze = ZewifEnvelope::new(Envelope::from(zewif.clone())).unwrap();
+    ze.compress().unwrap();
+    ze.encrypt(&ZewifEnvelope::derive_encryption_key("secret".to_string()))
+        .unwrap();
+    let path =
+        temp_file("inspect-encrypted.zewif", &ze.envelope().to_cbor_data());
+
+    // The flags are read without the password.
+    let flags = inspect::inspect_zewif(&path, None);
+    let password: PasswordFn = Box::new(|| Ok("secret".to_string()));
+    let contents = inspect::inspect_zewif(&path, Some(password));
+    std::fs::remove_file(&path).unwrap();
+    let flags = flags.unwrap();
+    assert_eq!(flags.encrypted(), Some(true));
+    assert_eq!(flags.compressed(), Some(true));
+    assert!(flags.contents().is_none());
+
+    let contents = contents.unwrap();
+    assert_eq!(contents.encrypted(), Some(true));
+    assert_eq!(
+        contents.contents().unwrap().transactions(),
+        zewif.transactions().len()
+    );
+}
@@ -0,0 +1,173 @@
+use serde_json::Value;
+use zcash_keys::address::Address as DecodedAddress;
+use zcash_protocol::consensus::NetworkType;
+use zewif::Zewif;
+use zmigrate::{
+    keys,
+    legacy::{self, KeyOrigin},
+    verify::AddressKind,
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+/// Decodes every key in a sweep manifest and checks that it controls the
+/// address listed with it.
+fn assert_manifest_derives(network: NetworkType, manifest: &Value) {
+    for entry in manifest["keys"].as_array().unwrap() {
+        let address = entry["address"].as_str().unwrap();
+        match entry["type"].as_str().unwrap() {
+            "transparent" => {
+                let wif = entry["wif"].as_str().unwrap();
+                let key =
+                    keys::decode_transparent_secret_key(network, wif).unwrap();
+                let Ok(DecodedAddress::Transparent(decoded)) =
+                    keys::decode_address(network, address)
+                else {
+                    panic!("Not a transparent address: {}", address);
+                };
+                // A compressed key's payload ends with a 1 after the key.
+                let compressed = bs58::decode(wif)
+                    .with_check(None)
+                    .into_vec()
+                    .unwrap()
+                    .len()
+                    == 34;
+                assert_eq!(
+                    keys::transparent_key_compression(&key, &decoded),
+                    Some(compressed),
+                    "{}",
+                    address
+                );
+            }
+            "sapling" => {
+                let encoded = entry["extended_spending_key"].as_str().unwrap();
+                let xsk = keys::decode_sapling_spending_key(network, encoded)
+                    .unwrap();
+                let Ok(DecodedAddress::Sapling(decoded)) =
+                    keys::decode_address(network, address)
+                else {
+                    panic!("Not a Sapling address: {}", address);
+                };
+                assert!(
+                    xsk.to_diversifiable_full_viewing_key()
+                        .decrypt_diversifier(&decoded)
+                        .is_some(),
+                    "{}",
+                    address
+                );
+            }
+            "sprout" => assert!(entry.get("wif").is_none()),
+            other => panic!("Unexpected key type {}", other),
+        }
+    }
+}
+
+fn network(zewif: &Zewif) -> NetworkType {
+    keys::network_type(zewif.wallets()[0].network())
+}
+
+fn test_classify(path_elements: &[&str]) -> usize {
+    let path = fixtures_path(path_elements);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap_or_else(|_| {
+        panic!("Unable to process file: {:?}", path_elements)
+    });
+    let report = legacy::classify_keys(&zewif).unwrap();
+
+    let address_count = zewif
+        .wallets()
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .count();
+    assert_eq!(report.classifications().len(), address_count);
+
+    // Only keys a light wallet can't regenerate are swept.
+    let to_sweep = report
+        .classifications()
+        .iter()
+        .filter(|c| c.origin().needs_sweep())
+        .count();
+    assert!(report.sweep_keys().len() <= to_sweep);
+
+    let manifest = report.to_manifest_json();
+    assert_eq!(
+        manifest["keys"].as_array().unwrap().len(),
+        report.sweep_keys().len()
+    );
+    assert_manifest_derives(network(&zewif), &manifest);
+
+    // The human-readable report must never contain key material.
+    assert!(!format!("{:?}", report.sweep_keys()).contains("secret-extended"));
+    report.sweep_keys().len()
+}
+
+#[test]
+fn test_classify_zcashd() {
+    let paths = vec![
+        vec!["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "sprout", "node0_wallet.dat"],
+        vec!["zcashd", "wallet0.dat"],
+        vec!["zcashd", "wallet1.dat"],
+        vec!["zcashd", "wallet5.dat"],
+    ];
+    let swept: usize = paths.iter().map(|path| test_classify(path)).sum();
+    assert!(swept > 0);
+}
+
+#[test]
+fn test_legacy_account_keys_need_sweep() {
+    // zcashd v5.6.0 derives the keys of its Sapling addresses under the
+    // legacy account, which light wallets don't scan.
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    let report = legacy::classify_keys(&zewif).unwrap();
+    let legacy: Vec<_> = report
+        .classifications()
+        .iter()
+        .filter(|c| c.origin() == KeyOrigin::LegacyAccount)
+        .collect();
+    assert!(!legacy.is_empty());
+    let manifest = report.to_manifest_json();
+    for classification in &legacy {
+        assert!(
+            manifest["keys"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|key| key["address"] == classification.address())
+        );
+    }
+    assert!(report.to_string().contains("(legacy account)"));
+}
+
+#[test]
+fn test_classify_sprout() {
+    let path = fixtures_path(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    let report = legacy::classify_keys(&zewif).unwrap();
+
+    let sprout: Vec<_> = report
+        .classifications()
+        .iter()
+        .filter(|c| c.kind() == AddressKind::Sprout)
+        .collect();
+    assert!(!sprout.is_empty());
+    assert!(sprout.iter().all(|c| c.origin() == KeyOrigin::Imported));
+
+    let manifest = report.to_manifest_json();
+    let entries: Vec<_> = manifest["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|key| key["type"] == "sprout")
+        .collect();
+    assert_eq!(entries.len(), sprout.len());
+    for (entry, classification) in entries.iter().zip(&sprout) {
+        assert_eq!(entry["address"], classification.address());
+        assert_eq!(entry["sweep_with"], "zcashd");
+    }
+    assert!(report.to_string().contains("sprout address"));
+}
@@ -0,0 +1,45 @@
+use std::process::Command;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_path};
+
+#[test]
+fn test_log_file() {
+    let input = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let output = temp_path("logging.zewif");
+    let log_file = temp_path("logging.log");
+    let result = Command::new(env!("CARGO_BIN_EXE_zmigrate"))
+        .arg("--quiet")
+        .arg("--log-file")
+        .arg(&log_file)
+        .args(["--from", "zcashd"])
+        .arg(&input)
+        .arg(&output)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&output);
+    let log = std::fs::read_to_string(&log_file);
+    let _ = std::fs::remove_file(&log_file);
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    let log = log.unwrap();
+
+    // The trace is written whatever the verbosity.
+    assert!(log.contains("Migrating to Zewif finished"));
+    assert!(log.contains("zmigrate::progress"));
+
+    // Other crates only contribute warnings and errors.
+    for line in log.lines() {
+        let detailed = [" TRACE ", " DEBUG ", " INFO "]
+            .iter()
+            .any(|level| line.contains(level));
+        if detailed {
+            assert!(line.contains(" zmigrate"), "{}", line);
+        }
+    }
+}
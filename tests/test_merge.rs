@@ -0,0 +1,307 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use bc_envelope::prelude::*;
+use zewif::{Address, ProtocolAddress, Transaction, TransparentAddress, Zewif};
+use zmigrate::{
+    format::{WalletWriter, WriteOptions},
+    inspect::ContentSummary,
+    merge, metadata,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    sprout, zcashd_cmd,
+    zewif_cmd::EnvelopeWriter,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, golden_zewif};
+
+fn golden_wallet(node: u32) -> PathBuf {
+    fixtures_path(&[
+        "zcashd",
+        "golden-v5.6.0",
+        &format!("node{}_wallet.dat", node),
+    ])
+}
+
+fn addresses(zewif: &Zewif) -> HashSet<String> {
+    zewif
+        .wallets()
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .map(|a| a.as_string())
+        .collect()
+}
+
+/// The number of duplicate transactions recorded in the `merged-sources`
+/// metadata, and the sources of each wallet by index.
+fn merged_sources(zewif: &Zewif) -> (u64, Vec<Vec<String>>) {
+    let payloads = metadata::payloads(zewif, "merged-sources").unwrap();
+    assert_eq!(payloads.len(), 1);
+    let duplicates = payloads[0]
+        .extract_object_for_predicate("duplicateTransactions")
+        .unwrap();
+    let mut wallets: Vec<(u64, Vec<String>)> = payloads[0]
+        .objects_for_predicate("wallet")
+        .iter()
+        .map(|entry| {
+            let mut sources: Vec<String> = entry
+                .objects_for_predicate("source")
+                .iter()
+                .map(|source| source.extract_subject().unwrap())
+                .collect();
+            sources.sort();
+            (entry.extract_subject().unwrap(), sources)
+        })
+        .collect();
+    wallets.sort();
+    let indexes: Vec<u64> = wallets.iter().map(|(index, _)| *index).collect();
+    assert_eq!(indexes, (0..wallets.len() as u64).collect::<Vec<_>>());
+    (
+        duplicates,
+        wallets.into_iter().map(|(_, sources)| sources).collect(),
+    )
+}
+
+fn sprout_addresses(zewif: &Zewif) -> usize {
+    addresses(zewif)
+        .iter()
+        .filter(|address| sprout::is_sprout_address(address))
+        .count()
+}
+
+#[test]
+fn test_merge_deduplicates() {
+    let zewif = zcashd_cmd::zcashd_to_zewif(&golden_wallet(0)).unwrap();
+    let merged = merge::merge(vec![
+        ("a.dat".to_string(), zewif.clone()),
+        ("b.dat".to_string(), zewif.clone()),
+    ])
+    .unwrap();
+
+    // Merging a wallet with itself adds nothing.
+    let single = ContentSummary::new(&zewif);
+    let summary = ContentSummary::new(&merged);
+    assert_eq!(summary.wallets(), single.wallets());
+    assert_eq!(summary.accounts(), single.accounts());
+    assert_eq!(summary.addresses(), single.addresses());
+    assert_eq!(summary.transactions(), single.transactions());
+
+    // Every wallet came from both inputs, and every transaction was a
+    // duplicate.
+    let (duplicates, sources) = merged_sources(&merged);
+    assert_eq!(duplicates, zewif.transactions().len() as u64);
+    assert_eq!(sources.len(), merged.wallets().len());
+    for wallet_sources in sources {
+        assert_eq!(wallet_sources, ["a.dat", "b.dat"]);
+    }
+}
+
+#[test]
+fn test_merge_distinct_wallets() {
+    let node0 = zcashd_cmd::zcashd_to_zewif(&golden_wallet(0)).unwrap();
+    let node1 = zcashd_cmd::zcashd_to_zewif(&golden_wallet(1)).unwrap();
+    let (summary0, summary1) =
+        (ContentSummary::new(&node0), ContentSummary::new(&node1));
+    let shared = node0
+        .transactions()
+        .keys()
+        .filter(|txid| node1.transactions().contains_key(txid))
+        .count();
+    let merged = merge::merge(vec![
+        ("node0_wallet.dat".to_string(), node0),
+        ("node1_wallet.dat".to_string(), node1),
+    ])
+    .unwrap();
+
+    // The nodes have different seeds, so their wallets are kept apart, but
+    // they share transactions.
+    let summary = ContentSummary::new(&merged);
+    assert_eq!(summary.wallets(), summary0.wallets() + summary1.wallets());
+    assert_eq!(
+        summary.accounts(),
+        summary0.accounts() + summary1.accounts()
+    );
+    assert!(
+        summary.transactions()
+            <= summary0.transactions() + summary1.transactions()
+    );
+    assert!(
+        summary.transactions()
+            >= summary0.transactions().max(summary1.transactions())
+    );
+
+    // Each wallet is recorded with the node it came from, node 0's first.
+    let (duplicates, sources) = merged_sources(&merged);
+    assert_eq!(duplicates, shared as u64);
+    let expected: Vec<Vec<String>> = std::iter::repeat_n(
+        vec!["node0_wallet.dat".to_string()],
+        summary0.wallets(),
+    )
+    .chain(std::iter::repeat_n(
+        vec!["node1_wallet.dat".to_string()],
+        summary1.wallets(),
+    ))
+    .collect();
+    assert_eq!(sources, expected);
+}
+
+#[test]
+fn test_merge_shared_seed() {
+    // The tarnished node 0 wallet is the golden one after more activity, so
+    // both hold the same seed.
+    let golden = golden_wallet(0);
+    let tarnished =
+        fixtures_path(&["zcashd", "tarnished-v5.6.0", "node0_wallet.dat"]);
+    let golden_zewif = zcashd_cmd::zcashd_to_zewif(&golden).unwrap();
+    let tarnished_zewif = zcashd_cmd::zcashd_to_zewif(&tarnished).unwrap();
+    assert_eq!(
+        golden_zewif.wallets().len(),
+        tarnished_zewif.wallets().len()
+    );
+
+    let migration = Migrator::new("zcashd", &golden)
+        .input("zcashd", &tarnished)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    let merged = migration.zewif().unwrap();
+
+    // The wallets merge into one per seed, holding every address and
+    // transaction of either input once.
+    assert_eq!(merged.wallets().len(), golden_zewif.wallets().len());
+    let expected: HashSet<String> = addresses(&golden_zewif)
+        .union(&addresses(&tarnished_zewif))
+        .cloned()
+        .collect();
+    assert_eq!(addresses(merged), expected);
+    for txid in golden_zewif
+        .transactions()
+        .keys()
+        .chain(tarnished_zewif.transactions().keys())
+    {
+        assert!(merged.transactions().contains_key(txid));
+    }
+    assert!(
+        merged.transactions().len()
+            < golden_zewif.transactions().len()
+                + tarnished_zewif.transactions().len()
+    );
+}
+
+#[test]
+fn test_migrator_merges_inputs() {
+    let migration = Migrator::new("zcashd", golden_wallet(0))
+        .input("zcashd", golden_wallet(0))
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    let single = zcashd_cmd::zcashd_to_zewif(&golden_wallet(0)).unwrap();
+    assert_eq!(
+        migration.zewif().unwrap().wallets().len(),
+        single.wallets().len()
+    );
+}
+
+#[test]
+fn test_merge_keeps_complete_transaction() {
+    let zewif = golden_zewif();
+    let (txid, tx) = zewif
+        .transactions()
+        .iter()
+        .find(|(_, tx)| tx.raw().is_some())
+        .expect("golden node0 has raw transactions");
+
+    // The first input holds a copy of the transaction without its raw
+    // bytes.
+    let envelope = Envelope::from(tx.clone());
+    let raw = envelope
+        .optional_assertion_with_predicate("raw")
+        .unwrap()
+        .unwrap();
+    let stripped =
+        Transaction::try_from(envelope.remove_assertion(raw)).unwrap();
+    let mut partial = Zewif::new();
+    partial.add_transaction(*txid, stripped);
+
+    let merged = merge::merge(vec![
+        ("partial.zewif".to_string(), partial),
+        ("node0_wallet.dat".to_string(), zewif.clone()),
+    ])
+    .unwrap();
+    assert!(merged.transactions()[txid].raw().is_some());
+    assert_eq!(merged_sources(&merged).0, 1);
+}
+
+#[test]
+fn test_merge_keeps_keys_of_either_copy() {
+    let zewif = golden_zewif();
+    let (index, address) = zewif.wallets()[0].accounts()[0]
+        .addresses()
+        .iter()
+        .enumerate()
+        .find_map(|(index, address)| match address.address() {
+            ProtocolAddress::Transparent(t)
+                if t.spend_authority().is_some() =>
+            {
+                Some((index, t.address().to_string()))
+            }
+            _ => None,
+        })
+        .expect("golden node0 has transparent keys");
+
+    // The first input holds a copy of the address without its key.
+    let mut keyless = zewif.clone();
+    keyless.wallets_mut()[0].accounts_mut()[0].addresses_mut()[index] =
+        Address::new(ProtocolAddress::Transparent(TransparentAddress::new(
+            address.clone(),
+        )));
+
+    let merged = merge::merge(vec![
+        ("keyless.zewif".to_string(), keyless),
+        ("node0_wallet.dat".to_string(), zewif),
+    ])
+    .unwrap();
+    let merged_address = merged.wallets()[0].accounts()[0]
+        .addresses()
+        .iter()
+        .find(|a| a.as_string() == address)
+        .unwrap();
+    let ProtocolAddress::Transparent(transparent) = merged_address.address()
+    else {
+        panic!("Expected a transparent address");
+    };
+    assert!(transparent.spend_authority().is_some());
+}
+
+#[test]
+fn test_merge_drops_sprout_where_supported() {
+    // Sprout addresses are dropped from the zcashd input, while the Zewif
+    // input, which can't drop them, is merged as read.
+    let zewif_bytes = EnvelopeWriter::Binary
+        .write(
+            &golden_zewif(),
+            &mut WriteOptions::new(),
+            &Progress::quiet(),
+        )
+        .unwrap();
+    let sprout_wallet =
+        fixtures_path(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let migration = Migrator::new("zcashd", &sprout_wallet)
+        .input("zewif", zewif_bytes)
+        .drop_sprout(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    let merged = migration.zewif().unwrap();
+
+    let undropped = zcashd_cmd::zcashd_to_zewif(&sprout_wallet).unwrap();
+    assert!(sprout_addresses(&undropped) > 0);
+    assert_eq!(sprout_addresses(merged), 0);
+    assert_eq!(
+        metadata::payloads(merged, "sprout-excluded").unwrap().len(),
+        1
+    );
+    assert_eq!(merged_sources(merged).1.len(), merged.wallets().len());
+}
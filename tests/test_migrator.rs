@@ -0,0 +1,152 @@
+use std::{cell::Cell, path::PathBuf, rc::Rc};
+
+use zmigrate::{
+    migrator::{Migrator, OutputSink, Report},
+    network::NetworkName,
+    progress::Progress,
+    source::{self, WalletSource},
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_path};
+
+fn golden_wallet() -> PathBuf {
+    fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"])
+}
+
+#[test]
+fn test_zcashd_round_trip() {
+    bc_envelope::register_tags();
+    let output = temp_path("migrator-round-trip.zewif");
+    let migration = Migrator::new("zcashd", golden_wallet())
+        .output(OutputSink::File(output.clone()))
+        .compress(true)
+        .encrypt_with(|| Ok("secret".to_string()))
+        .run(&Progress::quiet())
+        .unwrap();
+    let migrated = migration.zewif().unwrap();
+    assert!(migration.sprout_report().is_some());
+    assert!(migration.verification().is_none());
+
+    let reread = Migrator::new("zewif", &output)
+        .to("dump")
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .decrypt_with(|| Ok("secret".to_string()))
+        .run(&Progress::quiet());
+    std::fs::remove_file(&output).unwrap();
+    let reread = reread.unwrap();
+    assert_eq!(
+        reread.zewif().unwrap().transactions().len(),
+        migrated.transactions().len()
+    );
+}
+
+#[test]
+fn test_encrypted_input_needs_password() {
+    bc_envelope::register_tags();
+    let output = temp_path("migrator-encrypted.zewif");
+    Migrator::new("zcashd", golden_wallet())
+        .output(OutputSink::File(output.clone()))
+        .encrypt_with(|| Ok("secret".to_string()))
+        .run(&Progress::quiet())
+        .unwrap();
+    let result = Migrator::new("zewif", &output)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet());
+    std::fs::remove_file(&output).unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_failure_leaves_no_output() {
+    let output = temp_path("migrator-wrong-network.zewif");
+    let result = Migrator::new("zcashd", golden_wallet())
+        .output(OutputSink::File(output.clone()))
+        .expect_network(Some(NetworkName::Mainnet))
+        .run(&Progress::quiet());
+    assert!(result.is_err());
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_reports_verification() {
+    let reported = Rc::new(Cell::new(false));
+    let seen = reported.clone();
+    Migrator::new("zcashd", golden_wallet())
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .verify(true)
+        .on_report(move |report| {
+            if let Report::Verification(report) = report {
+                assert!(!report.results().is_empty());
+                assert!(report.is_clean(), "{}", report);
+                seen.set(true);
+            }
+        })
+        .run(&Progress::quiet())
+        .unwrap();
+    assert!(reported.get());
+}
+
+#[test]
+fn test_in_memory_input_stays_off_disk() {
+    let bytes = std::fs::read(golden_wallet()).unwrap();
+    // Only Linux can hand zcashd's reader an anonymous in-memory file;
+    // elsewhere writing the wallet to disk must be allowed.
+    let result = Migrator::new("zcashd", bytes.clone())
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet());
+    assert_eq!(result.is_ok(), cfg!(target_os = "linux"));
+    Migrator::new("zcashd", bytes)
+        .allow_disk(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_memory_file_not_inherited() {
+    source::with_memory_file(b"wallet", false, |path| {
+        let fd: i32 = path.file_name().unwrap().to_str().unwrap().parse()?;
+        // SAFETY: F_GETFD only reads the descriptor's flags.
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+        assert_eq!(std::fs::read(path)?, b"wallet");
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_in_memory_input() {
+    bc_envelope::register_tags();
+    let file = std::fs::File::open(golden_wallet()).unwrap();
+    let source = WalletSource::from_reader(file).unwrap();
+    let mut output = temp_path("migrator-in-memory.zewif");
+    let migration = Migrator::new("zcashd", source)
+        .allow_disk(true)
+        .output(OutputSink::File(output.clone()))
+        .run(&Progress::quiet())
+        .unwrap();
+    let expected = zcashd_cmd::zcashd_to_zewif(&golden_wallet()).unwrap();
+    assert_eq!(
+        migration.zewif().unwrap().transactions().len(),
+        expected.transactions().len()
+    );
+
+    let bytes = std::fs::read(&output).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    output = temp_path("migrator-in-memory.dump");
+    let migration = Migrator::new("zewif", bytes.as_slice())
+        .to("dump")
+        .output(OutputSink::File(output.clone()))
+        .run(&Progress::quiet())
+        .unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert_eq!(
+        migration.zewif().unwrap().transactions().len(),
+        expected.transactions().len()
+    );
+}
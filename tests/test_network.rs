@@ -0,0 +1,73 @@
+use zewif::Network;
+#[cfg(feature = "zingo")]
+use zmigrate::zingo_cmd;
+use zmigrate::{
+    network::{self, NetworkName},
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn test_zcashd_network(path_elements: &[&str], expected: Network) {
+    let path = fixtures_path(path_elements);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap_or_else(|_| {
+        panic!("Unable to process file: {:?}", path_elements)
+    });
+    assert_eq!(network::detect_network(&zewif).unwrap(), Some(expected));
+
+    let output = zcashd_cmd::dump_wallet(&path).unwrap();
+    assert!(
+        output
+            .contains(&format!("Network: {}", network::network_name(expected)))
+    );
+}
+
+#[test]
+fn test_zcashd_regtest() {
+    let paths = vec![
+        vec!["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "golden-v5.6.0", "node1_wallet.dat"],
+        vec!["zcashd", "tarnished-v5.6.0", "node0_wallet.dat"],
+    ];
+    for path in &paths {
+        test_zcashd_network(path, Network::Regtest);
+    }
+}
+
+#[test]
+fn test_expect_network_mismatch() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+
+    network::ensure_network(&zewif, NetworkName::Regtest).unwrap();
+    let err = network::ensure_network(&zewif, NetworkName::Mainnet)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "Expected a mainnet wallet, but the input is a regtest wallet"
+    );
+}
+
+#[cfg(feature = "zingo")]
+#[test]
+fn test_zingo_network() {
+    let paths = vec![
+        (
+            vec!["zingo", "mainnet", "hhcclaltpcckcsslpcnetblr-latest.dat"],
+            Network::Main,
+        ),
+        (
+            vec!["zingo", "regtest", "hmvasmuvwmssvichcarbpoct-v27.dat"],
+            Network::Regtest,
+        ),
+        (vec!["zingo", "testnet", "v28.dat"], Network::Test),
+    ];
+    for (path, expected) in &paths {
+        let network = zingo_cmd::wallet_network(&fixtures_path(path))
+            .unwrap_or_else(|_| panic!("Unable to process file: {:?}", path));
+        assert_eq!(network, *expected);
+    }
+}
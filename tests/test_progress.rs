@@ -0,0 +1,64 @@
+use std::process::Command;
+
+use serde_json::Value;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_path};
+
+#[test]
+fn test_json_progress() {
+    let input = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let output = temp_path("progress.zewif");
+    let result = Command::new(env!("CARGO_BIN_EXE_zmigrate"))
+        .args(["--progress", "json", "--verify", "--compress"])
+        .args(["--from", "zcashd"])
+        .arg(&input)
+        .arg(&output)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&output);
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+
+    let events: Vec<Value> = String::from_utf8(result.stderr)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    // Every stage starts and then finishes before the next one starts.
+    let mut stages = Vec::new();
+    for pair in events.chunks(2) {
+        let [start, finish] = pair else {
+            panic!("Unpaired progress event: {:?}", pair);
+        };
+        assert_eq!(start["event"], "start");
+        assert_eq!(finish["event"], "finish");
+        assert_eq!(start["stage"], finish["stage"]);
+        assert!(finish["stage_ms"].is_u64());
+        assert!(finish["elapsed_ms"].as_u64() >= start["elapsed_ms"].as_u64());
+        stages.push(start["stage"].as_str().unwrap().to_string());
+    }
+    assert_eq!(
+        stages,
+        [
+            "read_bdb",
+            "parse_dump",
+            "parse_wallet",
+            "migrate",
+            "verify",
+            "compress",
+            "write"
+        ]
+    );
+
+    let migrate = &events[7];
+    assert_eq!(migrate["stage"], "migrate");
+    assert_eq!(migrate["unit"], "transactions");
+    assert!(migrate["count"].as_u64().unwrap() > 0);
+}
@@ -0,0 +1,76 @@
+use zmigrate::records_cmd;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_path};
+
+#[test]
+fn test_filter_by_keyname() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let all = records_cmd::read_records(&path, &[]).unwrap();
+    let names =
+        records_cmd::read_records(&path, &["name".to_string()]).unwrap();
+
+    assert!(!names.is_empty());
+    assert!(names.len() < all.len());
+    assert!(names.iter().all(|r| r.keyname() == "name"));
+
+    let json = records_cmd::records_to_json(&names);
+    let json = json.as_array().unwrap();
+    assert_eq!(json.len(), names.len());
+    // Address book keys are length-prefixed address strings.
+    assert!(json.iter().all(|r| r["key_text"].is_string()));
+}
+
+#[test]
+fn test_export_wallet() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let keynames = vec!["name".to_string(), "purpose".to_string()];
+    let records = records_cmd::read_records(&path, &keynames).unwrap();
+
+    let export = temp_path("records-wallet.dat");
+    records_cmd::export_wallet(&records, &export).unwrap();
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(&export).unwrap().permissions().mode()
+    };
+    let reread = records_cmd::read_records(&export, &[]);
+    std::fs::remove_file(&export).unwrap();
+    #[cfg(unix)]
+    assert_eq!(mode & 0o777, 0o600);
+    let reread = reread.unwrap();
+
+    assert_eq!(reread.len(), records.len());
+    for (a, b) in records.iter().zip(&reread) {
+        assert_eq!(a.keyname(), b.keyname());
+        assert_eq!(a.key_data(), b.key_data());
+        assert_eq!(a.value(), b.value());
+    }
+}
+
+#[test]
+fn test_redact_secrets() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let keynames = vec!["key".to_string(), "name".to_string()];
+    let records = records_cmd::read_records(&path, &keynames).unwrap();
+    assert_eq!(records_cmd::secret_keynames(&records), vec!["key"]);
+
+    let redacted = records_cmd::redact_secrets(&records);
+    assert_eq!(redacted.len(), records.len());
+    for (original, record) in records.iter().zip(&redacted) {
+        assert_eq!(record.is_redacted(), record.keyname() == "key");
+        if record.is_redacted() {
+            assert!(record.value().is_empty());
+            let json = record.to_json();
+            assert!(json.get("value").is_none());
+            assert!(json.get("value_text").is_none());
+        } else {
+            assert_eq!(record.value(), original.value());
+        }
+    }
+
+    let export = temp_path("redacted-wallet.dat");
+    assert!(records_cmd::export_wallet(&redacted, &export).is_err());
+    assert!(!export.exists());
+}
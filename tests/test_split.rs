@@ -0,0 +1,221 @@
+use bc_envelope::prelude::*;
+use zewif::{
+    Account, Bip39Mnemonic, Network, SeedMaterial, Zewif, ZewifWallet,
+};
+use zmigrate::{
+    filter::TransactionFilter,
+    metadata,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    split::{self, SplitOptions},
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, golden_zewif, temp_file};
+
+#[test]
+fn test_split_wallet() {
+    let zewif = golden_zewif();
+    let split = split::split(&zewif, &SplitOptions::new().with_wallet(Some(0)))
+        .unwrap();
+    assert_eq!(split.wallets().len(), 1);
+    let wallet = &split.wallets()[0];
+    assert_eq!(wallet.accounts().len(), zewif.wallets()[0].accounts().len());
+    assert!(wallet.seed_material().is_some());
+    assert!(split.transactions().len() <= zewif.transactions().len());
+}
+
+#[test]
+fn test_split_account() {
+    let zewif = golden_zewif();
+    let account = &zewif.wallets()[0].accounts()[0];
+    let options = SplitOptions::new()
+        .with_wallet(Some(0))
+        .with_account(Some("0".to_string()));
+    let split = split::split(&zewif, &options).unwrap();
+
+    let wallet = &split.wallets()[0];
+    assert_eq!(wallet.accounts().len(), 1);
+    assert_eq!(wallet.accounts()[0].name(), account.name());
+    // The seed would expose the wallet's other accounts.
+    assert!(wallet.seed_material().is_none());
+    // Only the transactions the account references are kept.
+    for txid in split.transactions().keys() {
+        assert!(account.relevant_transactions().contains(txid));
+    }
+
+    let split = split::split(&zewif, &options.with_keep_seed(true)).unwrap();
+    assert!(split.wallets()[0].seed_material().is_some());
+}
+
+#[test]
+fn test_split_errors() {
+    let zewif = golden_zewif();
+    let error = split::split(&zewif, &SplitOptions::new().with_wallet(Some(9)))
+        .unwrap_err();
+    assert!(error.to_string().starts_with("No wallet 9"));
+
+    let options =
+        SplitOptions::new().with_account(Some("no such account".to_string()));
+    let error = split::split(&zewif, &options).unwrap_err();
+    assert_eq!(error.to_string(), "No account matches no such account");
+}
+
+/// The `conformsTo` of every attachment of `zewif`, as formatted text.
+fn attachments(zewif: &Zewif) -> String {
+    zewif
+        .attachments()
+        .add_to_envelope(Envelope::new("attachments"))
+        .format()
+}
+
+#[test]
+fn test_split_keeps_metadata() {
+    let mut zewif = golden_zewif();
+    metadata::record(
+        &mut zewif,
+        "transaction-filter",
+        Envelope::new("transactionFilter"),
+    );
+    metadata::record(&mut zewif, "sqlite-data", Envelope::new("sqliteData"));
+
+    // Extracting the one wallet keeps everything.
+    let split = split::split(&zewif, &SplitOptions::new().with_wallet(Some(0)))
+        .unwrap();
+    let text = attachments(&split);
+    assert!(text.contains("zmigrate/transaction-filter"));
+    assert!(text.contains("zmigrate/sqlite-data"));
+
+    // Extracting an account leaves out the data describing the others.
+    let options = SplitOptions::new()
+        .with_wallet(Some(0))
+        .with_account(Some("0".to_string()));
+    let split = split::split(&zewif, &options).unwrap();
+    let text = attachments(&split);
+    assert!(text.contains("zmigrate/transaction-filter"));
+    assert!(text.contains("metadataLeftOut"));
+    assert!(!text.contains("sqliteData"));
+}
+
+#[test]
+fn test_split_account_without_keys() {
+    // An HD account with no addresses has no keys but the wallet's seed.
+    let mut wallet = ZewifWallet::new(Network::Test);
+    wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+        "abandon abandon abandon abandon abandon abandon abandon abandon \
+         abandon abandon abandon about"
+            .to_string(),
+        None,
+    )));
+    let mut account = Account::new();
+    account.set_zip32_account_id(0);
+    wallet.add_account(account);
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(wallet);
+
+    let options = SplitOptions::new().with_account(Some("0".to_string()));
+    let split = split::split(&zewif, &options).unwrap();
+    assert!(!split::has_keys(&split.wallets()[0]));
+    let split = split::split(&zewif, &options.with_keep_seed(true)).unwrap();
+    assert!(split::has_keys(&split.wallets()[0]));
+}
+
+#[test]
+fn test_split_account_of_merged_wallets() {
+    // Two nodes with different seeds and a Sprout address each, merged with
+    // their Sprout addresses dropped and their raw transactions removed.
+    let nodes: Vec<_> = (0..2)
+        .map(|node| {
+            fixtures_path(&[
+                "zcashd",
+                "sprout",
+                &format!("node{}_wallet.dat", node),
+            ])
+        })
+        .collect();
+    let migration = Migrator::new("zcashd", &nodes[0])
+        .input("zcashd", &nodes[1])
+        .drop_sprout(true)
+        .filter(TransactionFilter::new().with_no_raw_tx(true))
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    let merged = migration.zewif().unwrap();
+    assert!(merged.wallets().len() >= 2);
+    assert!(attachments(merged).contains("zmigrate/sprout-excluded"));
+
+    let options = SplitOptions::new()
+        .with_wallet(Some(0))
+        .with_account(Some("0".to_string()));
+    let split = split::split(merged, &options).unwrap();
+    let text = attachments(&split);
+
+    // None of the other wallets' addresses, Sprout ones included, nor their
+    // sources, are recorded.
+    let mut others: Vec<String> = merged.wallets()[1..]
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .map(|a| a.as_string())
+        .collect();
+    for node in &nodes {
+        let outcome =
+            zcashd_cmd::migrate_zcashd(node, false, &Progress::quiet())
+                .unwrap();
+        others.extend(outcome.sprout_report().unwrap().addresses().to_vec());
+    }
+    for address in &others {
+        assert!(!text.contains(address.as_str()), "{} leaked", address);
+    }
+    assert!(!text.contains("node1_wallet.dat"));
+    assert!(!text.contains("zmigrate/sprout-excluded"));
+    assert!(!text.contains("duplicateTransactions"));
+    assert!(!text.contains("removedTransactions"));
+
+    // What concerns the extracted wallet is kept.
+    let sources = metadata::payloads(&split, "merged-sources").unwrap();
+    assert_eq!(sources.len(), 1);
+    let wallets = sources[0].objects_for_predicate("wallet");
+    assert_eq!(wallets.len(), 1);
+    assert_eq!(wallets[0].extract_subject::<u64>().unwrap(), 0);
+    let source: String =
+        wallets[0].extract_object_for_predicate("source").unwrap();
+    assert!(source.ends_with("node0_wallet.dat"));
+    let filters = metadata::payloads(&split, "transaction-filter").unwrap();
+    assert_eq!(filters.len(), 1);
+    assert!(
+        filters[0]
+            .extract_object_for_predicate::<bool>("rawTransactionsRemoved")
+            .unwrap()
+    );
+    assert!(text.contains("metadataLeftOut"));
+}
+
+#[test]
+fn test_split_refuses_to_overwrite() {
+    let input = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let output = temp_file("split-existing.zewif", b"existing");
+    let split = || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_zmigrate"))
+            .arg("split")
+            .args(["--from", "zcashd", "--wallet", "0"])
+            .arg(&input)
+            .arg(&output)
+            .output()
+            .unwrap()
+    };
+
+    let refused = split();
+    let existing = std::fs::read(&output).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert!(!refused.status.success());
+    assert_eq!(existing, b"existing");
+
+    let written = split();
+    let split_bytes = std::fs::read(&output).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert!(written.status.success());
+    assert_ne!(split_bytes, b"existing");
+}
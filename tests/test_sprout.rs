@@ -0,0 +1,87 @@
+use bc_envelope::prelude::*;
+use zmigrate::{metadata, progress::Progress, sprout, zcashd_cmd};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn sprout_addresses(zewif: &zewif::Zewif) -> usize {
+    zewif
+        .wallets()
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .filter(|a| sprout::is_sprout_address(&a.as_string()))
+        .count()
+}
+
+fn test_sprout(path_elements: &[&str]) {
+    let path = fixtures_path(path_elements);
+
+    // Each node of the Sprout cache holds one Sprout address with a single
+    // unspent note.
+    let (zewif, report) =
+        zcashd_cmd::migrate_zcashd(&path, false, &Progress::quiet())
+            .unwrap_or_else(|_| {
+                panic!("Unable to process file: {:?}", path_elements)
+            })
+            .into_parts();
+    let report = report.unwrap();
+    assert!(report.has_sprout());
+    assert_eq!(report.addresses().len(), 1);
+    assert_eq!(report.unspent_notes().len(), 1);
+    assert!(!report.excluded());
+    assert!(report.to_string().contains("🛑"));
+
+    let (dropped, report) =
+        zcashd_cmd::migrate_zcashd(&path, true, &Progress::quiet())
+            .unwrap()
+            .into_parts();
+    let report = report.unwrap();
+    assert!(report.excluded());
+    assert!(sprout_addresses(&zewif) > 0);
+    assert_eq!(sprout_addresses(&dropped), 0);
+    // Transactions are kept, since they may involve other pools too.
+    assert_eq!(dropped.transactions().len(), zewif.transactions().len());
+
+    // The exclusion is recorded with the addresses dropped.
+    assert!(
+        metadata::payloads(&zewif, "sprout-excluded")
+            .unwrap()
+            .is_empty()
+    );
+    let records = metadata::payloads(&dropped, "sprout-excluded").unwrap();
+    assert_eq!(records.len(), 1);
+    let recorded: Vec<String> = records[0]
+        .objects_for_predicate("address")
+        .iter()
+        .map(|address| address.extract_subject::<String>().unwrap())
+        .collect();
+    assert_eq!(recorded, report.addresses());
+    let unspent_notes: u64 = records[0]
+        .extract_object_for_predicate("unspentNotes")
+        .unwrap();
+    assert_eq!(unspent_notes, 1);
+}
+
+#[test]
+fn test_sprout_zcashd() {
+    let paths = vec![
+        vec!["zcashd", "sprout", "node0_wallet.dat"],
+        vec!["zcashd", "sprout", "node1_wallet.dat"],
+        vec!["zcashd", "sprout", "node2_wallet.dat"],
+        vec!["zcashd", "sprout", "node3_wallet.dat"],
+    ];
+    for path in &paths {
+        test_sprout(path);
+    }
+}
+
+#[test]
+fn test_no_sprout_zcashd() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let outcome =
+        zcashd_cmd::migrate_zcashd(&path, false, &Progress::quiet()).unwrap();
+    let report = outcome.sprout_report().unwrap();
+    assert!(report.to_string().contains("Sprout Report"));
+}
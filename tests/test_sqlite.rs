@@ -0,0 +1,198 @@
+#![cfg(feature = "sqlite")]
+
+use rusqlite::Connection;
+use zcash_protocol::consensus::NetworkType;
+use zewif::Network;
+use zmigrate::{
+    balance::Pool,
+    inspect::ContentSummary,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    sqlite::{self, SqliteWallet},
+    verify::{self, AddressKind, KeySource, VerificationStatus},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_read_sqlite() {
+    let wallet =
+        SqliteWallet::from_file(&fixtures_path(&["sqlite", "data.db"]))
+            .unwrap();
+    assert_eq!(wallet.network().unwrap(), Network::Main);
+    let names: Vec<_> = wallet.accounts().iter().map(|a| a.name()).collect();
+    assert_eq!(names, [Some("Default"), Some("Savings"), None]);
+    assert!(wallet.accounts()[2].seed_fingerprint().is_none());
+    assert_eq!(wallet.accounts()[0].addresses().len(), 2);
+    assert_eq!(wallet.accounts()[2].birthday_height(), 2_100_000);
+    assert_eq!(wallet.transactions().len(), 4);
+    assert_eq!(wallet.received_notes().len(), 4);
+    assert_eq!(
+        wallet
+            .received_notes()
+            .iter()
+            .filter(|n| n.is_spent())
+            .count(),
+        1
+    );
+    let orchard: Vec<_> = wallet
+        .received_notes()
+        .iter()
+        .filter(|n| n.pool() == Pool::Orchard)
+        .collect();
+    assert_eq!(orchard.len(), 1);
+    assert_eq!(orchard[0].memo().as_deref(), Some("Savings"));
+    assert_eq!(wallet.sent_notes().len(), 2);
+}
+
+#[test]
+fn test_migrate_sqlite() {
+    let wallet =
+        SqliteWallet::from_file(&fixtures_path(&["sqlite", "data.db"]))
+            .unwrap();
+    let zewif = sqlite::migrate_to_zewif(&wallet).unwrap();
+
+    // The two accounts derived from one seed form one wallet, and the
+    // imported account another.
+    let summary = ContentSummary::new(&zewif);
+    assert_eq!(summary.wallets(), 2);
+    assert_eq!(summary.accounts(), 3);
+    assert_eq!(summary.addresses().get("sapling"), Some(&3));
+    assert_eq!(summary.addresses().get("transparent"), Some(&2));
+    assert_eq!(summary.transactions(), 4);
+    assert_eq!(
+        summary
+            .height_range()
+            .map(|(low, high)| (u32::from(low), u32::from(high))),
+        Some((2_000_010, 2_100_005))
+    );
+
+    let accounts = zewif.wallets()[0].accounts();
+    assert_eq!(accounts[0].zip32_account_id(), Some(0));
+    assert_eq!(accounts[0].relevant_transactions().len(), 3);
+    assert_eq!(
+        accounts[0].birthday_height().map(u32::from),
+        Some(2_000_000)
+    );
+    let imported = &zewif.wallets()[1].accounts()[0];
+    assert_eq!(imported.zip32_account_id(), None);
+    assert_eq!(imported.birthday_height().map(u32::from), Some(2_100_000));
+}
+
+#[test]
+fn test_sqlite_viewing_keys() {
+    let wallet =
+        SqliteWallet::from_file(&fixtures_path(&["sqlite", "data.db"]))
+            .unwrap();
+    // The imported account has only an incoming viewing key.
+    assert!(wallet.accounts()[0].ufvk().is_some());
+    assert!(wallet.accounts()[2].ufvk().is_none());
+    assert!(
+        wallet
+            .accounts()
+            .iter()
+            .all(|a| a.sapling_ivk(NetworkType::Main).is_some())
+    );
+
+    let zewif = sqlite::migrate_to_zewif(&wallet).unwrap();
+    let report = verify::verify_zewif(&zewif).unwrap();
+    let sapling: Vec<_> = report
+        .results()
+        .iter()
+        .filter(|r| r.kind() == AddressKind::Sapling)
+        .collect();
+    assert_eq!(sapling.len(), 3);
+    for result in sapling {
+        assert_eq!(
+            result.status(),
+            &VerificationStatus::Derived(KeySource::ViewingKey),
+            "{}",
+            result.address()
+        );
+    }
+}
+
+/// Rebuilds the fixture with the schema of `zcash_client_sqlite` releases
+/// that keyed accounts by ZIP-32 index and recorded one spend per note.
+fn old_schema_database() -> Connection {
+    let db = Connection::open_in_memory().unwrap();
+    let path = fixtures_path(&["sqlite", "data.db"]);
+    db.execute("ATTACH DATABASE ?1 AS src", [path.to_str().unwrap()])
+        .unwrap();
+    db.execute_batch(
+        "CREATE TABLE accounts AS
+             SELECT hd_account_index AS account, ufvk, birthday_height
+             FROM src.accounts WHERE hd_account_index IS NOT NULL;
+         CREATE TABLE addresses AS
+             SELECT a.hd_account_index AS account, d.diversifier_index_be,
+                    d.address, d.cached_transparent_receiver_address
+             FROM src.addresses d JOIN src.accounts a ON d.account_id = a.id
+             WHERE a.hd_account_index IS NOT NULL;
+         CREATE TABLE transactions AS SELECT * FROM src.transactions;
+         CREATE TABLE sapling_received_notes AS
+             SELECT n.id AS id_note, n.tx, n.output_index,
+                    a.hd_account_index AS account, n.value, n.is_change,
+                    n.memo, s.transaction_id AS spent
+             FROM src.sapling_received_notes n
+             JOIN src.accounts a ON n.account_id = a.id
+             LEFT JOIN src.sapling_received_note_spends s
+                 ON s.sapling_received_note_id = n.id
+             WHERE a.hd_account_index IS NOT NULL;
+         DETACH DATABASE src;",
+    )
+    .unwrap();
+    db
+}
+
+#[test]
+fn test_read_old_schema() {
+    let wallet = SqliteWallet::from_connection(&old_schema_database()).unwrap();
+    let indices: Vec<_> = wallet
+        .accounts()
+        .iter()
+        .map(|a| a.account_index())
+        .collect();
+    assert_eq!(indices, [Some(0), Some(1)]);
+    assert!(wallet.accounts().iter().all(|a| a.name().is_none()));
+    assert!(wallet.accounts()[0].seed_fingerprint().is_none());
+    assert_eq!(wallet.accounts()[0].addresses().len(), 2);
+    assert_eq!(wallet.received_notes().len(), 2);
+    assert_eq!(
+        wallet
+            .received_notes()
+            .iter()
+            .filter(|n| n.is_spent())
+            .count(),
+        1
+    );
+    assert!(wallet.sent_notes().is_empty());
+
+    let zewif = sqlite::migrate_to_zewif(&wallet).unwrap();
+    let summary = ContentSummary::new(&zewif);
+    assert_eq!(summary.accounts(), 2);
+    assert_eq!(summary.addresses().get("sapling"), Some(&2));
+}
+
+#[test]
+fn test_sqlite_from_memory() {
+    bc_envelope::register_tags();
+    let bytes = std::fs::read(fixtures_path(&["sqlite", "data.db"])).unwrap();
+    let migration = Migrator::new("sqlite", bytes)
+        .allow_disk(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    assert_eq!(migration.zewif().unwrap().transactions().len(), 4);
+}
+
+#[test]
+fn test_not_a_sqlite_wallet() {
+    let path = fixtures_path(&["ywallet", "zec.db"]);
+    let e = SqliteWallet::from_file(&path).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Not a zcash_client_sqlite database: no addresses table"
+    );
+}
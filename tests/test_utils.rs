@@ -1,5 +1,10 @@
+#![allow(dead_code)]
+
 use std::path::PathBuf;
 
+use zewif::Zewif;
+use zmigrate::zcashd_cmd;
+
 /// Returns the path to the test fixtures directory.
 /// This is a common utility used across all tests.
 pub fn fixtures_dir() -> PathBuf {
@@ -17,3 +22,27 @@ pub fn fixtures_dir() -> PathBuf {
 pub fn fixtures_path(path_elements: &[&str]) -> PathBuf {
     fixtures_dir().join(path_elements.iter().collect::<PathBuf>())
 }
+
+/// Returns a path in the system temporary directory that is unique to this
+/// test process. Nothing is created; the caller removes whatever it writes.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "zmigrate-{}-{}",
+        std::process::id(),
+        name
+    ))
+}
+
+/// Writes `data` to a file at [`temp_path`]`(name)` and returns its path.
+pub fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = temp_path(name);
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+/// Migrates the golden zcashd v5.6.0 `node0` wallet.
+pub fn golden_zewif() -> Zewif {
+    bc_envelope::register_tags();
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    zcashd_cmd::zcashd_to_zewif(&path).unwrap()
+}
@@ -0,0 +1,122 @@
+use zewif::{
+    Address, ProtocolAddress, TransparentAddress, TransparentSpendAuthority,
+};
+use zmigrate::{
+    sprout,
+    verify::{self, AddressKind, VerificationReport, VerificationStatus},
+    zcashd_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn test_verify(path_elements: &[&str]) -> VerificationReport {
+    let path = fixtures_path(path_elements);
+    let zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap_or_else(|_| {
+        panic!("Unable to process file: {:?}", path_elements)
+    });
+    let report = verify::verify_zewif(&zewif).unwrap();
+
+    let address_count = zewif
+        .wallets()
+        .iter()
+        .flat_map(|w| w.accounts())
+        .flat_map(|a| a.addresses())
+        .count();
+    assert_eq!(report.results().len(), address_count);
+    assert!(report.to_string().starts_with("Key Derivation Report"));
+    report
+}
+
+#[test]
+fn test_verify_zcashd() {
+    let paths = vec![
+        vec!["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "tarnished-v5.6.0", "node0_wallet.dat"],
+        vec!["zcashd", "sprout", "node0_wallet.dat"],
+        vec!["zcashd", "wallet0.dat"],
+        vec!["zcashd", "wallet5.dat"],
+    ];
+    for path in &paths {
+        test_verify(path);
+    }
+}
+
+#[test]
+fn test_golden_wallets_verify_clean() {
+    for node in ["node0", "node1", "node2", "node3"] {
+        let file = format!("{}_wallet.dat", node);
+        let report = test_verify(&["zcashd", "golden-v5.6.0", &file]);
+        assert!(report.is_clean(), "{}:\n{}", node, report);
+        assert_eq!(report.skipped().count(), 0);
+    }
+}
+
+#[test]
+fn test_sprout_addresses_are_skipped() {
+    let report = test_verify(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let sprout: Vec<_> = report
+        .results()
+        .iter()
+        .filter(|r| sprout::is_sprout_address(r.address()))
+        .collect();
+    assert!(!sprout.is_empty());
+    for result in &sprout {
+        assert_eq!(result.kind(), AddressKind::Sprout);
+        assert!(!result.status().is_failure());
+    }
+    assert_eq!(report.skipped().count(), sprout.len());
+    assert!(report.to_string().contains("Sprout addresses"));
+}
+
+#[test]
+fn test_corrupted_keys_fail() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut zewif = zcashd_cmd::zcashd_to_zewif(&path).unwrap();
+    let addresses = zewif.wallets_mut()[0].accounts_mut()[0].addresses_mut();
+    let keyed: Vec<(usize, String, TransparentSpendAuthority)> = addresses
+        .iter()
+        .enumerate()
+        .filter_map(|(index, address)| match address.address() {
+            ProtocolAddress::Transparent(transparent) => {
+                transparent.spend_authority().map(|key| {
+                    (index, transparent.address().to_string(), key.clone())
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    assert!(keyed.len() >= 2, "golden node0 has transparent keys");
+    let (first, first_address, _) = &keyed[0];
+    let (second, _, second_key) = &keyed[1];
+
+    // The first address with the second's key, and the second address with
+    // no key at all.
+    let mut mismatched = TransparentAddress::new(first_address.clone());
+    mismatched.set_spend_authority(second_key.clone());
+    addresses[*first] = Address::new(ProtocolAddress::Transparent(mismatched));
+    let second_address = addresses[*second].as_string();
+    addresses[*second] = Address::new(ProtocolAddress::Transparent(
+        TransparentAddress::new(second_address.clone()),
+    ));
+
+    let report = verify::verify_zewif(&zewif).unwrap();
+    let status = |address: &str| {
+        report
+            .results()
+            .iter()
+            .find(|r| r.address() == address)
+            .unwrap()
+            .status()
+            .clone()
+    };
+    assert!(matches!(
+        status(first_address),
+        VerificationStatus::Mismatch(_)
+    ));
+    assert_eq!(status(&second_address), VerificationStatus::NoKeyMaterial);
+    assert!(!report.is_clean());
+    assert_eq!(report.failures().count(), 2);
+    assert!(verify::ensure_clean(&report).is_err());
+}
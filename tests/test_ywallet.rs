@@ -0,0 +1,151 @@
+#![cfg(feature = "ywallet")]
+
+use zewif::{Network, ProtocolAddress, TransparentSpendAuthority};
+use zmigrate::{
+    inspect::ContentSummary,
+    metadata,
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    verify::{self, VerificationStatus},
+    ywallet::{self, YWallet},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_read_ywallet() {
+    let ywallet =
+        YWallet::from_file(&fixtures_path(&["ywallet", "zec.db"])).unwrap();
+    assert_eq!(ywallet.network().unwrap(), Network::Main);
+    let names: Vec<_> = ywallet.accounts().iter().map(|a| a.name()).collect();
+    assert_eq!(names, ["Main", "Savings", "Imported"]);
+    assert!(ywallet.accounts()[2].seed().is_none());
+    assert!(ywallet.accounts()[2].transparent_address().is_none());
+    assert_eq!(ywallet.transactions().len(), 4);
+    assert_eq!(ywallet.notes().iter().filter(|n| n.is_spent()).count(), 1);
+    assert_eq!(ywallet.contacts()[0].name(), "Alice");
+}
+
+#[test]
+fn test_migrate_ywallet() {
+    let ywallet =
+        YWallet::from_file(&fixtures_path(&["ywallet", "zec.db"])).unwrap();
+    let zewif = ywallet::migrate_to_zewif(&ywallet).unwrap();
+
+    // The two accounts sharing a seed form one wallet, and the imported
+    // account another.
+    let summary = ContentSummary::new(&zewif);
+    assert_eq!(summary.wallets(), 2);
+    assert_eq!(summary.accounts(), 3);
+    assert_eq!(summary.addresses().get("sapling"), Some(&3));
+    assert_eq!(summary.addresses().get("transparent"), Some(&2));
+    assert_eq!(summary.transactions(), 4);
+    assert_eq!(
+        summary
+            .height_range()
+            .map(|(low, high)| (u32::from(low), u32::from(high))),
+        Some((2_000_000, 2_000_200))
+    );
+}
+
+#[test]
+fn test_ywallet_keys() {
+    let ywallet =
+        YWallet::from_file(&fixtures_path(&["ywallet", "zec.db"])).unwrap();
+    let zewif = ywallet::migrate_to_zewif(&ywallet).unwrap();
+
+    let addresses = |wallet: usize, account: usize| {
+        zewif.wallets()[wallet].accounts()[account]
+            .addresses()
+            .iter()
+            .map(|a| a.address().clone())
+            .collect::<Vec<_>>()
+    };
+
+    // The seed accounts' Sapling keys derive their addresses, so they are
+    // stored on them.
+    for account in 0..2 {
+        let sapling: Vec<_> = addresses(0, account)
+            .into_iter()
+            .filter_map(|address| match address {
+                ProtocolAddress::Shielded(s) => Some(s),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sapling.len(), 1);
+        assert!(sapling[0].spending_key().is_some());
+        assert!(sapling[0].incoming_viewing_key().is_some());
+    }
+
+    // The imported account's spending key belongs to another address, so
+    // neither it nor the viewing key it implies is stored.
+    let [ProtocolAddress::Shielded(imported)] = &addresses(1, 0)[..] else {
+        panic!("Expected a single Sapling address");
+    };
+    assert!(imported.spending_key().is_none());
+    assert!(imported.incoming_viewing_key().is_none());
+
+    // The second account's transparent secret key belongs to another
+    // address, so only the first account's is stored.
+    let spend_authorities: Vec<bool> = (0..2)
+        .flat_map(|account| addresses(0, account))
+        .filter_map(|address| match address {
+            ProtocolAddress::Transparent(t) => Some(matches!(
+                t.spend_authority(),
+                Some(TransparentSpendAuthority::SpendingKey(_))
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(spend_authorities, [true, false]);
+
+    // No key stored on an address derives a different one.
+    let report = verify::verify_zewif(&zewif).unwrap();
+    assert!(
+        !report.failures().any(|failure| matches!(
+            failure.status(),
+            VerificationStatus::Mismatch(_)
+        )),
+        "{:?}",
+        report.failures().collect::<Vec<_>>()
+    );
+
+    // The keys that don't derive their addresses are kept in the metadata,
+    // and the ones that do aren't repeated there.
+    let payloads = metadata::payloads(&zewif, "ywallet-data").unwrap();
+    assert_eq!(payloads.len(), 1);
+    let account = |id: u64| {
+        payloads[0]
+            .objects_for_predicate("account")
+            .into_iter()
+            .find(|entry| entry.extract_subject::<u64>().unwrap() == id)
+            .map(|entry| entry.format())
+            .unwrap_or_default()
+    };
+    assert!(!account(1).contains("saplingSpendingKey"));
+    assert!(!account(1).contains("transparentSpendingKey"));
+    assert!(!account(2).contains("saplingSpendingKey"));
+    assert!(account(2).contains("transparentSpendingKey"));
+    assert!(account(3).contains("saplingSpendingKey"));
+    assert!(account(3).contains("zxviews1placeholder3"));
+}
+
+#[test]
+fn test_ywallet_from_memory() {
+    bc_envelope::register_tags();
+    let bytes = std::fs::read(fixtures_path(&["ywallet", "zec.db"])).unwrap();
+    let migration = Migrator::new("ywallet", bytes)
+        .allow_disk(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap();
+    assert_eq!(migration.zewif().unwrap().transactions().len(), 4);
+}
+
+#[test]
+fn test_not_a_ywallet() {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    assert!(YWallet::from_file(&path).is_err());
+}
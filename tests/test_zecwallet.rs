@@ -0,0 +1,93 @@
+#![cfg(feature = "zecwallet")]
+
+use zmigrate::{
+    error,
+    format::{PasswordFn, ReadOptions, Registry},
+    progress::Progress,
+    zecwallet_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+// No real ZecWallet Lite wallets are among the fixtures yet. The tests that
+// need them are ignored until wallets written by ZecWallet Lite itself are
+// added under `fixtures/zecwallet`.
+
+fn password(password: &'static str) -> ReadOptions {
+    ReadOptions::new().with_password(Some(Box::new(move || {
+        Ok(password.to_string())
+    }) as PasswordFn))
+}
+
+#[test]
+fn test_zecwallet_is_registered() {
+    let registry = Registry::builtin();
+    let reader = registry.reader("zecwallet").unwrap();
+    assert!(!reader.drops_sprout());
+}
+
+#[test]
+fn test_wrong_password_classification() {
+    // ZecWallet Lite's message when the seed doesn't decrypt.
+    let wrong = anyhow::anyhow!("Decryption Error. Is your password correct?")
+        .context("Unlocking wallet");
+    assert!(zecwallet_cmd::is_wrong_password(&wrong));
+
+    // Keys that decrypt but don't match are damaged, not a wrong password.
+    let damaged = anyhow::anyhow!("Keys mismatched: address 0 doesn't match");
+    assert!(!zecwallet_cmd::is_wrong_password(&damaged));
+}
+
+#[test]
+fn test_truncated_wallet() {
+    let result = zecwallet_cmd::migrate_zecwallet_bytes(
+        &[0x07, 0x00],
+        &mut ReadOptions::new(),
+        &Progress::quiet(),
+    );
+    let e = result.unwrap_err();
+    assert!(e.to_string().contains("Parsing ZecWallet Lite wallet"));
+}
+
+#[test]
+#[ignore = "needs a real ZecWallet Lite wallet in fixtures/zecwallet"]
+fn test_unencrypted_wallet() {
+    let path = fixtures_path(&["zecwallet", "zecwallet-light-wallet.dat"]);
+    let zewif = zecwallet_cmd::migrate_zecwallet(
+        &path,
+        &mut ReadOptions::new(),
+        &Progress::quiet(),
+    )
+    .unwrap();
+    assert_eq!(zewif.wallets().len(), 1);
+    let account = &zewif.wallets()[0].accounts()[0];
+    assert!(!account.addresses().is_empty());
+    assert!(!zewif.transactions().is_empty());
+}
+
+#[test]
+#[ignore = "needs a real ZecWallet Lite wallet in fixtures/zecwallet"]
+fn test_encrypted_wallet() {
+    let path =
+        fixtures_path(&["zecwallet", "zecwallet-light-wallet-encrypted.dat"]);
+    let (_, encrypted) = zecwallet_cmd::wallet_version(&path).unwrap();
+    assert!(encrypted);
+
+    let zewif = zecwallet_cmd::migrate_zecwallet(
+        &path,
+        &mut password("password"),
+        &Progress::quiet(),
+    )
+    .unwrap();
+    assert!(!zewif.wallets()[0].accounts()[0].addresses().is_empty());
+
+    let e = zecwallet_cmd::migrate_zecwallet(
+        &path,
+        &mut password("wrong"),
+        &Progress::quiet(),
+    )
+    .unwrap_err();
+    assert_eq!(error::exit_code(&e), 12);
+}
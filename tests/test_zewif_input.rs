@@ -0,0 +1,204 @@
+use bc_envelope::prelude::*;
+use zewif::Zewif;
+use zmigrate::{
+    error::{self, ZewifInputError},
+    format::{PasswordFn, WalletWriter, WriteOptions},
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    zewif_cmd::{self, EnvelopeWriter},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{golden_zewif, temp_file};
+
+/// Writes `zewif` as Zewif binary, encrypted with `password` if given.
+fn write_zewif(zewif: &Zewif, password: Option<&'static str>) -> Vec<u8> {
+    let password: Option<PasswordFn> =
+        password.map(|p| Box::new(move || Ok(p.to_string())) as PasswordFn);
+    let mut options = WriteOptions::new()
+        .with_compress(true)
+        .with_password(password);
+    EnvelopeWriter::Binary
+        .write(zewif, &mut options, &Progress::quiet())
+        .unwrap()
+}
+
+/// Opens and decodes a Zewif file held in memory.
+fn read_zewif(bytes: &[u8], password: &'static str) -> anyhow::Result<Zewif> {
+    let ze = zewif_cmd::zewif_file_from_bytes(bytes)?
+        .open(|| Ok(password.to_string()))?;
+    zewif_cmd::decode_zewif(ze.envelope())
+}
+
+fn input_error(e: &anyhow::Error) -> &ZewifInputError {
+    e.chain()
+        .find_map(|cause| cause.downcast_ref::<ZewifInputError>())
+        .unwrap_or_else(|| panic!("Expected a ZewifInputError, got: {}", e))
+}
+
+#[test]
+fn test_invalid_cbor() {
+    let path = temp_file("invalid.zewif", b"this is not cbor");
+    let e = zewif_cmd::read_zewif_file(&path).err().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(input_error(&e), ZewifInputError::InvalidCbor(_)));
+    assert_eq!(error::exit_code(&e), 10);
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let errors = [
+        ZewifInputError::InvalidCbor(String::new()),
+        ZewifInputError::NotZewif(String::new()),
+        ZewifInputError::WrongPassword,
+        ZewifInputError::UnsupportedVersion {
+            found: 2,
+            supported: 1,
+        },
+        ZewifInputError::Decode {
+            path: vec![],
+            message: String::new(),
+        },
+        ZewifInputError::Corrupt(String::new()),
+    ];
+    let mut codes: Vec<i32> = errors.iter().map(|e| e.exit_code()).collect();
+    codes.push(error::EXIT_FAILURE);
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), errors.len() + 1);
+}
+
+#[test]
+fn test_decode_round_trip() {
+    let zewif = golden_zewif();
+    let envelope = Envelope::from(zewif.clone());
+    let decoded = zewif_cmd::decode_zewif(&envelope).unwrap();
+    assert_eq!(decoded.transactions().len(), zewif.transactions().len());
+}
+
+#[test]
+fn test_not_zewif() {
+    bc_envelope::register_tags();
+    let data = Envelope::new("not a wallet").to_cbor_data();
+    let e = read_zewif(&data, "unused").unwrap_err();
+    assert!(matches!(input_error(&e), ZewifInputError::NotZewif(_)));
+    assert_eq!(error::exit_code(&e), 11);
+}
+
+#[test]
+fn test_wrong_password() {
+    let zewif = golden_zewif();
+    let data = write_zewif(&zewif, Some("right"));
+    let e = read_zewif(&data, "wrong").unwrap_err();
+    assert_eq!(input_error(&e), &ZewifInputError::WrongPassword);
+    assert_eq!(error::exit_code(&e), 12);
+
+    let decoded = read_zewif(&data, "right").unwrap();
+    assert_eq!(decoded.transactions().len(), zewif.transactions().len());
+}
+
+#[test]
+fn test_corrupt_ciphertext() {
+    let zewif = golden_zewif();
+    let mut data = write_zewif(&zewif, Some("right"));
+    // The ciphertext makes up nearly all of the file. Decryption is
+    // authenticated, so damage fails it as a wrong password does.
+    let middle = data.len() / 2;
+    data[middle] ^= 0xff;
+    let e = read_zewif(&data, "right").unwrap_err();
+    assert_eq!(input_error(&e), &ZewifInputError::WrongPassword);
+}
+
+#[test]
+fn test_corrupt_compressed() {
+    let zewif = golden_zewif();
+    let mut data = write_zewif(&zewif, None);
+    // The compressed contents make up nearly all of the file.
+    let middle = data.len() / 2;
+    data[middle] ^= 0xff;
+    let e = read_zewif(&data, "unused").unwrap_err();
+    assert!(matches!(input_error(&e), ZewifInputError::Corrupt(_)));
+    assert_eq!(error::exit_code(&e), 15);
+}
+
+#[test]
+fn test_unsupported_version() {
+    let zewif = golden_zewif();
+    let envelope = Envelope::from(zewif).add_assertion("version", 2u32);
+    let e = zewif_cmd::decode_zewif(&envelope).unwrap_err();
+    assert_eq!(
+        input_error(&e),
+        &ZewifInputError::UnsupportedVersion {
+            found: 2,
+            supported: zewif_cmd::ZEWIF_VERSION,
+        }
+    );
+    assert_eq!(error::exit_code(&e), 13);
+}
+
+#[test]
+fn test_decode_error() {
+    let zewif = golden_zewif();
+    let envelope =
+        Envelope::from(zewif).add_assertion("wallet", "not a wallet");
+    let e = zewif_cmd::decode_zewif(&envelope).unwrap_err();
+    let ZewifInputError::Decode { path, .. } = input_error(&e) else {
+        panic!("Expected a decode error, got: {}", e);
+    };
+    assert_eq!(path[0], "zewif");
+    assert!(path[1].starts_with("wallet["));
+    assert_eq!(error::exit_code(&e), 14);
+}
+
+#[test]
+fn test_output_is_plain_zewif() {
+    // What zmigrate writes is exactly what the zewif crate encodes, so any
+    // Zewif reader accepts it.
+    let zewif = golden_zewif();
+    for password in [None, Some("right")] {
+        let data = write_zewif(&zewif, password);
+        let ze = zewif_cmd::zewif_file_from_bytes(&data)
+            .unwrap()
+            .open(|| Ok("right".to_string()))
+            .unwrap();
+        assert!(
+            ze.envelope()
+                .assertions_with_predicate("version")
+                .is_empty()
+        );
+        assert!(
+            ze.envelope()
+                .is_equivalent_to(&Envelope::from(zewif.clone()))
+        );
+        let decoded = Zewif::try_from(ze.envelope().clone()).unwrap();
+        assert_eq!(decoded.transactions().len(), zewif.transactions().len());
+    }
+}
+
+#[test]
+fn test_undecodable_file_converts_to_format() {
+    let zewif = golden_zewif();
+    let envelope = Envelope::from(zewif).add_assertion("version", 2u32);
+    let input = temp_file("undecodable.zewif", &envelope.to_cbor_data());
+    let output = temp_file("undecodable.envelope", &[]);
+    let migration = Migrator::new("zewif", &input)
+        .to("format")
+        .output(OutputSink::File(output.clone()))
+        .run(&Progress::quiet());
+    let notation = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert!(migration.unwrap().zewif().is_none());
+    assert_eq!(notation, format!("{}\n", envelope.format()));
+
+    // Decoding it is still refused.
+    let input = temp_file("undecodable.zewif", &envelope.to_cbor_data());
+    let e = Migrator::new("zewif", &input)
+        .to("dump")
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet())
+        .unwrap_err();
+    std::fs::remove_file(&input).unwrap();
+    assert_eq!(error::exit_code(&e), 13);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "zingo")]
+
+use zewif::{
+    Account, Bip39Mnemonic, BlockHeight, Network, SeedMaterial, Transaction,
+    TxId, Zewif, ZewifWallet,
+};
+use zmigrate::{zingo_cmd, zingo_export::export_zingo};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+/// The BIP-39 mnemonic with all-zero entropy.
+const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon \
+    abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+    abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+fn seed_wallet(network: Network, account_ids: &[u32]) -> ZewifWallet {
+    let mut wallet = ZewifWallet::new(network);
+    wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+        MNEMONIC.to_string(),
+        None,
+    )));
+    for &account_id in account_ids {
+        let mut account = Account::new();
+        account.set_zip32_account_id(account_id);
+        account.set_birthday_height(Some(BlockHeight::from(2_500_000)));
+        wallet.add_account(account);
+    }
+    wallet
+}
+
+#[test]
+fn test_export_zingo_round_trip() {
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(seed_wallet(Network::Test, &[1]));
+    let bytes = export_zingo(&zewif, false).unwrap();
+
+    zingo_cmd::check_wallet_parses(&bytes).unwrap();
+    assert_eq!(zingo_cmd::wallet_version_bytes(&bytes).unwrap(), 30);
+    assert_eq!(
+        zingo_cmd::wallet_network_bytes(&bytes).unwrap(),
+        Network::Test
+    );
+    // The file ends with the seed's entropy and the account index.
+    let (rest, account_id) = bytes.split_at(bytes.len() - 4);
+    assert_eq!(account_id, 1u32.to_le_bytes());
+    assert_eq!(
+        &rest[rest.len() - 33..],
+        [[32].as_slice(), &[0; 32]].concat()
+    );
+}
+
+#[test]
+fn test_export_zingo_needs_one_seed_account() {
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(ZewifWallet::new(Network::Main));
+    let e = export_zingo(&zewif, false).unwrap_err();
+    assert_eq!(e.to_string(), "Zingo wallets need a BIP-39 mnemonic seed");
+
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(seed_wallet(Network::Main, &[0, 1]));
+    let e = export_zingo(&zewif, false).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Zingo wallets hold one account, but the wallet has 2 ZIP-32 accounts"
+    );
+}
+
+#[test]
+fn test_export_zingo_matches_zingolib() {
+    // A version 30 testnet wallet written by zingolib, for account 0 of a
+    // 24-word seed.
+    let fixture = std::fs::read(fixtures_path(&[
+        "zingo",
+        "testnet",
+        "glory_goddess.dat",
+    ]))
+    .unwrap();
+    // It ends with the chain name, options, birthday, an empty verified
+    // tree, price information with no prices, the seed's entropy and the
+    // account index.
+    let tail = &fixture[fixture.len() - 89..];
+    assert_eq!(&tail[..12], b"\x04\0\0\0\0\0\0\0test");
+    let birthday = u64::from_le_bytes(tail[26..34].try_into().unwrap()) as u32;
+    let entropy = &tail[53..85];
+    let mnemonic =
+        bip0039::Mnemonic::<bip0039::English>::from_entropy(entropy.to_vec())
+            .unwrap();
+
+    let mut wallet = ZewifWallet::new(Network::Test);
+    wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+        mnemonic.phrase().to_string(),
+        None,
+    )));
+    let mut account = Account::new();
+    account.set_zip32_account_id(0);
+    account.set_birthday_height(Some(BlockHeight::from(birthday)));
+    wallet.add_account(account);
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(wallet);
+    let bytes = export_zingo(&zewif, false).unwrap();
+
+    // The version, the unified spending key and the receiver selection
+    // match, as does everything after the transaction records, which
+    // zingolib wrote and the export leaves out.
+    let capability = 8 + 7 + 3 + 285 + 3;
+    assert_eq!(bytes[..capability], fixture[..capability]);
+    assert_eq!(bytes[bytes.len() - 89..], *tail);
+}
+
+#[test]
+fn test_export_zingo_needs_rescan_for_transactions() {
+    let mut zewif = Zewif::new();
+    zewif.add_wallet(seed_wallet(Network::Test, &[0]));
+    let empty = export_zingo(&zewif, false).unwrap();
+    let txid = TxId::from_bytes([1; 32]);
+    zewif.add_transaction(txid, Transaction::new(txid));
+
+    let e = export_zingo(&zewif, false).unwrap_err();
+    assert!(e.to_string().contains("--rescan"), "{}", e);
+    // With --rescan the wallet is written as if it had no transactions.
+    assert_eq!(export_zingo(&zewif, true).unwrap(), empty);
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "zingo")]
+
+use zmigrate::zingo_cmd;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn test_strict(path_elements: &[&str]) {
+    let path = fixtures_path(path_elements);
+    let dump = zingo_cmd::dump_wallet(&path).unwrap_or_else(|_| {
+        panic!("Unable to process file: {:?}", path_elements)
+    });
+    let strict = zingo_cmd::dump_wallet_checked(&path, true);
+    if dump.ends_with("Success (partial)") {
+        assert!(dump.contains("Parsing stopped at offset"));
+        assert!(dump.contains("Last parsed: "));
+        let err = strict.unwrap_err().to_string();
+        assert!(err.contains("unparsed bytes at offset"));
+    } else {
+        assert!(!dump.contains("Unparsed bytes"));
+        assert_eq!(strict.unwrap(), dump);
+    }
+}
+
+#[test]
+fn test_zingo_strict() {
+    let paths = vec![
+        vec!["zingo", "mainnet", "hhcclaltpcckcsslpcnetblr-latest.dat"],
+        vec!["zingo", "regtest", "hmvasmuvwmssvichcarbpoct-v27.dat"],
+        vec!["zingo", "regtest", "aaaaaaaaaaaaaaaaaaaaaaaa-v26.dat"],
+        vec!["zingo", "testnet", "G93738061a.dat"],
+        vec!["zingo", "testnet", "Gab72a38b.dat"],
+        vec!["zingo", "testnet", "glory_goddess.dat"],
+        vec!["zingo", "testnet", "v26.dat"],
+        vec!["zingo", "testnet", "v28.dat"],
+    ];
+    for path in &paths {
+        test_strict(path);
+    }
+}
@@ -0,0 +1,185 @@
+#![cfg(feature = "zingo")]
+
+use zmigrate::{
+    migrator::{Migrator, OutputSink},
+    progress::Progress,
+    zingo_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_path, temp_file};
+
+/// Every zingo fixture with its serialization version.
+const FIXTURES: &[(&[&str], u64)] = &[
+    (
+        &["zingo", "regtest", "aaaaaaaaaaaaaaaaaaaaaaaa-v26.dat"],
+        26,
+    ),
+    (
+        &[
+            "zingo",
+            "regtest",
+            "aadaalacaadaalacaadaalac-orch-and-sapling.dat",
+        ],
+        26,
+    ),
+    (
+        &["zingo", "regtest", "aadaalacaadaalacaadaalac-orch-only.dat"],
+        26,
+    ),
+    (&["zingo", "testnet", "v26.dat"], 26),
+    (
+        &["zingo", "regtest", "hmvasmuvwmssvichcarbpoct-v27.dat"],
+        27,
+    ),
+    // Despite its name, this wallet was written with version 27.
+    (
+        &["zingo", "mainnet", "vtfcorfbcbpctcfupmegmwbp-v28.dat"],
+        27,
+    ),
+    (&["zingo", "testnet", "v27.dat"], 27),
+    (
+        &[
+            "zingo",
+            "mainnet",
+            "hhcclaltpcckcsslpcnetblr-gf0aaf9347.dat",
+        ],
+        28,
+    ),
+    (&["zingo", "testnet", "G93738061a.dat"], 28),
+    (&["zingo", "testnet", "Gab72a38b.dat"], 28),
+    (&["zingo", "testnet", "v28.dat"], 28),
+    (
+        &["zingo", "mainnet", "hhcclaltpcckcsslpcnetblr-latest.dat"],
+        30,
+    ),
+    (
+        &["zingo", "testnet", "cbbhrwiilgbrababsshsmtpr-latest.dat"],
+        30,
+    ),
+    (&["zingo", "testnet", "glory_goddess.dat"], 30),
+    (&["zingo", "testnet", "latest.dat"], 30),
+];
+
+#[test]
+fn test_detect_versions() {
+    for (path, version) in FIXTURES {
+        let detected = zingo_cmd::wallet_version(&fixtures_path(path))
+            .unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+        assert_eq!(detected, *version, "{:?}", path);
+    }
+}
+
+fn test_dump_version(path_elements: &[&str], version: u64) {
+    assert_eq!(
+        zingo_cmd::wallet_version(&fixtures_path(path_elements)).unwrap(),
+        version
+    );
+    let dump = zingo_cmd::dump_wallet(&fixtures_path(path_elements))
+        .unwrap_or_else(|e| panic!("{:?}: {}", path_elements, e));
+    assert!(dump.contains(&format!("Version: {}", version)));
+    assert!(dump.lines().last().unwrap().contains("Success"));
+}
+
+#[test]
+fn test_in_memory_wallet() {
+    let (path, version) = FIXTURES[0];
+    let bytes = std::fs::read(fixtures_path(path)).unwrap();
+    assert_eq!(zingo_cmd::wallet_version_bytes(&bytes).unwrap(), version);
+    assert_eq!(
+        zingo_cmd::dump_wallet_bytes(&bytes, false).unwrap(),
+        zingo_cmd::dump_wallet(&fixtures_path(path)).unwrap()
+    );
+}
+
+#[test]
+fn test_version_26() {
+    test_dump_version(
+        &["zingo", "regtest", "aaaaaaaaaaaaaaaaaaaaaaaa-v26.dat"],
+        26,
+    );
+    test_dump_version(&["zingo", "testnet", "v26.dat"], 26);
+}
+
+#[test]
+fn test_version_27() {
+    test_dump_version(
+        &["zingo", "regtest", "hmvasmuvwmssvichcarbpoct-v27.dat"],
+        27,
+    );
+}
+
+#[test]
+fn test_version_28() {
+    test_dump_version(&["zingo", "testnet", "v28.dat"], 28);
+    test_dump_version(&["zingo", "testnet", "G93738061a.dat"], 28);
+}
+
+#[test]
+fn test_version_30() {
+    test_dump_version(&["zingo", "testnet", "latest.dat"], 30);
+    test_dump_version(
+        &["zingo", "mainnet", "hhcclaltpcckcsslpcnetblr-latest.dat"],
+        30,
+    );
+}
+
+#[test]
+fn test_unsupported_version() {
+    let mut data =
+        std::fs::read(fixtures_path(&["zingo", "testnet", "latest.dat"]))
+            .unwrap();
+    data[..8]
+        .copy_from_slice(&(zingo_cmd::MAX_ZINGO_VERSION + 1).to_le_bytes());
+    let path = temp_file("future-zingo.dat", &data);
+    let result = zingo_cmd::dump_wallet(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.starts_with(&format!(
+        "unsupported zingo wallet version {}",
+        zingo_cmd::MAX_ZINGO_VERSION + 1
+    )));
+}
+
+#[test]
+fn test_tested_versions_have_fixtures() {
+    let mut versions: Vec<u64> = FIXTURES.iter().map(|(_, v)| *v).collect();
+    versions.sort();
+    versions.dedup();
+    assert_eq!(versions, zingo_cmd::TESTED_ZINGO_VERSIONS);
+}
+
+#[test]
+fn test_untested_version_warns() {
+    let data =
+        std::fs::read(fixtures_path(&["zingo", "testnet", "latest.dat"]))
+            .unwrap();
+    assert_eq!(zingo_cmd::wallet_version_bytes(&data).unwrap(), 30);
+    // A real version 30 wallet, read as though version 30 had no fixture.
+    let dump = zingo_cmd::dump_wallet_bytes_tested(&data, false, &[26, 27, 28])
+        .unwrap();
+    assert!(dump.contains("Version 30 has not been tested"));
+    // With the real list of tested versions it reads without the warning.
+    let dump = zingo_cmd::dump_wallet_bytes(&data, false).unwrap();
+    assert!(!dump.contains("has not been tested"));
+}
+
+#[test]
+fn test_dump_rejects_compress_and_encrypt() {
+    let path = fixtures_path(&["zingo", "testnet", "latest.dat"]);
+    let compressed = Migrator::new("zingo", &path)
+        .to("dump")
+        .compress(true)
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet());
+    assert!(compressed.is_err());
+
+    let encrypted = Migrator::new("zingo", &path)
+        .to("dump")
+        .encrypt_with(|| Ok("password".to_string()))
+        .output(OutputSink::Writer(Box::new(std::io::sink())))
+        .run(&Progress::quiet());
+    assert!(encrypted.is_err());
+}